@@ -0,0 +1,349 @@
+use bevy::prelude::*;
+use rlappy_bev::sim::{self, GameConfig, BIRD_SIZE};
+use std::collections::VecDeque;
+
+use crate::party::Party;
+use crate::share::ActiveChallenge;
+use crate::{ActiveConfig, Bird, GameOverUi, MatchSeed, Pipe, PreviousX};
+
+/// Survival time, in seconds, the adjustments try to keep the player within.
+const TARGET_SURVIVAL: std::ops::RangeInclusive<f32> = 20.0..=60.0;
+/// Runs averaged when deciding the next adjustment.
+const HISTORY_LEN: usize = 3;
+/// Vertical clearance, in world units, below which passing a gap counts as a near miss.
+const NEAR_MISS_CLEARANCE: f32 = 12.0;
+
+const GAP_STEP: f32 = 10.0;
+const GAP_OFFSET_RANGE: std::ops::RangeInclusive<f32> = -40.0..=80.0;
+const SPEED_STEP: f32 = 0.05;
+const SPEED_SCALE_RANGE: std::ops::RangeInclusive<f32> = 0.7..=1.2;
+
+#[derive(Clone, Copy, Debug)]
+struct RunSummary {
+    seconds: f32,
+    near_misses: u32,
+}
+
+/// Nudges gap size and pipe speed between runs to keep new players in a target
+/// survival window. Present only with `--adaptive`, and never for `--ranked` runs.
+#[derive(Resource)]
+pub struct AdaptiveDifficulty {
+    base: GameConfig,
+    gap_offset: f32,
+    speed_scale: f32,
+    history: VecDeque<RunSummary>,
+    /// Whether the current run is adjusted and counted; off for challenges, LAN matches
+    /// and party turns, whose course has to match what the other players fly.
+    counting: bool,
+    ticks: u32,
+    near_misses: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Adjustment {
+    Easier,
+    Harder,
+    Unchanged,
+}
+
+impl AdaptiveDifficulty {
+    pub fn new(base: GameConfig) -> Self {
+        Self {
+            base,
+            gap_offset: 0.0,
+            speed_scale: 1.0,
+            history: VecDeque::with_capacity(HISTORY_LEN),
+            counting: false,
+            ticks: 0,
+            near_misses: 0,
+        }
+    }
+
+    fn config(&self) -> GameConfig {
+        GameConfig {
+            pipe_gap: self.base.pipe_gap + self.gap_offset,
+            pipe_speed: self.base.pipe_speed * self.speed_scale,
+            ..self.base
+        }
+    }
+
+    fn adjust(&mut self, summary: RunSummary) -> Adjustment {
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(summary);
+
+        let runs = self.history.len() as f32;
+        let average_seconds = self.history.iter().map(|run| run.seconds).sum::<f32>() / runs;
+        let near_misses_per_minute = self.history.iter().map(|run| run.near_misses).sum::<u32>()
+            as f32
+            / (self.history.iter().map(|run| run.seconds).sum::<f32>() / 60.0).max(1.0);
+
+        // Lots of narrow escapes mean the player is barely coping even if they survive
+        let struggling = average_seconds < *TARGET_SURVIVAL.start() || near_misses_per_minute > 6.0;
+        let cruising = average_seconds > *TARGET_SURVIVAL.end() && near_misses_per_minute < 2.0;
+
+        let (gap_step, speed_step, adjustment) = if struggling {
+            (GAP_STEP, -SPEED_STEP, Adjustment::Easier)
+        } else if cruising {
+            (-GAP_STEP, SPEED_STEP, Adjustment::Harder)
+        } else {
+            (0.0, 0.0, Adjustment::Unchanged)
+        };
+
+        self.gap_offset =
+            (self.gap_offset + gap_step).clamp(*GAP_OFFSET_RANGE.start(), *GAP_OFFSET_RANGE.end());
+        self.speed_scale = (self.speed_scale + speed_step)
+            .clamp(*SPEED_SCALE_RANGE.start(), *SPEED_SCALE_RANGE.end());
+        adjustment
+    }
+}
+
+/// Applies the current adjustment to the config used by the run that is starting, unless
+/// that run's config was set by a challenge code, a LAN host or a party.
+pub fn apply_adaptive_config(
+    mut adaptive: ResMut<AdaptiveDifficulty>,
+    mut config: ResMut<ActiveConfig>,
+    challenge: Option<Res<ActiveChallenge>>,
+    match_seed: Option<Res<MatchSeed>>,
+    party: Option<Res<Party>>,
+) {
+    adaptive.ticks = 0;
+    adaptive.near_misses = 0;
+    adaptive.counting = challenge.is_none() && match_seed.is_none() && party.is_none();
+    if adaptive.counting {
+        config.0 = adaptive.config();
+    }
+}
+
+/// Counts survived ticks and gaps passed with little clearance. Runs after `update_score`.
+pub fn track_run(
+    mut adaptive: ResMut<AdaptiveDifficulty>,
+    config: Res<ActiveConfig>,
+    bird_query: Query<&Transform, With<Bird>>,
+    pipe_query: Query<(&Transform, &Pipe, &PreviousX)>,
+) {
+    if !adaptive.counting {
+        return;
+    }
+    adaptive.ticks += 1;
+
+    for bird_transform in bird_query.iter() {
+        let bird_y = bird_transform.translation.y;
+        for (pipe_transform, pipe, previous) in pipe_query.iter() {
+            // Same test as `update_score`, counting each pair once via its top pipe
+            let passing = sim::passes_bird(previous.0, pipe_transform.translation.x);
+            if !passing || pipe_transform.translation.y < pipe.gap_y {
+                continue;
+            }
+
+            let half_gap = config.0.pipe_gap / 2.0 - BIRD_SIZE / 2.0;
+            let clearance = half_gap - (bird_y - pipe.gap_y).abs();
+            if clearance < NEAR_MISS_CLEARANCE {
+                adaptive.near_misses += 1;
+            }
+        }
+    }
+}
+
+/// Records the finished run, picks the next adjustment and shows it on the game over screen.
+pub fn finish_adaptive_run(
+    mut adaptive: ResMut<AdaptiveDifficulty>,
+    fixed_time: Res<Time<Fixed>>,
+    mut commands: Commands,
+) {
+    if !adaptive.counting {
+        return;
+    }
+    adaptive.counting = false;
+    let summary = RunSummary {
+        seconds: adaptive.ticks as f32 * fixed_time.timestep().as_secs_f32(),
+        near_misses: adaptive.near_misses,
+    };
+    let adjustment = adaptive.adjust(summary);
+
+    let next = adaptive.config();
+    let verdict = match adjustment {
+        Adjustment::Easier => "easing off",
+        Adjustment::Harder => "stepping up",
+        Adjustment::Unchanged => "holding steady",
+    };
+
+    commands.spawn((
+        TextBundle::from_section(
+            format!(
+                "Survived {:.0}s, {} near misses\nAdaptive difficulty {verdict}: gap {:.0} ({:+.0}), speed {:.0}%",
+                summary.seconds,
+                summary.near_misses,
+                next.pipe_gap,
+                adaptive.gap_offset,
+                adaptive.speed_scale * 100.0,
+            ),
+            TextStyle {
+                font_size: 20.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(340.0),
+            left: Val::Px(200.0),
+            ..default()
+        }),
+        GameOverUi,
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::share::{self, RunInputs};
+    use crate::{
+        run_setup, GameMode, GameSeed, GameState, PendingJump, PipeRng, PipeSpawnTimer, SeedConfig,
+    };
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::state::app::StatesPlugin;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use rlappy_bev::challenge::{Challenge, ChallengeMode};
+
+    /// An app that runs `run_setup` on the way from the menu into a run, with adaptive
+    /// difficulty on and already eased off by a short first run.
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin))
+            .init_state::<GameState>()
+            .insert_resource(ActiveConfig(GameConfig::default()))
+            .insert_resource(PipeSpawnTimer(Timer::new(
+                GameConfig::default().spawn_interval(),
+                TimerMode::Repeating,
+            )))
+            .insert_resource(PipeRng(StdRng::seed_from_u64(0)))
+            .init_resource::<SeedConfig>()
+            .init_resource::<GameSeed>()
+            .init_resource::<PendingJump>()
+            .init_resource::<RunInputs>()
+            .init_resource::<GameMode>()
+            .add_systems(
+                OnTransition {
+                    exited: GameState::Menu,
+                    entered: GameState::Playing,
+                },
+                run_setup(),
+            );
+        let mut adaptive = AdaptiveDifficulty::new(GameConfig::default());
+        adaptive.adjust(RunSummary {
+            seconds: 5.0,
+            near_misses: 0,
+        });
+        app.insert_resource(adaptive);
+        app.update();
+        app
+    }
+
+    fn start_run(app: &mut App) {
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Playing);
+        app.update();
+    }
+
+    #[test]
+    fn solo_runs_get_the_adjusted_config() {
+        let mut app = app();
+        start_run(&mut app);
+        let config = app.world().resource::<ActiveConfig>().0;
+        assert_eq!(config.pipe_gap, GameConfig::default().pipe_gap + GAP_STEP);
+    }
+
+    #[test]
+    fn challenge_config_survives_run_setup() {
+        let mut app = app();
+        let config = GameConfig {
+            pipe_gap: 150.0,
+            pipe_speed: 260.0,
+            ..GameConfig::default()
+        };
+        let code = Challenge::new(42, ChallengeMode::Solo, 7, config).encode();
+        let challenge = Challenge::decode(&code).unwrap();
+        app.world_mut().run_system_once(
+            move |mut commands: Commands,
+                  mut active: ResMut<ActiveConfig>,
+                  mut mode: ResMut<GameMode>| {
+                share::accept_challenge(&mut commands, &mut active, &mut mode, challenge.clone());
+            },
+        );
+        start_run(&mut app);
+
+        assert_eq!(app.world().resource::<ActiveConfig>().0, config);
+        assert_eq!(app.world().resource::<GameSeed>().0, 42);
+        assert!(!app.world().resource::<AdaptiveDifficulty>().counting);
+    }
+
+    fn run(seconds: f32, near_misses: u32) -> RunSummary {
+        RunSummary {
+            seconds,
+            near_misses,
+        }
+    }
+
+    #[test]
+    fn short_runs_ease_off_and_long_calm_runs_step_up() {
+        let base = GameConfig::default();
+        let mut adaptive = AdaptiveDifficulty::new(base);
+        assert_eq!(adaptive.adjust(run(8.0, 0)), Adjustment::Easier);
+        let easier = adaptive.config();
+        assert_eq!(easier.pipe_gap, base.pipe_gap + GAP_STEP);
+        assert!(easier.pipe_speed < base.pipe_speed);
+
+        let mut adaptive = AdaptiveDifficulty::new(base);
+        assert_eq!(adaptive.adjust(run(90.0, 1)), Adjustment::Harder);
+        let harder = adaptive.config();
+        assert_eq!(harder.pipe_gap, base.pipe_gap - GAP_STEP);
+        assert!(harder.pipe_speed > base.pipe_speed);
+
+        let mut adaptive = AdaptiveDifficulty::new(base);
+        assert_eq!(adaptive.adjust(run(40.0, 2)), Adjustment::Unchanged);
+        assert_eq!(adaptive.config(), base);
+    }
+
+    #[test]
+    fn narrow_escapes_count_as_struggling() {
+        let mut adaptive = AdaptiveDifficulty::new(GameConfig::default());
+        // Survived long enough, but with ten near misses a minute
+        assert_eq!(adaptive.adjust(run(90.0, 15)), Adjustment::Easier);
+    }
+
+    #[test]
+    fn decisions_average_the_last_few_runs() {
+        let mut adaptive = AdaptiveDifficulty::new(GameConfig::default());
+        adaptive.adjust(run(100.0, 0));
+        // One bad run among good ones averages out to the target window
+        assert_eq!(adaptive.adjust(run(5.0, 0)), Adjustment::Unchanged);
+        for _ in 0..HISTORY_LEN {
+            adaptive.adjust(run(5.0, 0));
+        }
+        // The good run has dropped out of the history by now
+        assert_eq!(adaptive.history.len(), HISTORY_LEN);
+        assert_eq!(adaptive.adjust(run(5.0, 0)), Adjustment::Easier);
+    }
+
+    #[test]
+    fn adjustments_stay_within_their_ranges() {
+        let base = GameConfig::default();
+        let mut adaptive = AdaptiveDifficulty::new(base);
+        for _ in 0..50 {
+            adaptive.adjust(run(1.0, 0));
+        }
+        assert_eq!(adaptive.gap_offset, *GAP_OFFSET_RANGE.end());
+        assert_eq!(adaptive.speed_scale, *SPEED_SCALE_RANGE.start());
+
+        for _ in 0..50 {
+            adaptive.adjust(run(600.0, 0));
+        }
+        assert_eq!(adaptive.gap_offset, *GAP_OFFSET_RANGE.start());
+        assert_eq!(adaptive.speed_scale, *SPEED_SCALE_RANGE.end());
+        assert_eq!(adaptive.config().validate(), Ok(()));
+    }
+}
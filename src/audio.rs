@@ -0,0 +1,278 @@
+use bevy::prelude::*;
+
+use crate::input::{Action, Actions};
+use crate::settings::Settings;
+#[cfg(feature = "audio")]
+use crate::settings_menu::SettingsFromPause;
+#[cfg(feature = "audio")]
+use crate::{input::ButtonFocus, GameDifficulty, GameEvent, GameState};
+#[cfg(feature = "audio")]
+use bevy::audio::{Decodable, Volume};
+#[cfg(feature = "audio")]
+use bevy::utils::{HashMap, HashSet};
+#[cfg(feature = "audio")]
+use rlappy_bev::music::Music;
+#[cfg(feature = "audio")]
+use rlappy_bev::synth::{self, Effect, SAMPLE_RATE};
+#[cfg(feature = "audio")]
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+#[cfg(feature = "audio")]
+use std::sync::Arc;
+
+/// Mute toggles wherever the key isn't being typed into a field.
+pub fn mute_input(actions: Res<Actions>, mut settings: ResMut<Settings>) {
+    if actions.just_pressed(Action::Mute) {
+        settings.volume.muted = !settings.volume.muted;
+        settings.save();
+    }
+}
+
+/// Synthesised sound effects, played when gameplay and UI events come in.
+#[cfg(feature = "audio")]
+#[derive(Resource)]
+pub struct Effects {
+    sources: HashMap<Effect, Handle<AudioSource>>,
+    /// Set once a sound failed to start, which means there is no audio device; Bevy keeps
+    /// such sounds around forever, so none are spawned after that.
+    silent: bool,
+}
+
+/// One playing effect, despawned by Bevy when it ends.
+#[cfg(feature = "audio")]
+#[derive(Component)]
+pub struct EffectSound;
+
+#[cfg(feature = "audio")]
+pub fn load_effects(mut commands: Commands, mut sources: ResMut<Assets<AudioSource>>) {
+    let sources = Effect::ALL
+        .into_iter()
+        .map(|effect| {
+            let bytes = synth::wav_bytes(&effect.render());
+            (
+                effect,
+                sources.add(AudioSource {
+                    bytes: bytes.into(),
+                }),
+            )
+        })
+        .collect();
+    commands.insert_resource(Effects {
+        sources,
+        silent: false,
+    });
+}
+
+/// Picks the effects for this frame's events, once each however many birds caused them.
+#[cfg(feature = "audio")]
+pub fn play_effects(
+    mut events: EventReader<GameEvent>,
+    mut transitions: EventReader<StateTransitionEvent<GameState>>,
+    pressed: Query<&Interaction, (Changed<Interaction>, With<Button>)>,
+    focus: Res<ButtonFocus>,
+    actions: Res<Actions>,
+    state: Res<State<GameState>>,
+    settings: Res<Settings>,
+    effects: Res<Effects>,
+    mut commands: Commands,
+) {
+    let mut playing = HashSet::new();
+    for event in events.read() {
+        playing.insert(match event {
+            GameEvent::Flapped => Effect::Flap,
+            GameEvent::Scored => Effect::Score,
+            GameEvent::Crashed => Effect::Hit,
+        });
+    }
+    for transition in transitions.read() {
+        if transition.entered == Some(GameState::GameOver) {
+            playing.insert(Effect::Die);
+        }
+    }
+    let on_buttons = matches!(
+        state.get(),
+        GameState::Menu | GameState::Controls | GameState::Settings
+    );
+    if pressed
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+        || (focus.is_changed() && !focus.is_added())
+        || (on_buttons && actions.just_pressed(Action::Confirm))
+    {
+        playing.insert(Effect::Click);
+    }
+
+    let gain = settings.volume.effects_gain();
+    if effects.silent || gain <= 0.0 {
+        return;
+    }
+    for effect in playing {
+        commands.spawn((
+            AudioBundle {
+                source: effects.sources[&effect].clone(),
+                settings: PlaybackSettings::DESPAWN.with_volume(Volume::new(gain)),
+            },
+            EffectSound,
+        ));
+    }
+}
+
+/// Stops spawning effects once one has gone a frame without getting a sink.
+#[cfg(feature = "audio")]
+pub fn check_output(
+    mut commands: Commands,
+    mut effects: ResMut<Effects>,
+    sounds: Query<(Entity, Ref<EffectSound>), Without<AudioSink>>,
+) {
+    for (entity, sound) in sounds.iter() {
+        if !sound.is_added() {
+            effects.silent = true;
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// What the game wants from the music, read by the audio thread as it generates it.
+#[cfg(feature = "audio")]
+struct MusicControl {
+    /// `f32` bits.
+    speed: AtomicU32,
+    /// `f32` bits.
+    gain: AtomicU32,
+    ducked: AtomicBool,
+    /// Bumped once per game-over sting.
+    stings: AtomicU32,
+}
+
+/// The soundtrack as an audio asset; it never ends, so it is spawned once and steered.
+#[cfg(feature = "audio")]
+#[derive(Asset, TypePath)]
+pub struct MusicSource {
+    control: Arc<MusicControl>,
+}
+
+#[cfg(feature = "audio")]
+impl Decodable for MusicSource {
+    type DecoderItem = f32;
+    type Decoder = MusicDecoder;
+
+    fn decoder(&self) -> MusicDecoder {
+        MusicDecoder {
+            music: Music::default(),
+            control: self.control.clone(),
+            stings: self.control.stings.load(Ordering::Relaxed),
+            gain: 0.0,
+            until_poll: 0,
+        }
+    }
+}
+
+/// Samples between reads of [`MusicControl`], about 12 ms.
+#[cfg(feature = "audio")]
+const CONTROL_INTERVAL: usize = 512;
+
+#[cfg(feature = "audio")]
+pub struct MusicDecoder {
+    music: Music,
+    control: Arc<MusicControl>,
+    stings: u32,
+    gain: f32,
+    until_poll: usize,
+}
+
+#[cfg(feature = "audio")]
+impl Iterator for MusicDecoder {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.until_poll == 0 {
+            let control = &self.control;
+            self.music
+                .set_speed(f32::from_bits(control.speed.load(Ordering::Relaxed)));
+            self.music
+                .set_ducked(control.ducked.load(Ordering::Relaxed));
+            self.gain = f32::from_bits(control.gain.load(Ordering::Relaxed));
+            let stings = control.stings.load(Ordering::Relaxed);
+            if stings != self.stings {
+                self.stings = stings;
+                self.music.sting();
+            }
+            self.until_poll = CONTROL_INTERVAL;
+        }
+        self.until_poll -= 1;
+        self.music.next().map(|sample| sample * self.gain)
+    }
+}
+
+#[cfg(feature = "audio")]
+impl bevy::audio::Source for MusicDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<std::time::Duration> {
+        None
+    }
+}
+
+/// Handle on the playing soundtrack.
+#[cfg(feature = "audio")]
+#[derive(Resource)]
+pub struct MusicPlayer {
+    control: Arc<MusicControl>,
+}
+
+#[cfg(feature = "audio")]
+pub fn start_music(
+    mut commands: Commands,
+    mut sources: ResMut<Assets<MusicSource>>,
+    settings: Res<Settings>,
+) {
+    let control = Arc::new(MusicControl {
+        speed: AtomicU32::new(1f32.to_bits()),
+        gain: AtomicU32::new(settings.volume.music_gain().to_bits()),
+        ducked: AtomicBool::new(false),
+        stings: AtomicU32::new(0),
+    });
+    commands.spawn(AudioSourceBundle {
+        source: sources.add(MusicSource {
+            control: control.clone(),
+        }),
+        // Not LOOP, which would buffer the endless stream to replay it
+        settings: PlaybackSettings::ONCE,
+    });
+    commands.insert_resource(MusicPlayer { control });
+}
+
+/// Speeds the music up with the pipes, ducks it while paused and stings on game over.
+#[cfg(feature = "audio")]
+pub fn steer_music(
+    player: Res<MusicPlayer>,
+    difficulty: Res<GameDifficulty>,
+    state: Res<State<GameState>>,
+    from_pause: Option<Res<SettingsFromPause>>,
+    mut transitions: EventReader<StateTransitionEvent<GameState>>,
+    settings: Res<Settings>,
+) {
+    let control = &player.control;
+    control
+        .speed
+        .store(difficulty.speed_multiplier.to_bits(), Ordering::Relaxed);
+    let paused = *state.get() == GameState::Paused || from_pause.is_some();
+    control.ducked.store(paused, Ordering::Relaxed);
+    control
+        .gain
+        .store(settings.volume.music_gain().to_bits(), Ordering::Relaxed);
+    for transition in transitions.read() {
+        if transition.entered == Some(GameState::GameOver) {
+            control.stings.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
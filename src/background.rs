@@ -0,0 +1,223 @@
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rlappy_bev::sim::{GROUND_HEIGHT, GROUND_THICKNESS, WINDOW_HEIGHT};
+use std::f32::consts::TAU;
+
+use crate::settings::Settings;
+use crate::{ActiveConfig, GameDifficulty};
+
+/// Width of one tile of every layer; the content repeats every tile, so two side by side
+/// cover the window at any scroll offset.
+const TILE_WIDTH: f32 = 1024.0;
+/// Bottom edge of the layers that stand on the ground, hidden behind it.
+const FLOOR: f32 = GROUND_HEIGHT - GROUND_THICKNESS / 2.0;
+
+/// Scenery behind the course, back to front. Each is a white silhouette tinted by the
+/// palette, which can also leave it out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layer {
+    /// Fades from the clear colour at the top to a horizon colour.
+    Sky,
+    Clouds,
+    Hills,
+    City,
+}
+
+impl Layer {
+    const ALL: [Layer; 4] = [Layer::Sky, Layer::Clouds, Layer::Hills, Layer::City];
+
+    /// Fraction of the pipe speed it scrolls at, so nearer layers move faster.
+    fn speed(self) -> f32 {
+        match self {
+            Layer::Sky => 0.0,
+            Layer::Clouds => 0.1,
+            Layer::Hills => 0.25,
+            Layer::City => 0.5,
+        }
+    }
+
+    /// Size and centre height in world units.
+    fn size(self) -> (UVec2, f32) {
+        let (height, y) = match self {
+            Layer::Sky => (WINDOW_HEIGHT, 0.0),
+            Layer::Clouds => (200.0, 160.0),
+            Layer::Hills => (200.0, FLOOR + 100.0),
+            Layer::City => (240.0, FLOOR + 120.0),
+        };
+        (UVec2::new(TILE_WIDTH as u32, height as u32), y)
+    }
+
+    /// Coverage of each pixel, row 0 at the top; the left and right edges line up.
+    fn render(self) -> (UVec2, Vec<f32>) {
+        let (size, _) = self.size();
+        let (width, height) = (size.x as usize, size.y as usize);
+        let mut alpha = vec![0.0; width * height];
+        // The same scenery every time
+        let mut rng = StdRng::seed_from_u64(self as u64);
+        match self {
+            Layer::Sky => {
+                for row in 0..height {
+                    let t = row as f32 / height as f32;
+                    alpha[row * width..(row + 1) * width].fill(t * t);
+                }
+            }
+            Layer::Clouds => {
+                for _ in 0..7 {
+                    let centre = Vec2::new(
+                        rng.gen_range(0.0..width as f32),
+                        rng.gen_range(40.0..height as f32 - 40.0),
+                    );
+                    // A few overlapping puffs per cloud
+                    for _ in 0..4 {
+                        let puff = centre
+                            + Vec2::new(rng.gen_range(-50.0..50.0), rng.gen_range(-8.0..8.0));
+                        let radius =
+                            Vec2::new(rng.gen_range(30.0..55.0), rng.gen_range(14.0..24.0));
+                        let rows = (puff.y - radius.y).max(0.0) as usize
+                            ..((puff.y + radius.y + 1.0) as usize).min(height);
+                        for row in rows {
+                            for x in 0..width {
+                                // Nearest copy, so clouds wrap across the tile edge
+                                let dx = (x as f32 - puff.x + width as f32 / 2.0)
+                                    .rem_euclid(width as f32)
+                                    - width as f32 / 2.0;
+                                let d = Vec2::new(dx, row as f32 - puff.y) / radius;
+                                let cover = ((1.0 - d.length()) * radius.y).clamp(0.0, 1.0);
+                                let pixel = &mut alpha[row * width + x];
+                                *pixel = pixel.max(cover);
+                            }
+                        }
+                    }
+                }
+            }
+            Layer::Hills => {
+                // Whole numbers of waves per tile, so the ridge repeats exactly
+                let waves: Vec<(f32, f32, f32)> = [(2.0, 35.0), (3.0, 20.0), (7.0, 8.0)]
+                    .into_iter()
+                    .map(|(count, amplitude)| (count, amplitude, rng.gen_range(0.0..TAU)))
+                    .collect();
+                for x in 0..width {
+                    let t = x as f32 / width as f32;
+                    let ridge = height as f32 * 0.6
+                        + waves
+                            .iter()
+                            .map(|(count, amplitude, phase)| {
+                                amplitude * (TAU * count * t + phase).sin()
+                            })
+                            .sum::<f32>();
+                    fill_column(&mut alpha, width, height, x, ridge);
+                }
+            }
+            Layer::City => {
+                let mut x = 0;
+                while x < width {
+                    // The last building stops at the edge
+                    let building = rng.gen_range(30..80).min(width - x);
+                    let top = rng.gen_range(0.2..0.65) * height as f32;
+                    let gap = building.min(4);
+                    for column in x..x + building - gap {
+                        fill_column(&mut alpha, width, height, column, top);
+                    }
+                    x += building;
+                }
+            }
+        }
+        (size, alpha)
+    }
+
+    fn image(self) -> Image {
+        let (size, alpha) = self.render();
+        let data = alpha
+            .into_iter()
+            .flat_map(|alpha| [255, 255, 255, (alpha * 255.0) as u8])
+            .collect();
+        Image::new(
+            Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::RENDER_WORLD,
+        )
+    }
+}
+
+/// Fills a column from `height` pixels above the bottom up to the bottom edge, softening
+/// the top pixel.
+fn fill_column(alpha: &mut [f32], width: usize, rows: usize, x: usize, height: f32) {
+    for row in 0..rows {
+        let above_bottom = (rows - row) as f32;
+        alpha[row * width + x] = (height - above_bottom + 1.0).clamp(0.0, 1.0);
+    }
+}
+
+/// One of the two side-by-side copies of a layer.
+#[derive(Component)]
+pub struct BackgroundTile(Layer);
+
+/// Spawns every layer once; they stay for the whole session and are recoloured or hidden
+/// when the palette changes.
+pub fn spawn_background(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    for (index, layer) in Layer::ALL.into_iter().enumerate() {
+        let texture = images.add(layer.image());
+        let (size, y) = layer.size();
+        for tile in 0..2 {
+            commands.spawn((
+                SpriteBundle {
+                    texture: texture.clone(),
+                    // Behind the pipes, ground and birds
+                    transform: Transform::from_xyz(
+                        tile as f32 * TILE_WIDTH,
+                        y,
+                        -10.0 + index as f32,
+                    ),
+                    sprite: Sprite {
+                        custom_size: Some(size.as_vec2()),
+                        ..default()
+                    },
+                    ..default()
+                },
+                BackgroundTile(layer),
+            ));
+        }
+    }
+}
+
+/// Moves each layer at its fraction of the pipes' current speed, wrapping tiles that
+/// have scrolled off the left round to the right.
+pub fn scroll_background(
+    time: Res<Time>,
+    config: Res<ActiveConfig>,
+    difficulty: Res<GameDifficulty>,
+    mut tiles: Query<(&mut Transform, &BackgroundTile)>,
+) {
+    let pipe_speed = config.0.pipe_speed * difficulty.speed_multiplier;
+    for (mut transform, tile) in tiles.iter_mut() {
+        let x = &mut transform.translation.x;
+        *x -= tile.0.speed() * pipe_speed * time.delta_seconds();
+        if *x <= -TILE_WIDTH {
+            *x += 2.0 * TILE_WIDTH;
+        }
+    }
+}
+
+pub fn apply_palette(
+    settings: Res<Settings>,
+    mut tiles: Query<(&mut Sprite, &mut Visibility, &BackgroundTile)>,
+) {
+    for (mut sprite, mut visibility, tile) in tiles.iter_mut() {
+        match settings.palette.layer(tile.0) {
+            Some(color) => {
+                sprite.color = color;
+                *visibility = Visibility::Inherited;
+            }
+            None => *visibility = Visibility::Hidden,
+        }
+    }
+}
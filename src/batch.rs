@@ -0,0 +1,164 @@
+//! Runs many headless bot games and summarises the results.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::bot::Bot;
+use crate::sim::{DeathCause, GameConfig, Simulation, TICK_RATE};
+
+/// Mixed into the course seed so the bot's noise is not correlated with the pipes.
+const BOT_SEED_SALT: u64 = 0x9e37_79b9_7f4a_7c15;
+
+#[derive(Clone, Copy, Debug)]
+pub struct BatchOptions {
+    pub games: u32,
+    pub skill: f32,
+    /// Game `i` uses seed `base_seed + i`.
+    pub base_seed: u64,
+    /// Games still alive after this many ticks are stopped and counted as timeouts.
+    pub max_ticks: u32,
+    pub threads: usize,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            games: 1000,
+            skill: 0.8,
+            base_seed: 0,
+            max_ticks: (10.0 * 60.0 * TICK_RATE) as u32, // Ten minutes of play
+            threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct GameResult {
+    pub seed: u64,
+    pub score: u32,
+    pub ticks: u32,
+    /// `None` when the game hit `max_ticks`.
+    pub death: Option<DeathCause>,
+}
+
+/// Plays one game with a bot whose own randomness is derived from the course seed.
+pub fn play_game(seed: u64, config: GameConfig, skill: f32, max_ticks: u32) -> GameResult {
+    let mut sim = Simulation::with_config(seed, config);
+    let mut bot = Bot::new(skill, seed ^ BOT_SEED_SALT);
+    while !sim.is_over() && sim.tick < max_ticks {
+        let jump = bot.decide(sim.observation());
+        sim.step(jump);
+    }
+    GameResult {
+        seed,
+        score: sim.score,
+        ticks: sim.tick,
+        death: sim.death,
+    }
+}
+
+/// Plays `options.games` games split across `options.threads` threads, in seed order.
+pub fn run_batch(config: GameConfig, options: &BatchOptions) -> Vec<GameResult> {
+    let threads = options.threads.clamp(1, options.games.max(1) as usize);
+    let per_thread = options.games.div_ceil(threads as u32);
+
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads as u32)
+            .map(|thread| {
+                let start = thread * per_thread;
+                let end = (start + per_thread).min(options.games);
+                scope.spawn(move || {
+                    (start..end)
+                        .map(|i| {
+                            let seed = options.base_seed.wrapping_add(i as u64);
+                            play_game(seed, config, options.skill, options.max_ticks)
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("simulation thread panicked"))
+            .collect()
+    })
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ScoreStats {
+    pub count: usize,
+    pub mean: f64,
+    pub min: u32,
+    pub max: u32,
+    pub median: u32,
+    pub p10: u32,
+    pub p25: u32,
+    pub p75: u32,
+    pub p90: u32,
+    pub p99: u32,
+}
+
+impl ScoreStats {
+    pub fn from_scores(scores: &[u32]) -> Self {
+        if scores.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = scores.to_vec();
+        sorted.sort_unstable();
+        let total: u64 = sorted.iter().map(|&score| score as u64).sum();
+
+        Self {
+            count: sorted.len(),
+            mean: total as f64 / sorted.len() as f64,
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            median: percentile(&sorted, 50.0),
+            p10: percentile(&sorted, 10.0),
+            p25: percentile(&sorted, 25.0),
+            p75: percentile(&sorted, 75.0),
+            p90: percentile(&sorted, 90.0),
+            p99: percentile(&sorted, 99.0),
+        }
+    }
+}
+
+/// Nearest-rank percentile of an already sorted, non-empty slice.
+pub fn percentile(sorted: &[u32], p: f64) -> u32 {
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Splits `0..=max` into at most `buckets` equal-width score ranges and counts each.
+pub fn histogram(scores: &[u32], buckets: u32) -> Vec<(Range<u32>, usize)> {
+    let Some(&max) = scores.iter().max() else {
+        return Vec::new();
+    };
+    let width = (max + 1).div_ceil(buckets.max(1));
+    let mut counts = vec![0; (max / width + 1) as usize];
+    for &score in scores {
+        counts[(score / width) as usize] += 1;
+    }
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(i, count)| {
+            let start = i as u32 * width;
+            (start..start + width, count)
+        })
+        .collect()
+}
+
+/// Games per outcome, most common first. `None` counts games that hit the tick limit.
+pub fn death_counts(results: &[GameResult]) -> Vec<(Option<DeathCause>, usize)> {
+    let mut counts: HashMap<Option<DeathCause>, usize> = HashMap::new();
+    for result in results {
+        *counts.entry(result.death).or_default() += 1;
+    }
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|a, b| {
+        b.1.cmp(&a.1)
+            .then_with(|| a.0.map(DeathCause::label).cmp(&b.0.map(DeathCause::label)))
+    });
+    counts
+}
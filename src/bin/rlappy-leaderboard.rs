@@ -0,0 +1,47 @@
+//! Leaderboard server: ranks scores submitted with a replay, after re-simulating the replay
+//! to make sure it reaches the score it claims.
+//!
+//! Usage: rlappy-leaderboard [--port <N>] [--bind <addr>] [--data <file>]
+//!
+//! `--data` keeps the board in a JSON file (default `leaderboard.json`).
+
+use rlappy_bev::leaderboard::{self, Board};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let mut port = 8470;
+    let mut bind = "0.0.0.0".to_string();
+    let mut data = "leaderboard.json".to_string();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_default();
+        match arg.as_str() {
+            "--port" => port = value().parse().unwrap_or(port),
+            "--bind" => bind = value(),
+            "--data" => data = value(),
+            _ => eprintln!("Ignoring unknown argument: {arg}"),
+        }
+    }
+
+    let board = match Board::open(&data) {
+        Ok(board) => board,
+        Err(err) => {
+            eprintln!("Failed to load {data}: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let listener = match std::net::TcpListener::bind((bind.as_str(), port)) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("Failed to listen on {bind}:{port}: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    println!(
+        "Leaderboard on http://{bind}:{port}/scores ({} entries in {data})",
+        board.entries().len()
+    );
+    leaderboard::serve(listener, std::sync::Arc::new(std::sync::Mutex::new(board)));
+    ExitCode::SUCCESS
+}
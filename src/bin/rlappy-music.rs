@@ -0,0 +1,83 @@
+//! Renders the game's music to a WAV file, to listen to it or check it without an audio
+//! device.
+//!
+//! Usage: rlappy-music [--out <file>] [--seconds <N>] [--speed <from[:to]>]
+//!                     [--pause <start:length>] [--sting <at>]
+//!
+//! The speed multiplier ramps linearly from `from` to `to` over the track, the music ducks
+//! while the pause window is open, and the game-over sting plays at `at` seconds.
+
+use rlappy_bev::music::Music;
+use rlappy_bev::synth::{self, SAMPLE_RATE};
+use std::process::ExitCode;
+
+/// Samples between control updates, about as often as the game steers it.
+const CONTROL_INTERVAL: usize = 512;
+
+fn parse_pair(value: &str) -> Option<(f32, f32)> {
+    let (a, b) = value.split_once(':')?;
+    Some((a.parse().ok()?, b.parse().ok()?))
+}
+
+fn main() -> ExitCode {
+    let mut out = "./music.wav".to_string();
+    let mut seconds = 30.0f32;
+    let mut speed = (1.0f32, 2.5f32);
+    let mut pause = None;
+    let mut sting = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_default();
+        match arg.as_str() {
+            "--out" => out = value,
+            "--seconds" => seconds = value.parse().unwrap_or(seconds),
+            "--speed" => {
+                speed = parse_pair(&value)
+                    .or_else(|| value.parse().ok().map(|speed| (speed, speed)))
+                    .unwrap_or(speed)
+            }
+            "--pause" => pause = parse_pair(&value),
+            "--sting" => sting = value.parse::<f32>().ok(),
+            _ => eprintln!("Ignoring unknown argument: {arg}"),
+        }
+    }
+    if seconds <= 0.0 {
+        eprintln!("Usage: rlappy-music [--out music.wav] [--seconds 30] [--speed 1:2.5] [--pause 10:4] [--sting 25]");
+        return ExitCode::FAILURE;
+    }
+
+    let total = (seconds * SAMPLE_RATE as f32) as usize;
+    let at = |seconds: f32| (seconds * SAMPLE_RATE as f32) as usize;
+    let mut music = Music::default();
+    let mut samples = Vec::with_capacity(total);
+    while samples.len() < total {
+        let i = samples.len();
+        if i % CONTROL_INTERVAL == 0 {
+            let t = i as f32 / total as f32;
+            music.set_speed(speed.0 + (speed.1 - speed.0) * t);
+            music.set_ducked(
+                pause.is_some_and(|(start, length)| (at(start)..at(start + length)).contains(&i)),
+            );
+        }
+        if sting.is_some_and(|sting| i == at(sting)) {
+            music.sting();
+        }
+        samples.extend(music.next());
+    }
+
+    if let Err(err) = synth::write_wav(&out, &samples) {
+        eprintln!("Failed to write {out}: {err}");
+        return ExitCode::FAILURE;
+    }
+    let mut tempo = |multiplier: f32| {
+        music.set_speed(multiplier);
+        music.tempo()
+    };
+    println!(
+        "Wrote {seconds:.1} s to {out}, {:.0} to {:.0} BPM",
+        tempo(speed.0),
+        tempo(speed.1)
+    );
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,254 @@
+//! Plays bot-vs-bot netplay matches and checks that both peers agree with an offline
+//! re-simulation of the inputs they exchanged.
+//!
+//! Usage: rlappy-netplay [--seed <N>] [--ticks <N>] [--skill <0..1>] [--memory]
+//!                       [--loss <0..1>] [--latency <ms>] [--jitter <ms>]
+//!        rlappy-netplay --host <port> [...]    one peer, for a match between two processes
+//!        rlappy-netplay --join <addr> [...]
+//!
+//! Without `--host` or `--join` both peers run in this process, over UDP on localhost or
+//! over an in-memory link with `--memory`. Loss, latency and jitter apply to every
+//! datagram each peer sends.
+
+use rlappy_bev::bot::Bot;
+use rlappy_bev::netplay::{
+    memory_pair, resimulate, Link, LinkConditions, LossyLink, MatchState, Peer, Role, SessionStats,
+    UdpLink,
+};
+use rlappy_bev::sim::{tick_duration, GameConfig};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+/// How long a peer keeps sending after its match is decided, so the other side can
+/// confirm it too.
+const LINGER: Duration = Duration::from_secs(1);
+/// A peer gives up after hearing nothing for this long.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy)]
+struct Options {
+    ticks: u32,
+    skill: f32,
+    conditions: LinkConditions,
+}
+
+struct Outcome {
+    local: usize,
+    seed: u64,
+    config: GameConfig,
+    /// Last state both inputs were known for, as computed with rollbacks during play.
+    confirmed: MatchState,
+    inputs: [Vec<bool>; 2],
+    stats: SessionStats,
+}
+
+impl Outcome {
+    /// The match re-simulated from scratch on the exchanged inputs, up to `ticks`.
+    fn referee(&self, ticks: u32) -> MatchState {
+        resimulate(
+            self.seed,
+            self.config,
+            [&self.inputs[0], &self.inputs[1]],
+            ticks,
+        )
+    }
+}
+
+fn main() -> ExitCode {
+    let mut seed = rand::random::<u64>();
+    let mut options = Options {
+        ticks: 1200,
+        skill: 0.9,
+        conditions: LinkConditions::default(),
+    };
+    let mut host = None;
+    let mut join = None;
+    let mut memory = false;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_default();
+        match arg.as_str() {
+            "--seed" => seed = value().parse().unwrap_or(seed),
+            "--ticks" => options.ticks = value().parse().unwrap_or(options.ticks),
+            "--skill" => options.skill = value().parse().unwrap_or(options.skill),
+            "--loss" => {
+                options.conditions.loss = value().parse().unwrap_or(options.conditions.loss)
+            }
+            "--latency" => {
+                options.conditions.latency = Duration::from_millis(value().parse().unwrap_or(0))
+            }
+            "--jitter" => {
+                options.conditions.jitter = Duration::from_millis(value().parse().unwrap_or(0))
+            }
+            "--host" => host = value().parse::<u16>().ok(),
+            "--join" => join = Some(value()),
+            "--memory" => memory = true,
+            _ => eprintln!("Ignoring unknown argument: {arg}"),
+        }
+    }
+    let role = Role::Host {
+        seed,
+        config: GameConfig::default(),
+    };
+
+    if let Some(port) = host {
+        let link = match UdpLink::host(port) {
+            Ok(link) => link,
+            Err(err) => {
+                eprintln!("Failed to listen on port {port}: {err}");
+                return ExitCode::FAILURE;
+            }
+        };
+        println!("Hosting seed {seed} on port {port}, waiting for a peer");
+        return report_single(play(link, role, options), options.ticks);
+    }
+    if let Some(addr) = join {
+        let link = match UdpLink::join(&addr) {
+            Ok(link) => link,
+            Err(err) => {
+                eprintln!("Failed to join {addr}: {err}");
+                return ExitCode::FAILURE;
+            }
+        };
+        println!("Joining {addr}");
+        return report_single(play(link, Role::Join, options), options.ticks);
+    }
+
+    let outcomes = if memory {
+        let (a, b) = memory_pair();
+        run_pair(a, b, role, options)
+    } else {
+        let links = UdpLink::host(0).and_then(|host| {
+            let port = host.local_addr()?.port();
+            Ok((host, UdpLink::join(("127.0.0.1", port))?))
+        });
+        match links {
+            Ok((a, b)) => run_pair(a, b, role, options),
+            Err(err) => {
+                eprintln!("Failed to open loopback sockets: {err}");
+                return ExitCode::FAILURE;
+            }
+        }
+    };
+
+    let [Some(a), Some(b)] = outcomes else {
+        eprintln!("A peer timed out");
+        return ExitCode::FAILURE;
+    };
+    let consistent = print_outcome(&a, options.ticks) & print_outcome(&b, options.ticks);
+
+    // Whichever peer confirmed less, the other must have reached the same state on the way
+    let common = a.confirmed.tick.min(b.confirmed.tick);
+    let agreed = a.referee(common).checksum() == b.referee(common).checksum();
+    println!(
+        "Common tick {common}: {}",
+        if agreed {
+            "same state"
+        } else {
+            "different states"
+        }
+    );
+    if consistent && agreed {
+        println!("Peers agree");
+        ExitCode::SUCCESS
+    } else {
+        eprintln!("Peers diverged");
+        ExitCode::FAILURE
+    }
+}
+
+fn run_pair<L: Link + Send + 'static>(
+    a: L,
+    b: L,
+    role: Role,
+    options: Options,
+) -> [Option<Outcome>; 2] {
+    let host = std::thread::spawn(move || play(a, role, options));
+    let joiner = std::thread::spawn(move || play(b, Role::Join, options));
+    [
+        host.join().expect("host thread panicked"),
+        joiner.join().expect("joining thread panicked"),
+    ]
+}
+
+/// Runs one bot peer at the game's tick rate until the match is decided or `options.ticks`
+/// have been confirmed.
+fn play<L: Link>(link: L, role: Role, options: Options) -> Option<Outcome> {
+    let salt = matches!(role, Role::Join) as u64;
+    let mut peer = Peer::new(LossyLink::new(link, options.conditions, salt), role);
+    let mut bot = None;
+    let mut finished_at = None;
+    let mut next_tick = Instant::now();
+    let started = Instant::now();
+
+    loop {
+        peer.poll();
+        if peer.silence().unwrap_or(started.elapsed()) > TIMEOUT {
+            return None;
+        }
+
+        if let Some(session) = peer.session_mut() {
+            let local = session.local();
+            let bot =
+                bot.get_or_insert_with(|| Bot::new(options.skill, session.seed() ^ local as u64));
+            let done = session.is_finished() || session.confirmed().tick >= options.ticks;
+            if !done {
+                let jump = bot.decide(session.state().players[local].observation());
+                session.advance(jump);
+            }
+            peer.send_inputs();
+
+            if done && finished_at.get_or_insert_with(Instant::now).elapsed() > LINGER {
+                break;
+            }
+        }
+
+        next_tick += tick_duration();
+        std::thread::sleep(next_tick.saturating_duration_since(Instant::now()));
+    }
+
+    let session = peer.session()?;
+    Some(Outcome {
+        local: session.local(),
+        seed: session.seed(),
+        config: *session.confirmed().players[0].config(),
+        confirmed: session.confirmed().clone(),
+        inputs: [session.inputs(0).to_vec(), session.inputs(1).to_vec()],
+        stats: session.stats,
+    })
+}
+
+/// Prints the peer's result at `ticks`, or where the match was decided if earlier, and
+/// returns whether its rolled-back state matches a clean re-simulation.
+fn print_outcome(outcome: &Outcome, ticks: u32) -> bool {
+    let consistent =
+        outcome.referee(outcome.confirmed.tick).checksum() == outcome.confirmed.checksum();
+    // Peers may confirm a few ticks past the limit, so compare them at the limit itself
+    let shown = outcome.referee(ticks.min(outcome.confirmed.tick));
+    let [p1, p2] = &shown.players;
+    println!(
+        "Player {}: tick {}  scores {} / {}  checksum {:016x} ({})  rollbacks {}  resimulated {}  stalls {}",
+        outcome.local + 1,
+        shown.tick,
+        p1.score,
+        p2.score,
+        shown.checksum(),
+        if consistent { "matches re-simulation" } else { "DOES NOT match re-simulation" },
+        outcome.stats.rollbacks,
+        outcome.stats.resimulated_ticks,
+        outcome.stats.stalls,
+    );
+    consistent
+}
+
+fn report_single(outcome: Option<Outcome>, ticks: u32) -> ExitCode {
+    match outcome {
+        Some(outcome) if print_outcome(&outcome, ticks) => ExitCode::SUCCESS,
+        Some(_) => ExitCode::FAILURE,
+        None => {
+            eprintln!("Timed out waiting for the peer");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Plays many headless bot games and prints the score distribution and death causes.
+//!
+//! Usage: rlappy-sim [--games <N>] [--skill <0..1>] [--config <file>] [--seed <N>]
+//!                   [--max-ticks <N>] [--threads <N>] [--buckets <N>]
+
+use rlappy_bev::batch::{death_counts, histogram, run_batch, BatchOptions, ScoreStats};
+use rlappy_bev::sim::GameConfig;
+use std::process::ExitCode;
+
+const BAR_WIDTH: usize = 50;
+
+fn main() -> ExitCode {
+    let mut options = BatchOptions::default();
+    let mut config = GameConfig::default();
+    let mut buckets = 20;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_default();
+        match arg.as_str() {
+            "--games" => options.games = value().parse().unwrap_or(options.games),
+            "--skill" => {
+                let text = value();
+                match text.parse::<f32>() {
+                    // Also turns away NaN, which fails every comparison
+                    Ok(skill) if (0.0..=1.0).contains(&skill) => options.skill = skill,
+                    _ => {
+                        eprintln!("Invalid --skill: {text} (expected a number from 0 to 1)");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+            "--seed" => options.base_seed = value().parse().unwrap_or(options.base_seed),
+            "--max-ticks" => options.max_ticks = value().parse().unwrap_or(options.max_ticks),
+            "--threads" => options.threads = value().parse().unwrap_or(options.threads),
+            "--buckets" => buckets = value().parse().unwrap_or(buckets),
+            "--config" => {
+                let path = value();
+                match GameConfig::load(&path) {
+                    Ok(loaded) => config = loaded,
+                    Err(err) => {
+                        eprintln!("Failed to load config {path}: {err}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+            _ => eprintln!("Ignoring unknown argument: {arg}"),
+        }
+    }
+
+    let results = run_batch(config, &options);
+    let scores: Vec<u32> = results.iter().map(|result| result.score).collect();
+    let stats = ScoreStats::from_scores(&scores);
+
+    println!(
+        "{} games, bot skill {:.2}, seeds {}..{}",
+        stats.count,
+        options.skill,
+        options.base_seed,
+        options.base_seed.wrapping_add(options.games as u64)
+    );
+    println!("{config:?}");
+    println!();
+    println!(
+        "Score  mean {:.2}  min {}  max {}",
+        stats.mean, stats.min, stats.max
+    );
+    println!(
+        "       p10 {}  p25 {}  median {}  p75 {}  p90 {}  p99 {}",
+        stats.p10, stats.p25, stats.median, stats.p75, stats.p90, stats.p99
+    );
+
+    println!();
+    println!("Histogram");
+    let bins = histogram(&scores, buckets);
+    let tallest = bins
+        .iter()
+        .map(|(_, count)| *count)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    for (range, count) in bins {
+        let bar = "#".repeat(count * BAR_WIDTH / tallest);
+        println!(
+            "{:>4}-{:<4} {:>6}  {bar}",
+            range.start,
+            range.end - 1,
+            count
+        );
+    }
+
+    println!();
+    println!("Outcomes");
+    for (death, count) in death_counts(&results) {
+        let label = death.map_or("time limit", |cause| cause.label());
+        println!(
+            "  {label:<12} {count:>6}  ({:.1}%)",
+            count as f64 * 100.0 / results.len().max(1) as f64
+        );
+    }
+
+    ExitCode::SUCCESS
+}
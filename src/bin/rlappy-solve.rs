@@ -0,0 +1,165 @@
+//! Searches for a jump schedule that survives a number of gaps on a given seed.
+//!
+//! Usage: rlappy-solve --seed <N> [--gaps <N>] [--config <file>] [--resolution <units>]
+//!                     [--exact] [--max-ticks <N>] [--max-states <N>] [--out <file>]
+//!
+//! The search merges states into height buckets by default. When that finds no schedule it
+//! is repeated without merging, which either finds one or proves the seed impossible.
+
+use rlappy_bev::sim::GameConfig;
+use rlappy_bev::solver::{solve, Solution, SolverOptions};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let mut seed = None;
+    let mut gaps = 10;
+    let mut config = GameConfig::default();
+    let mut resolution = None;
+    let mut exact = false;
+    let mut max_ticks = None;
+    let mut max_states = None;
+    let mut out = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_default();
+        match arg.as_str() {
+            "--seed" => seed = value().parse().ok(),
+            "--gaps" => gaps = value().parse().unwrap_or(gaps),
+            "--config" => {
+                let path = value();
+                match GameConfig::load(&path) {
+                    Ok(loaded) => config = loaded,
+                    Err(err) => {
+                        eprintln!("Failed to load config {path}: {err}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+            "--resolution" => {
+                let text = value();
+                match text.parse::<f32>() {
+                    Ok(value) => resolution = Some(value),
+                    Err(_) => {
+                        eprintln!("Invalid --resolution: {text}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+            "--exact" => exact = true,
+            "--max-ticks" => max_ticks = value().parse().ok(),
+            "--max-states" => max_states = value().parse().ok(),
+            "--out" => out = Some(value()),
+            _ => eprintln!("Ignoring unknown argument: {arg}"),
+        }
+    }
+
+    let Some(seed) = seed else {
+        eprintln!("Usage: rlappy-solve --seed <N> [--gaps <N>] [--config <file>] [--resolution <units>] [--exact] [--max-ticks <N>] [--max-states <N>] [--out <file>]");
+        return ExitCode::FAILURE;
+    };
+
+    let mut options = SolverOptions::new(gaps, &config);
+    if let Some(resolution) = resolution {
+        options.resolution = Some(resolution);
+    }
+    if exact {
+        options.resolution = None;
+    }
+    if let Some(max_ticks) = max_ticks {
+        options.max_ticks = max_ticks;
+    }
+    if let Some(max_states) = max_states {
+        options.max_states = max_states;
+    }
+
+    let search = |options: &SolverOptions| {
+        let result = solve(seed, config, options);
+        if let Ok((_, stats)) = &result {
+            println!(
+                "Searched {} ticks, expanded {} states (peak layer {})",
+                stats.ticks, stats.expanded, stats.peak_states
+            );
+        }
+        result
+    };
+    let mut solution = match search(&options) {
+        Ok((solution, _)) => solution,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    // Merging states can only lose schedules, so only an exact search can rule them all out
+    if let Solution::NotFound {
+        gap,
+        tick,
+        resolution,
+    } = solution
+    {
+        println!(
+            "No schedule found at resolution {resolution}; every kept state dies before gap {gap} (tick {tick}). Searching exactly"
+        );
+        options.resolution = None;
+        solution = match search(&options) {
+            Ok((solution, _)) => solution,
+            Err(err) => {
+                eprintln!("{err}");
+                return ExitCode::FAILURE;
+            }
+        };
+    }
+
+    match solution {
+        Solution::Solved(replay) => {
+            // Re-simulate so a pruning or arithmetic mismatch can never ship a bad replay
+            let check = replay.simulate();
+            if check.is_over() || check.score < gaps {
+                eprintln!("Solution failed verification (score {})", check.score);
+                return ExitCode::FAILURE;
+            }
+
+            let path = out.unwrap_or_else(|| format!("./solution-{seed}.json"));
+            if let Err(err) = replay.save(&path) {
+                eprintln!("Failed to save replay to {path}: {err}");
+                return ExitCode::FAILURE;
+            }
+            println!(
+                "Seed {seed} survives {gaps} gaps in {} ticks with {} jumps; replay saved to {path}",
+                replay.ticks,
+                replay.jumps.len()
+            );
+            ExitCode::SUCCESS
+        }
+        Solution::Impossible { gap, tick } => {
+            println!(
+                "Seed {seed} is impossible: every schedule dies before gap {gap} (tick {tick})"
+            );
+            ExitCode::FAILURE
+        }
+        Solution::NotFound {
+            gap,
+            tick,
+            resolution,
+        } => {
+            println!(
+                "Seed {seed}: no schedule found at resolution {resolution}; every kept state dies before gap {gap} (tick {tick})"
+            );
+            ExitCode::FAILURE
+        }
+        Solution::Exhausted { score } => {
+            println!(
+                "Gave up after {} ticks with only {score} of {gaps} gaps scored",
+                options.max_ticks
+            );
+            ExitCode::FAILURE
+        }
+        Solution::TooManyStates { tick } => {
+            println!(
+                "Gave up on tick {tick} with more than {} states in a layer; the seed is undecided",
+                options.max_states
+            );
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Combines players' tournament results files into one standings table.
+//!
+//! Usage: rlappy-tournament <tournament.json> <results.json>... [--csv <file>] [--json <file>]
+//!
+//! The table is printed either way; `--csv` and `--json` also write it to a file, and `-`
+//! writes it to stdout instead of the table.
+
+use rlappy_bev::tournament::{standings, standings_csv, Results, Tournament};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let mut paths = Vec::new();
+    let mut csv_out = None;
+    let mut json_out = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--csv" => csv_out = args.next(),
+            "--json" => json_out = args.next(),
+            _ if arg.starts_with("--") => eprintln!("Ignoring unknown argument: {arg}"),
+            _ => paths.push(arg),
+        }
+    }
+
+    let Some((definition, result_paths)) = paths.split_first() else {
+        eprintln!("Usage: rlappy-tournament <tournament.json> <results.json>... [--csv <file>] [--json <file>]");
+        return ExitCode::FAILURE;
+    };
+    let tournament = match Tournament::load(definition) {
+        Ok(tournament) => tournament,
+        Err(err) => {
+            eprintln!("Failed to load tournament {definition}: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut results = Vec::new();
+    for path in result_paths {
+        match Results::load(path) {
+            Ok(result) => results.push(result),
+            Err(err) => {
+                eprintln!("Failed to load results {path}: {err}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let standings = match standings(&tournament, &results) {
+        Ok(standings) => standings,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut outputs = Vec::new();
+    if let Some(path) = csv_out {
+        outputs.push((path, standings_csv(&tournament, &standings)));
+    }
+    if let Some(path) = json_out {
+        match serde_json::to_string_pretty(&standings) {
+            Ok(json) => outputs.push((path, json + "\n")),
+            Err(err) => {
+                eprintln!("Failed to encode standings: {err}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let to_stdout = outputs.iter().any(|(path, _)| path == "-");
+
+    if !to_stdout {
+        println!(
+            "{}: {} seeds, {} attempts each, {:?} scoring",
+            tournament.name,
+            tournament.seeds.len(),
+            tournament.attempts,
+            tournament.scoring
+        );
+        println!();
+        println!("Rank  {:<20} {:>8} {:>9}", "Player", "Points", "Attempts");
+        for standing in &standings {
+            println!(
+                "{:>4}  {:<20} {:>8} {:>5}/{}",
+                standing.rank,
+                standing.player,
+                standing.points,
+                standing.attempts_played,
+                tournament.attempts as usize * tournament.seeds.len()
+            );
+        }
+    }
+
+    for (path, contents) in outputs {
+        if path == "-" {
+            print!("{contents}");
+        } else if let Err(err) = std::fs::write(&path, contents) {
+            eprintln!("Failed to write {path}: {err}");
+            return ExitCode::FAILURE;
+        } else if !to_stdout {
+            println!("Wrote {path}");
+        }
+    }
+    ExitCode::SUCCESS
+}
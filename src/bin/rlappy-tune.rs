@@ -0,0 +1,115 @@
+//! Tunes gap size and speed ramp so bot games hit a target score distribution, then
+//! writes the best config as a preset usable with `--config`.
+//!
+//! Usage: rlappy-tune --target <stat=score>... [--skill <0..1>] [--games <N>]
+//!                    [--exploration <N>] [--refinement <N>] [--base <file>] [--out <file>]
+//!                    [--gap <min:max>] [--ramp <min:max>] [--max-speed <min:max>]
+
+use rlappy_bev::sim::GameConfig;
+use rlappy_bev::tuner::{tune, Target, TuneSpace, TunerOptions};
+use std::ops::RangeInclusive;
+use std::process::ExitCode;
+
+fn parse_range(value: &str) -> Option<RangeInclusive<f32>> {
+    let (min, max) = value.split_once(':')?;
+    Some(min.parse().ok()?..=max.parse().ok()?)
+}
+
+fn main() -> ExitCode {
+    let mut targets = Vec::new();
+    let mut options = TunerOptions::default();
+    let mut space = TuneSpace::default();
+    let mut base = GameConfig::default();
+    let mut out = "./preset.json".to_string();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_default();
+        match arg.as_str() {
+            "--target" => match value.parse::<Target>() {
+                Ok(target) => targets.push(target),
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--skill" => match value.parse::<f32>() {
+                // Also turns away NaN, which fails every comparison
+                Ok(skill) if (0.0..=1.0).contains(&skill) => options.batch.skill = skill,
+                _ => {
+                    eprintln!("Invalid --skill: {value} (expected a number from 0 to 1)");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--games" => options.batch.games = value.parse().unwrap_or(options.batch.games),
+            "--seed" => options.batch.base_seed = value.parse().unwrap_or(0),
+            "--exploration" => options.exploration = value.parse().unwrap_or(options.exploration),
+            "--refinement" => options.refinement = value.parse().unwrap_or(options.refinement),
+            "--gap" | "--ramp" | "--max-speed" => {
+                let Some(range) = parse_range(&value) else {
+                    eprintln!("Invalid {arg}: {value} (expected <min>:<max>)");
+                    return ExitCode::FAILURE;
+                };
+                match arg.as_str() {
+                    "--gap" => space.pipe_gap = range,
+                    "--ramp" => space.speed_increase_rate = range,
+                    _ => space.max_speed_multiplier = range,
+                }
+            }
+            "--base" => match GameConfig::load(&value) {
+                Ok(loaded) => base = loaded,
+                Err(err) => {
+                    eprintln!("Failed to load config {value}: {err}");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--out" => out = value,
+            _ => {
+                eprintln!("Unknown argument: {arg}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if targets.is_empty() {
+        eprintln!("Usage: rlappy-tune --target median=25 --target p90=60 [--skill 0.8] [--games 400] [--out preset.json]");
+        return ExitCode::FAILURE;
+    }
+
+    if let Err(err) = space.validate(&base) {
+        eprintln!("Invalid search range: {err}");
+        return ExitCode::FAILURE;
+    }
+
+    let describe = |achieved: &[f64]| {
+        targets
+            .iter()
+            .zip(achieved)
+            .map(|(target, actual)| format!("{} {actual:.1}/{}", target.label(), target.score))
+            .collect::<Vec<_>>()
+            .join("  ")
+    };
+
+    let total = options.exploration + options.refinement;
+    let best = tune(base, &space, &targets, &options, |round, best| {
+        println!(
+            "[{round:>3}/{total}] best loss {:.4}  {}  gap {:.1}  ramp {:.4}  max speed {:.2}",
+            best.loss,
+            describe(&best.achieved),
+            best.config.pipe_gap,
+            best.config.speed_increase_rate,
+            best.config.max_speed_multiplier
+        );
+    });
+
+    if let Err(err) = best.config.validate() {
+        eprintln!("Best config is invalid, not writing it: {err}");
+        return ExitCode::FAILURE;
+    }
+    if let Err(err) = best.config.save(&out) {
+        eprintln!("Failed to write preset to {out}: {err}");
+        return ExitCode::FAILURE;
+    }
+    println!("Best match ({}) written to {out}", describe(&best.achieved));
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,75 @@
+//! Heuristic bot with a tunable skill level, used for balance analysis.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::VecDeque;
+
+use crate::sim::Observation;
+
+/// How far below the gap centre the bot aims before flapping.
+const AIM_OFFSET: f32 = 30.0;
+/// Worst-case reaction delay, reached at skill 0.
+const MAX_REACTION_TICKS: f32 = 12.0;
+/// Worst-case standard deviation of the aim point, reached at skill 0.
+const MAX_AIM_ERROR: f32 = 60.0;
+/// Worst-case chance per tick of a spurious flap, reached at skill 0.
+const MAX_MISTAKE_RATE: f64 = 0.02;
+
+/// Flaps whenever it is falling below its aim point. Lower skill adds reaction delay,
+/// aim error that changes every gap, and occasional spurious flaps.
+#[derive(Clone, Debug)]
+pub struct Bot {
+    skill: f32,
+    rng: StdRng,
+    delayed: VecDeque<Observation>,
+    aim_error: f32,
+    aimed_gap_y: Option<f32>,
+}
+
+impl Bot {
+    /// `skill` is clamped to `0.0..=1.0`; 1.0 reacts instantly and never misjudges.
+    pub fn new(skill: f32, seed: u64) -> Self {
+        Self {
+            skill: skill.clamp(0.0, 1.0),
+            rng: StdRng::seed_from_u64(seed),
+            delayed: VecDeque::new(),
+            aim_error: 0.0,
+            aimed_gap_y: None,
+        }
+    }
+
+    pub fn skill(&self) -> f32 {
+        self.skill
+    }
+
+    pub fn decide(&mut self, observation: Observation) -> bool {
+        let clumsiness = 1.0 - self.skill;
+
+        let reaction_ticks = (clumsiness * MAX_REACTION_TICKS).round() as usize;
+        self.delayed.push_back(observation);
+        while self.delayed.len() > reaction_ticks + 1 {
+            self.delayed.pop_front();
+        }
+        let seen = self.delayed[0];
+
+        // Re-roll the aim error whenever a new gap becomes the target
+        if self.aimed_gap_y != Some(seen.gap_y) {
+            self.aimed_gap_y = Some(seen.gap_y);
+            self.aim_error = gaussian(&mut self.rng) * clumsiness * MAX_AIM_ERROR;
+        }
+
+        if self.rng.gen_bool(clumsiness as f64 * MAX_MISTAKE_RATE) {
+            return true;
+        }
+
+        let target = seen.gap_y - AIM_OFFSET + self.aim_error;
+        seen.bird_y < target && seen.bird_velocity < 0.0
+    }
+}
+
+/// Standard normal sample via the Box-Muller transform.
+fn gaussian(rng: &mut StdRng) -> f32 {
+    let u1: f32 = rng.gen_range(f32::EPSILON..1.0);
+    let u2: f32 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos()
+}
@@ -0,0 +1,360 @@
+//! Challenge codes: a run's game version, seed, mode and score, and optionally its inputs,
+//! packed into a short string that can be pasted into someone else's game.
+//!
+//! The code is URL-safe base64 of a small binary record ending in a checksum, so a
+//! mistyped or truncated code is rejected instead of starting the wrong course. Codes
+//! with an input log can be re-simulated to check the score they claim.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+
+use crate::leaderboard::MAX_TICKS;
+use crate::netplay::{config_fields, config_from_fields, take};
+use crate::replay::Replay;
+use crate::sim::GameConfig;
+
+/// Layout version of the encoded record.
+pub const CHALLENGE_FORMAT: u8 = 1;
+
+const HAS_CONFIG: u8 = 1;
+const HAS_INPUTS: u8 = 2;
+
+/// Mode the challenge was played in; the same course is played in the same mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChallengeMode {
+    Solo,
+    Versus,
+    SplitScreen,
+    Coop,
+}
+
+impl ChallengeMode {
+    const ALL: [ChallengeMode; 4] = [
+        ChallengeMode::Solo,
+        ChallengeMode::Versus,
+        ChallengeMode::SplitScreen,
+        ChallengeMode::Coop,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ChallengeMode::Solo => "Solo",
+            ChallengeMode::Versus => "Versus",
+            ChallengeMode::SplitScreen => "Split screen",
+            ChallengeMode::Coop => "Co-op",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Challenge {
+    /// `major.minor.patch` of the game that made the code.
+    pub game_version: [u8; 3],
+    pub seed: u64,
+    pub mode: ChallengeMode,
+    /// Score to beat, in gaps.
+    pub score: u32,
+    pub config: GameConfig,
+    /// One jump flag per tick for player one, when the sender included it.
+    pub inputs: Option<Vec<bool>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChallengeError {
+    /// Not base64, too short, or the checksum doesn't match.
+    Malformed,
+    /// Made by a newer game with a layout this one can't read.
+    UnsupportedFormat(u8),
+}
+
+impl std::fmt::Display for ChallengeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChallengeError::Malformed => write!(f, "not a valid challenge code"),
+            ChallengeError::UnsupportedFormat(format) => {
+                write!(f, "challenge code format {format} needs a newer game")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ChallengeError {}
+
+impl Challenge {
+    pub fn new(seed: u64, mode: ChallengeMode, score: u32, config: GameConfig) -> Self {
+        Self {
+            game_version: this_version(),
+            seed,
+            mode,
+            score,
+            config,
+            inputs: None,
+        }
+    }
+
+    pub fn game_version_string(&self) -> String {
+        let [major, minor, patch] = self.game_version;
+        format!("{major}.{minor}.{patch}")
+    }
+
+    pub fn encode(&self) -> String {
+        let mut flags = 0;
+        if self.config != GameConfig::default() {
+            flags |= HAS_CONFIG;
+        }
+        if self.inputs.is_some() {
+            flags |= HAS_INPUTS;
+        }
+
+        let mut bytes = vec![CHALLENGE_FORMAT];
+        bytes.extend_from_slice(&self.game_version);
+        bytes.push(flags);
+        bytes.push(self.mode as u8);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        push_varint(&mut bytes, self.score);
+        if flags & HAS_CONFIG != 0 {
+            for field in config_fields(&self.config) {
+                bytes.extend_from_slice(&field.to_le_bytes());
+            }
+        }
+        if let Some(inputs) = &self.inputs {
+            // Jumps are sparse, so store the gaps between them rather than every tick
+            let replay = Replay::from_inputs(self.seed, self.config, inputs);
+            push_varint(&mut bytes, replay.ticks);
+            push_varint(&mut bytes, replay.jumps.len() as u32);
+            let mut last = 0;
+            for &jump in &replay.jumps {
+                push_varint(&mut bytes, jump - last);
+                last = jump;
+            }
+        }
+        bytes.extend_from_slice(&checksum(&bytes).to_le_bytes());
+        URL_SAFE_NO_PAD.encode(bytes)
+    }
+
+    pub fn decode(code: &str) -> Result<Self, ChallengeError> {
+        let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+        let bytes = URL_SAFE_NO_PAD
+            .decode(code)
+            .map_err(|_| ChallengeError::Malformed)?;
+        let (body, sum) = bytes
+            .split_last_chunk::<2>()
+            .ok_or(ChallengeError::Malformed)?;
+        if checksum(body) != u16::from_le_bytes(*sum) {
+            return Err(ChallengeError::Malformed);
+        }
+        let mut bytes = body;
+        let [format] = take::<1>(&mut bytes).ok_or(ChallengeError::Malformed)?;
+        if format != CHALLENGE_FORMAT {
+            return Err(ChallengeError::UnsupportedFormat(format));
+        }
+        Self::decode_body(bytes).ok_or(ChallengeError::Malformed)
+    }
+
+    fn decode_body(mut bytes: &[u8]) -> Option<Self> {
+        let game_version = take::<3>(&mut bytes)?;
+        let [flags, mode] = take::<2>(&mut bytes)?;
+        let mode = *ChallengeMode::ALL.get(mode as usize)?;
+        let seed = u64::from_le_bytes(take(&mut bytes)?);
+        let score = take_varint(&mut bytes)?;
+
+        let mut config = GameConfig::default();
+        if flags & HAS_CONFIG != 0 {
+            let mut fields = [0.0; 7];
+            for field in &mut fields {
+                *field = f32::from_le_bytes(take(&mut bytes)?);
+            }
+            config = config_from_fields(fields)?;
+        }
+
+        let mut inputs = None;
+        if flags & HAS_INPUTS != 0 {
+            // Checked before `inputs()` allocates a flag per tick
+            let ticks = take_varint(&mut bytes).filter(|&ticks| ticks <= MAX_TICKS)?;
+            let count = take_varint(&mut bytes)?;
+            let mut replay = Replay::new(seed, config);
+            replay.ticks = ticks;
+            let mut tick = 0u32;
+            for _ in 0..count {
+                tick = tick.checked_add(take_varint(&mut bytes)?)?;
+                if tick >= ticks {
+                    return None;
+                }
+                replay.jumps.push(tick);
+            }
+            inputs = Some(replay.inputs());
+        }
+
+        bytes.is_empty().then_some(Self {
+            game_version,
+            seed,
+            mode,
+            score,
+            config,
+            inputs,
+        })
+    }
+
+    /// Score the input log actually reaches, or `None` without one. Only solo runs have
+    /// a single input log to check.
+    pub fn verified_score(&self) -> Option<u32> {
+        let inputs = self.inputs.as_ref()?;
+        if self.mode != ChallengeMode::Solo {
+            return None;
+        }
+        Some(
+            Replay::from_inputs(self.seed, self.config, inputs)
+                .simulate()
+                .score,
+        )
+    }
+}
+
+/// This build's version as packed into codes.
+pub fn this_version() -> [u8; 3] {
+    let mut parts = env!("CARGO_PKG_VERSION")
+        .split('.')
+        .map(|part| part.parse::<u8>().unwrap_or(0));
+    [(); 3].map(|_| parts.next().unwrap_or(0))
+}
+
+fn push_varint(bytes: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn take_varint(bytes: &mut &[u8]) -> Option<u32> {
+    let mut value = 0u32;
+    for shift in (0..35).step_by(7) {
+        let [byte] = take::<1>(bytes)?;
+        value |= u32::from(byte & 0x7f).checked_shl(shift)?;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// FNV-1a folded to 16 bits; enough to catch typos and truncation.
+fn checksum(bytes: &[u8]) -> u16 {
+    let mut hash: u32 = 0x811c_9dc5;
+    for &byte in bytes {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    (hash ^ (hash >> 16)) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::Bot;
+    use crate::sim::Simulation;
+
+    const SEED: u64 = 0xdead_beef;
+
+    /// A bot's inputs for a solo run on `SEED`, and the score they reach.
+    fn bot_inputs() -> (Vec<bool>, u32) {
+        let mut sim = Simulation::new(SEED);
+        let mut bot = Bot::new(0.9, SEED);
+        let mut inputs = Vec::new();
+        while !sim.is_over() && inputs.len() < 60 * 60 {
+            let jump = bot.decide(sim.observation());
+            sim.step(jump);
+            inputs.push(jump);
+        }
+        (inputs, sim.score)
+    }
+
+    #[test]
+    fn codes_round_trip_in_every_mode() {
+        for mode in ChallengeMode::ALL {
+            let challenge = Challenge::new(SEED, mode, 300, GameConfig::default());
+            let code = challenge.encode();
+            // Seed, score and mode only make a code short enough to read out
+            assert!(code.len() < 32, "{code}");
+            assert_eq!(Challenge::decode(&code), Ok(challenge));
+        }
+    }
+
+    #[test]
+    fn configs_and_inputs_round_trip() {
+        let (mut inputs, score) = bot_inputs();
+        // Idle ticks after the last jump are kept too
+        inputs.extend([false; 5]);
+        let config = GameConfig {
+            pipe_gap: 170.0,
+            speed_increase_rate: 0.08,
+            ..GameConfig::default()
+        };
+        let mut challenge = Challenge::new(SEED, ChallengeMode::Solo, score, config);
+        challenge.inputs = Some(inputs);
+
+        // Pasted codes may pick up line breaks and spaces
+        let code = challenge.encode();
+        let (head, tail) = code.split_at(code.len() / 2);
+        assert_eq!(
+            Challenge::decode(&format!(" {head}\n{tail} ")),
+            Ok(challenge)
+        );
+    }
+
+    #[test]
+    fn checksum_catches_typos_and_truncation() {
+        let code = Challenge::new(SEED, ChallengeMode::Coop, 12, GameConfig::default()).encode();
+        for (index, c) in code.char_indices() {
+            let typo = if c == 'A' { 'B' } else { 'A' };
+            let mut mistyped = code.clone();
+            mistyped.replace_range(index..index + 1, &typo.to_string());
+            assert_eq!(
+                Challenge::decode(&mistyped),
+                Err(ChallengeError::Malformed),
+                "{mistyped}"
+            );
+        }
+        for len in 0..code.len() {
+            assert!(Challenge::decode(&code[..len]).is_err(), "{}", &code[..len]);
+        }
+        assert_eq!(
+            Challenge::decode("not a code!"),
+            Err(ChallengeError::Malformed)
+        );
+    }
+
+    #[test]
+    fn newer_formats_are_reported() {
+        let mut bytes = vec![CHALLENGE_FORMAT + 1, 9, 9, 9];
+        bytes.extend_from_slice(&checksum(&bytes).to_le_bytes());
+        let code = URL_SAFE_NO_PAD.encode(bytes);
+        assert_eq!(
+            Challenge::decode(&code),
+            Err(ChallengeError::UnsupportedFormat(CHALLENGE_FORMAT + 1))
+        );
+    }
+
+    #[test]
+    fn unplayable_configs_are_rejected() {
+        let config = GameConfig {
+            pipe_gap: f32::NAN,
+            ..GameConfig::default()
+        };
+        let code = Challenge::new(SEED, ChallengeMode::Solo, 1, config).encode();
+        assert_eq!(Challenge::decode(&code), Err(ChallengeError::Malformed));
+    }
+
+    #[test]
+    fn input_logs_verify_solo_scores() {
+        let (inputs, score) = bot_inputs();
+        let mut challenge =
+            Challenge::new(SEED, ChallengeMode::Solo, score + 10, GameConfig::default());
+        assert_eq!(challenge.verified_score(), None);
+        challenge.inputs = Some(inputs);
+        assert_eq!(challenge.verified_score(), Some(score));
+
+        challenge.mode = ChallengeMode::Versus;
+        assert_eq!(challenge.verified_score(), None);
+    }
+}
@@ -0,0 +1,297 @@
+use bevy::prelude::*;
+
+use crate::input::{self, Action, Actions, Binding, ButtonFocus, InputMap};
+use crate::settings::Settings;
+use crate::{GameState, StartButton};
+
+/// Controls screen, removed when leaving `GameState::Controls`.
+#[derive(Component)]
+pub struct ControlsUi;
+
+#[derive(Component, Clone, Copy)]
+pub enum ControlsButton {
+    Rebind(Action),
+    Reset,
+    Back,
+}
+
+/// Bindings shown on an action's row.
+#[derive(Component)]
+pub struct BindingText(Action);
+
+/// Prompt line under the rows.
+#[derive(Component)]
+pub struct ControlsText;
+
+/// Action waiting for its new binding, present while the screen listens for one.
+#[derive(Resource)]
+pub struct Capture {
+    action: Action,
+    /// Binding that other actions on the same screens already use, awaiting Enter or Esc.
+    conflict: Option<(Binding, Vec<Action>)>,
+    /// Last binding turned down because a fixed shortcut uses it, and that shortcut.
+    refused: Option<(Binding, &'static str)>,
+}
+
+const ROW_COLOR: Color = Color::srgb(0.2, 0.7, 0.2);
+const ROW_HOVER: Color = Color::srgb(0.25, 0.85, 0.25);
+const ROW_PRESSED: Color = Color::srgb(0.1, 0.5, 0.1);
+
+fn bindings_label(map: &InputMap, action: Action) -> String {
+    let labels: Vec<String> = map.bindings(action).iter().map(|b| b.label()).collect();
+    if labels.is_empty() {
+        format!("{}: (none)", action.label())
+    } else {
+        format!("{}: {}", action.label(), labels.join(", "))
+    }
+}
+
+fn button(width: f32, color: Color) -> ButtonBundle {
+    ButtonBundle {
+        style: Style {
+            width: Val::Px(width),
+            height: Val::Px(30.0),
+            // Outlined while focused for keyboard and gamepad
+            border: UiRect::all(Val::Px(2.0)),
+            padding: UiRect::horizontal(Val::Px(10.0)),
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        background_color: color.into(),
+        ..default()
+    }
+}
+
+fn label(value: String) -> TextBundle {
+    TextBundle::from_section(
+        value,
+        TextStyle {
+            font_size: 20.0,
+            color: Color::WHITE,
+            ..default()
+        },
+    )
+}
+
+pub fn spawn_controls(mut commands: Commands, settings: Res<Settings>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    margin: UiRect::all(Val::Auto),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(4.0),
+                    ..default()
+                },
+                ..default()
+            },
+            ControlsUi,
+        ))
+        .with_children(|parent| {
+            for action in Action::ALL {
+                parent
+                    .spawn((
+                        button(560.0, ROW_COLOR),
+                        StartButton,
+                        ControlsButton::Rebind(action),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            label(bindings_label(&settings.controls, action)),
+                            BindingText(action),
+                        ));
+                    });
+            }
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        margin: UiRect::top(Val::Px(8.0)),
+                        column_gap: Val::Px(20.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (text, action) in [
+                        ("RESET DEFAULTS", ControlsButton::Reset),
+                        ("BACK", ControlsButton::Back),
+                    ] {
+                        parent
+                            .spawn((button(270.0, ROW_COLOR), StartButton, action))
+                            .with_children(|parent| {
+                                parent.spawn(label(text.to_string()));
+                            });
+                    }
+                });
+            parent.spawn((label(String::new()), ControlsText));
+        });
+}
+
+/// Presses rows and buttons by click or Confirm; Back leaves for the menu.
+pub fn controls_buttons(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, &ControlsButton),
+        Changed<Interaction>,
+    >,
+    buttons: Query<(Entity, &GlobalTransform), With<StartButton>>,
+    controls_buttons: Query<&ControlsButton>,
+    actions: Res<Actions>,
+    focus: Res<ButtonFocus>,
+    mut settings: ResMut<Settings>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut commands: Commands,
+) {
+    let mut pressed = None;
+    for (interaction, mut color, button) in &mut interaction_query {
+        *color = match *interaction {
+            Interaction::Pressed => {
+                pressed = Some(*button);
+                ROW_PRESSED.into()
+            }
+            Interaction::Hovered => ROW_HOVER.into(),
+            Interaction::None => ROW_COLOR.into(),
+        };
+    }
+    if actions.just_pressed(Action::Confirm) {
+        pressed = focus
+            .current(&buttons)
+            .and_then(|entity| controls_buttons.get(entity).ok().copied());
+    } else if actions.just_pressed(Action::Back) {
+        pressed = Some(ControlsButton::Back);
+    }
+
+    match pressed {
+        Some(ControlsButton::Rebind(action)) => commands.insert_resource(Capture {
+            action,
+            conflict: None,
+            refused: None,
+        }),
+        Some(ControlsButton::Reset) => {
+            settings.controls = InputMap::default();
+            settings.save();
+        }
+        Some(ControlsButton::Back) => next_state.set(GameState::Menu),
+        None => {}
+    }
+}
+
+/// Listens for the next key, mouse button or gamepad button and binds it, asking first if
+/// another action on the same screens uses it and refusing keys that are fixed shortcuts
+/// there. Esc cancels.
+pub fn capture_binding(
+    mut capture: ResMut<Capture>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+    pad_buttons: Res<ButtonInput<GamepadButton>>,
+    mut settings: ResMut<Settings>,
+    mut commands: Commands,
+) {
+    // The press that started the capture is still down this frame
+    if capture.is_added() {
+        return;
+    }
+    if keyboard.just_pressed(KeyCode::Escape) {
+        commands.remove_resource::<Capture>();
+        return;
+    }
+
+    let action = capture.action;
+    if let Some((binding, others)) = &capture.conflict {
+        if keyboard.just_pressed(KeyCode::Enter) {
+            for &other in others {
+                settings.controls.unbind(other, *binding);
+            }
+            settings.controls.rebind(action, *binding);
+            settings.save();
+            commands.remove_resource::<Capture>();
+        }
+        return;
+    }
+
+    let binding = keyboard
+        .get_just_pressed()
+        .next()
+        .map(|&key| Binding::Key(key))
+        .or_else(|| mouse.get_just_pressed().next().map(|&b| Binding::Mouse(b)))
+        .or_else(|| touches.any_just_pressed().then_some(Binding::Touch))
+        .or_else(|| {
+            pad_buttons
+                .get_just_pressed()
+                .next()
+                .map(|button| Binding::Gamepad(button.button_type))
+        });
+    let Some(binding) = binding else {
+        return;
+    };
+    if let Some(shortcut) = input::shortcut(action, binding) {
+        capture.refused = Some((binding, shortcut));
+        return;
+    }
+    let others = settings.controls.conflicts(action, binding);
+    if others.is_empty() {
+        settings.controls.rebind(action, binding);
+        settings.save();
+        commands.remove_resource::<Capture>();
+    } else {
+        capture.conflict = Some((binding, others));
+    }
+}
+
+pub fn update_controls_text(
+    settings: Res<Settings>,
+    capture: Option<Res<Capture>>,
+    mut rows: Query<(&mut Text, &BindingText), Without<ControlsText>>,
+    mut prompts: Query<&mut Text, With<ControlsText>>,
+) {
+    for (mut text, row) in rows.iter_mut() {
+        let value = bindings_label(&settings.controls, row.0);
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+
+    let value = match capture.as_deref() {
+        None => "Enter or click: rebind  Esc: back".to_string(),
+        Some(Capture {
+            action,
+            conflict: None,
+            refused: None,
+        }) => format!(
+            "Press a key, mouse button or pad button for {}  Esc: cancel",
+            action.label()
+        ),
+        Some(Capture {
+            action,
+            conflict: None,
+            refused: Some((binding, shortcut)),
+        }) => format!(
+            "{} is the {shortcut} shortcut there.\nPress another for {}  Esc: cancel",
+            binding.label(),
+            action.label()
+        ),
+        Some(Capture {
+            action,
+            conflict: Some((binding, others)),
+            ..
+        }) => {
+            let others: Vec<&str> = others.iter().map(|other| other.label()).collect();
+            format!(
+                "{} is also {}.\nEnter: use it for {} only  Esc: cancel",
+                binding.label(),
+                others.join(", "),
+                action.label()
+            )
+        }
+    };
+    for mut text in prompts.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+pub fn end_capture(mut commands: Commands) {
+    commands.remove_resource::<Capture>();
+}
@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+
+use crate::{Bird, Dead, GameDifficulty, GameMode, Player, Score};
+
+/// Gaps the surviving partner must clear to bring a downed bird back.
+const REVIVE_GAPS: u32 = 3;
+
+/// Gap count when a co-op bird went down.
+#[derive(Component)]
+pub struct Downed {
+    at_gap: u32,
+}
+
+#[derive(Component)]
+pub struct TeamScoreText;
+
+pub fn coop_active(mode: Res<GameMode>) -> bool {
+    *mode == GameMode::Coop
+}
+
+pub fn spawn_team_score(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "Team: 0",
+            TextStyle {
+                font_size: 30.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(340.0),
+            ..default()
+        }),
+        TeamScoreText,
+    ));
+}
+
+/// Gaps cleared by the team. The course scores each gap once while either bird is flying,
+/// so a gap both birds fly through counts once.
+pub fn team_score(score: &Score) -> u32 {
+    score.0 / 2
+}
+
+/// Notes when birds go down and revives them next to their partner once the partner has
+/// carried on for `REVIVE_GAPS` gaps. Runs after `update_score`.
+pub fn revive_partners(
+    mut commands: Commands,
+    difficulty: Res<GameDifficulty>,
+    mode: Res<GameMode>,
+    newly_dead: Query<Entity, (With<Dead>, Without<Downed>)>,
+    mut birds: Query<(
+        Entity,
+        &Player,
+        &mut Bird,
+        &mut Transform,
+        &mut Sprite,
+        Option<&Downed>,
+        Has<Dead>,
+    )>,
+) {
+    for entity in newly_dead.iter() {
+        commands.entity(entity).insert(Downed {
+            at_gap: difficulty.pipes_passed,
+        });
+    }
+
+    // The partner has just cleared the gap, so its position is a safe place to come back
+    let Some((partner_y, partner_velocity)) = birds
+        .iter()
+        .find(|(.., dead)| !dead)
+        .map(|(_, _, bird, transform, ..)| (transform.translation.y, bird.velocity))
+    else {
+        return;
+    };
+
+    for (entity, player, mut bird, mut transform, mut sprite, downed, _) in birds.iter_mut() {
+        let Some(downed) = downed else {
+            continue;
+        };
+        if difficulty.pipes_passed < downed.at_gap + REVIVE_GAPS {
+            continue;
+        }
+        transform.translation.y = partner_y;
+        bird.velocity = partner_velocity;
+        sprite.color = mode.tint(player.0);
+        commands.entity(entity).remove::<(Dead, Downed)>();
+    }
+}
+
+pub fn update_team_hud(
+    difficulty: Res<GameDifficulty>,
+    score: Res<Score>,
+    birds: Query<(&Player, Option<&Downed>)>,
+    mut text_query: Query<&mut Text, With<TeamScoreText>>,
+) {
+    let mut value = format!("Team: {}", team_score(&score));
+    for (player, downed) in birds.iter() {
+        if let Some(downed) = downed {
+            let remaining = (downed.at_gap + REVIVE_GAPS).saturating_sub(difficulty.pipes_passed);
+            value.push_str(&format!("\nP{} back in {remaining}", player.0 + 1));
+        }
+    }
+
+    for mut text in text_query.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
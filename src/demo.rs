@@ -0,0 +1,303 @@
+use bevy::prelude::*;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use rlappy_bev::sim::{Observation, PIPE_WIDTH};
+
+use crate::{ActiveConfig, Bird, GameDifficulty, GameSeed, PendingJump, Pipe, Player, Score};
+
+/// Output format for recorded human demonstrations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DemoFormat {
+    Jsonl,
+    Binary,
+}
+
+impl DemoFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            DemoFormat::Jsonl => "jsonl",
+            DemoFormat::Binary => "bin",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct DemoSample {
+    pub tick: u32,
+    #[serde(flatten)]
+    pub observation: Observation,
+    pub jump: bool,
+}
+
+#[derive(Serialize)]
+struct DemoHeader {
+    version: u32,
+    seed: u64,
+    final_score: u32,
+    tick_rate: f64,
+    ticks: u32,
+}
+
+/// Collects (observation, action) pairs for the current run while recording is enabled.
+#[derive(Resource)]
+pub struct DemoRecorder {
+    pub format: DemoFormat,
+    samples: Vec<DemoSample>,
+}
+
+impl DemoRecorder {
+    pub fn new(format: DemoFormat) -> Self {
+        Self {
+            format,
+            samples: Vec::new(),
+        }
+    }
+}
+
+const BINARY_MAGIC: &[u8; 4] = b"RLDM";
+const DEMO_VERSION: u32 = 1;
+
+/// Builds the observation for the first bird against the nearest gap still ahead of it.
+pub fn observe(
+    bird: (&Transform, &Bird),
+    pipes: impl Iterator<Item = (Transform, Pipe)>,
+    difficulty: &GameDifficulty,
+    gap_size: f32,
+) -> Observation {
+    let (bird_transform, bird) = bird;
+    let bird_x = bird_transform.translation.x;
+
+    // Both pipes of a pair share x and gap_y, so the nearest one describes the gap
+    let next_gap = pipes
+        .filter(|(transform, _)| transform.translation.x + PIPE_WIDTH / 2.0 > bird_x)
+        .min_by(|(a, _), (b, _)| a.translation.x.total_cmp(&b.translation.x));
+
+    let (gap_dx, gap_y) = match next_gap {
+        Some((transform, pipe)) => (transform.translation.x - bird_x, pipe.gap_y),
+        None => (f32::MAX, 0.0),
+    };
+
+    Observation {
+        bird_y: bird_transform.translation.y,
+        bird_velocity: bird.velocity,
+        gap_dx,
+        gap_y,
+        gap_size,
+        speed_multiplier: difficulty.speed_multiplier,
+    }
+}
+
+pub fn record_demo_tick(
+    mut recorder: ResMut<DemoRecorder>,
+    pending_jump: Res<PendingJump>,
+    bird_query: Query<(&Transform, &Bird, &Player)>,
+    pipe_query: Query<(&Transform, &Pipe)>,
+    difficulty: Res<GameDifficulty>,
+    config: Res<ActiveConfig>,
+) {
+    // Demos follow player one, the only bird outside multiplayer modes
+    let Some((transform, bird, _)) = bird_query.iter().find(|(_, _, player)| player.0 == 0) else {
+        return;
+    };
+
+    let observation = observe(
+        (transform, bird),
+        pipe_query.iter().map(|(t, p)| (*t, *p)),
+        &difficulty,
+        config.0.pipe_gap,
+    );
+    let tick = recorder.samples.len() as u32;
+    recorder.samples.push(DemoSample {
+        tick,
+        observation,
+        jump: pending_jump.0[0],
+    });
+}
+
+/// Drops the samples of a run abandoned before game over.
+pub fn discard_demo(mut recorder: ResMut<DemoRecorder>) {
+    recorder.samples.clear();
+}
+
+/// Writes the finished run to `./demo-<timestamp>.<ext>` and clears the buffer.
+pub fn save_demo(
+    mut recorder: ResMut<DemoRecorder>,
+    seed: Res<GameSeed>,
+    score: Res<Score>,
+    fixed_time: Res<Time<Fixed>>,
+) {
+    if recorder.samples.is_empty() {
+        return;
+    }
+
+    let samples = std::mem::take(&mut recorder.samples);
+    let header = DemoHeader {
+        version: DEMO_VERSION,
+        seed: seed.0,
+        final_score: score.0 / 2,
+        tick_rate: 1.0 / fixed_time.timestep().as_secs_f64(),
+        ticks: samples.len() as u32,
+    };
+
+    let path = format!(
+        "./demo-{}.{}",
+        chrono::Local::now().format("%Y%m%d-%H%M%S"),
+        recorder.format.extension()
+    );
+
+    let result = File::create(&path).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        match recorder.format {
+            DemoFormat::Jsonl => write_jsonl(&mut writer, &header, &samples),
+            DemoFormat::Binary => write_binary(&mut writer, &header, &samples),
+        }?;
+        writer.flush()
+    });
+
+    match result {
+        Ok(()) => eprintln!("Demo saved to {path} ({} ticks)", header.ticks),
+        Err(err) => eprintln!("Failed to save demo to {path}: {err}"),
+    }
+}
+
+fn write_jsonl(
+    writer: &mut impl Write,
+    header: &DemoHeader,
+    samples: &[DemoSample],
+) -> std::io::Result<()> {
+    serde_json::to_writer(&mut *writer, header)?;
+    writeln!(writer)?;
+    for sample in samples {
+        serde_json::to_writer(&mut *writer, sample)?;
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+/// Little-endian layout: magic, version, seed, final score, tick rate, sample count,
+/// then per sample six `f32` observation fields followed by a `u8` jump flag.
+fn write_binary(
+    writer: &mut impl Write,
+    header: &DemoHeader,
+    samples: &[DemoSample],
+) -> std::io::Result<()> {
+    writer.write_all(BINARY_MAGIC)?;
+    writer.write_all(&header.version.to_le_bytes())?;
+    writer.write_all(&header.seed.to_le_bytes())?;
+    writer.write_all(&header.final_score.to_le_bytes())?;
+    writer.write_all(&header.tick_rate.to_le_bytes())?;
+    writer.write_all(&header.ticks.to_le_bytes())?;
+    for sample in samples {
+        let o = &sample.observation;
+        for value in [
+            o.bird_y,
+            o.bird_velocity,
+            o.gap_dx,
+            o.gap_y,
+            o.gap_size,
+            o.speed_multiplier,
+        ] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        writer.write_all(&[sample.jump as u8])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(ticks: u32) -> DemoHeader {
+        DemoHeader {
+            version: DEMO_VERSION,
+            seed: 42,
+            final_score: 3,
+            tick_rate: 60.0,
+            ticks,
+        }
+    }
+
+    fn samples() -> Vec<DemoSample> {
+        (0..3)
+            .map(|tick| DemoSample {
+                tick,
+                observation: Observation {
+                    bird_y: tick as f32 * 10.0,
+                    bird_velocity: -50.0,
+                    gap_dx: 200.0,
+                    gap_y: 25.0,
+                    gap_size: 180.0,
+                    speed_multiplier: 1.5,
+                },
+                jump: tick == 1,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn jsonl_has_a_header_line_then_a_flat_line_per_tick() {
+        let samples = samples();
+        let mut bytes = Vec::new();
+        write_jsonl(&mut bytes, &header(3), &samples).unwrap();
+        let lines: Vec<serde_json::Value> = String::from_utf8(bytes)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0]["seed"], 42);
+        assert_eq!(lines[0]["final_score"], 3);
+        assert_eq!(lines[0]["ticks"], 3);
+        assert_eq!(lines[2]["tick"], 1);
+        assert_eq!(lines[2]["bird_y"], 10.0);
+        assert_eq!(lines[2]["speed_multiplier"], 1.5);
+        assert_eq!(lines[2]["jump"], true);
+        assert_eq!(lines[3]["jump"], false);
+    }
+
+    #[test]
+    fn binary_follows_the_documented_layout() {
+        let samples = samples();
+        let mut bytes = Vec::new();
+        write_binary(&mut bytes, &header(3), &samples).unwrap();
+
+        const HEADER: usize = 4 + 4 + 8 + 4 + 8 + 4;
+        const SAMPLE: usize = 6 * 4 + 1;
+        assert_eq!(bytes.len(), HEADER + 3 * SAMPLE);
+        assert_eq!(&bytes[..4], BINARY_MAGIC);
+        assert_eq!(bytes[4..8], DEMO_VERSION.to_le_bytes());
+        assert_eq!(bytes[8..16], 42u64.to_le_bytes());
+        assert_eq!(bytes[16..20], 3u32.to_le_bytes());
+        assert_eq!(bytes[20..28], 60.0f64.to_le_bytes());
+        assert_eq!(bytes[28..32], 3u32.to_le_bytes());
+
+        let second = &bytes[HEADER + SAMPLE..HEADER + 2 * SAMPLE];
+        assert_eq!(second[..4], 10.0f32.to_le_bytes());
+        assert_eq!(second[20..24], 1.5f32.to_le_bytes());
+        assert_eq!(second[24], 1);
+    }
+
+    #[test]
+    fn observations_look_at_the_nearest_gap_still_ahead() {
+        let bird = Bird { velocity: -20.0 };
+        let transform = Transform::from_xyz(-100.0, 30.0, 0.0);
+        let difficulty = GameDifficulty::default();
+        let pipe = |x: f32, gap_y: f32| (Transform::from_xyz(x, 0.0, 0.0), Pipe { gap_y });
+
+        // Passed, ahead and further ahead
+        let pipes = [pipe(-200.0, 1.0), pipe(300.0, 3.0), pipe(50.0, 2.0)];
+        let observation = observe((&transform, &bird), pipes.into_iter(), &difficulty, 180.0);
+        assert_eq!(observation.gap_dx, 150.0);
+        assert_eq!(observation.gap_y, 2.0);
+        assert_eq!(observation.bird_y, 30.0);
+        assert_eq!(observation.bird_velocity, -20.0);
+        assert_eq!(observation.gap_size, 180.0);
+
+        let empty = observe((&transform, &bird), std::iter::empty(), &difficulty, 180.0);
+        assert_eq!(empty.gap_dx, f32::MAX);
+    }
+}
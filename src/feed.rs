@@ -0,0 +1,153 @@
+//! Spectator feed: the game state as JSON lines over TCP, for overlays and dashboards.
+//!
+//! Every line is one [`FeedMessage`] tagged with a `type` field. `tick` messages describe
+//! the whole scene once per fixed tick; `state` and `score` messages mark events as they
+//! happen, so a consumer can react to them without diffing ticks.
+
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
+
+pub const FEED_VERSION: u32 = 1;
+/// Unsent bytes a spectator may fall behind by before it is dropped, about two seconds of
+/// busy `tick` lines.
+const MAX_PENDING: usize = 256 * 1024;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FeedMessage {
+    /// First line every spectator receives.
+    Hello { version: u32, state: String },
+    Tick {
+        /// Fixed ticks since the run started.
+        tick: u32,
+        state: String,
+        speed_multiplier: f32,
+        birds: Vec<BirdFrame>,
+        pipes: Vec<PipeFrame>,
+    },
+    /// A `GameState` transition.
+    State { from: String, to: String },
+    /// A bird cleared a gap.
+    Score { player: usize, score: u32 },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BirdFrame {
+    pub player: usize,
+    pub x: f32,
+    pub y: f32,
+    pub velocity: f32,
+    pub alive: bool,
+    pub score: u32,
+}
+
+/// One gap on screen: the pipe pair's x centre, the gap's y centre and its height.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PipeFrame {
+    pub x: f32,
+    pub gap_y: f32,
+    pub gap: f32,
+}
+
+impl FeedMessage {
+    pub fn to_line(&self) -> String {
+        let mut line = serde_json::to_string(self).expect("feed messages always serialise");
+        line.push('\n');
+        line
+    }
+}
+
+/// One connected client and the bytes its socket has not taken yet.
+struct Spectator {
+    stream: TcpStream,
+    pending: Vec<u8>,
+}
+
+impl Spectator {
+    /// Queues `bytes` and writes as much as the socket takes without blocking, so a line
+    /// cut short now is finished on a later call. Returns `false` once the client is gone
+    /// or too far behind.
+    fn send(&mut self, bytes: &[u8]) -> bool {
+        self.pending.extend_from_slice(bytes);
+        while !self.pending.is_empty() {
+            match self.stream.write(&self.pending) {
+                Ok(0) => return false,
+                Ok(written) => {
+                    self.pending.drain(..written);
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(_) => return false,
+            }
+        }
+        self.pending.len() <= MAX_PENDING
+    }
+}
+
+/// Accepts spectators on a TCP port and sends every published message to all of them.
+///
+/// Sockets never block the game: each spectator has its own queue of unsent lines, and
+/// spectators that disconnect or fall too far behind are dropped.
+pub struct FeedServer {
+    listener: TcpListener,
+    spectators: Vec<Spectator>,
+}
+
+impl FeedServer {
+    /// Listens on `port` at `ip`, which is loopback unless other machines should be able to
+    /// watch; port 0 picks a free one.
+    pub fn bind(ip: IpAddr, port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind((ip, port))?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            spectators: Vec::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn spectators(&self) -> usize {
+        self.spectators.len()
+    }
+
+    /// Takes new connections, greeting each with `hello`; returns how many arrived.
+    pub fn accept(&mut self, hello: &FeedMessage) -> usize {
+        let mut accepted = 0;
+        while let Ok((stream, _)) = self.listener.accept() {
+            if stream.set_nonblocking(true).is_err() {
+                continue;
+            }
+            let _ = stream.set_nodelay(true);
+            self.spectators.push(Spectator {
+                stream,
+                pending: Vec::new(),
+            });
+            accepted += 1;
+        }
+        if accepted > 0 {
+            let line = hello.to_line();
+            let start = self.spectators.len() - accepted;
+            Self::send_to(&mut self.spectators, start, line.as_bytes());
+        }
+        accepted
+    }
+
+    pub fn publish(&mut self, message: &FeedMessage) {
+        if !self.spectators.is_empty() {
+            Self::send_to(&mut self.spectators, 0, message.to_line().as_bytes());
+        }
+    }
+
+    fn send_to(spectators: &mut Vec<Spectator>, start: usize, bytes: &[u8]) {
+        let mut index = 0;
+        spectators.retain_mut(|spectator| {
+            let keep = index < start || spectator.send(bytes);
+            index += 1;
+            keep
+        });
+    }
+}
@@ -0,0 +1,726 @@
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::settings::Settings;
+use crate::StartButton;
+
+/// What the player wants to do, whichever device it came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Jump,
+    /// Player two's flap in local multiplayer.
+    JumpTwo,
+    Pause,
+    Restart,
+    Screenshot,
+    /// Turns all sound off and on again.
+    Mute,
+    Confirm,
+    Back,
+    Up,
+    Down,
+    Left,
+    Right,
+    /// Hosts a LAN lobby instead of joining one.
+    Host,
+    /// Rolls a new seed for the LAN lobby being hosted.
+    NewSeed,
+}
+
+// Screens actions and shortcuts are read on, as bits
+const PLAYING: u8 = 1;
+const PAUSED: u8 = 2;
+const GAME_OVER: u8 = 4;
+const MENUS: u8 = 8;
+const LOBBY: u8 = 16;
+
+/// Keys read directly rather than through an action, with what they do and the screens
+/// they do it on. No action can be bound to one of them on those screens.
+const SHORTCUTS: [(KeyCode, &str, u8); 14] = [
+    (KeyCode::KeyV, "versus", MENUS),
+    (KeyCode::KeyX, "split screen", MENUS),
+    (KeyCode::KeyC, "co-op", MENUS),
+    (KeyCode::KeyT, "tournament", MENUS),
+    (KeyCode::KeyL, "LAN", MENUS),
+    (KeyCode::KeyH, "challenge", MENUS),
+    (KeyCode::KeyY, "party", MENUS),
+    (KeyCode::KeyK, "controls", MENUS),
+    (KeyCode::KeyO, "settings", MENUS | PAUSED),
+    (KeyCode::KeyC, "copy code", GAME_OVER),
+    (KeyCode::Period, "TAS step", PAUSED | GAME_OVER),
+    (KeyCode::Comma, "TAS rewind", PAUSED | GAME_OVER),
+    (KeyCode::KeyJ, "TAS jump", PAUSED | GAME_OVER),
+    (KeyCode::F5, "TAS save", PAUSED | GAME_OVER),
+];
+
+/// The fixed shortcut, if any, that `binding` would clash with on a screen where `action`
+/// is read.
+pub fn shortcut(action: Action, binding: Binding) -> Option<&'static str> {
+    SHORTCUTS
+        .into_iter()
+        .find(|&(key, _, contexts)| {
+            binding == Binding::Key(key) && contexts & action.contexts() != 0
+        })
+        .map(|(_, name, _)| name)
+}
+
+impl Action {
+    pub const ALL: [Action; 14] = [
+        Action::Jump,
+        Action::JumpTwo,
+        Action::Pause,
+        Action::Restart,
+        Action::Screenshot,
+        Action::Mute,
+        Action::Confirm,
+        Action::Back,
+        Action::Up,
+        Action::Down,
+        Action::Left,
+        Action::Right,
+        Action::Host,
+        Action::NewSeed,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::Jump => "Jump",
+            Action::JumpTwo => "P2 jump",
+            Action::Pause => "Pause",
+            Action::Restart => "Restart",
+            Action::Screenshot => "Screenshot",
+            Action::Mute => "Mute",
+            Action::Confirm => "Confirm",
+            Action::Back => "Back",
+            Action::Up => "Up",
+            Action::Down => "Down",
+            Action::Left => "Left",
+            Action::Right => "Right",
+            Action::Host => "Host lobby",
+            Action::NewSeed => "New seed",
+        }
+    }
+
+    /// Screens the action is read on, as a bit set; actions that share one can't share a
+    /// binding.
+    fn contexts(self) -> u8 {
+        match self {
+            Action::Jump | Action::JumpTwo => PLAYING,
+            Action::Pause | Action::Screenshot => PLAYING | PAUSED,
+            Action::Restart => GAME_OVER,
+            Action::Mute => PLAYING | PAUSED | GAME_OVER | MENUS,
+            Action::Confirm
+            | Action::Back
+            | Action::Up
+            | Action::Down
+            | Action::Left
+            | Action::Right => MENUS | LOBBY,
+            Action::Host | Action::NewSeed => LOBBY,
+        }
+    }
+
+    /// Menu direction the action moves the button focus in, in UI coordinates (y down).
+    fn direction(self) -> Option<Vec2> {
+        match self {
+            Action::Up => Some(Vec2::NEG_Y),
+            Action::Down => Some(Vec2::Y),
+            Action::Left => Some(Vec2::NEG_X),
+            Action::Right => Some(Vec2::X),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Binding {
+    Key(KeyCode),
+    /// Ignored while the pointer is over a button, so clicks only press the button.
+    Mouse(MouseButton),
+    /// A new touch anywhere, ignored over buttons like the mouse.
+    Touch,
+    /// A button on any gamepad, except that jumps go to the pad's own player.
+    Gamepad(GamepadButtonType),
+}
+
+/// Bindings for every action. Saved as a map from action to bindings; actions missing
+/// from a saved map keep their defaults.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(
+    from = "HashMap<Action, Vec<Binding>>",
+    into = "BTreeMap<Action, Vec<Binding>>"
+)]
+pub struct InputMap {
+    bindings: HashMap<Action, Vec<Binding>>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        use Binding::{Gamepad as Pad, Key, Mouse, Touch};
+        use GamepadButtonType as Button;
+        let bindings = Action::ALL.map(|action| {
+            let bindings = match action {
+                Action::Jump => vec![
+                    Key(KeyCode::Space),
+                    Mouse(MouseButton::Left),
+                    Touch,
+                    Pad(Button::South),
+                ],
+                Action::JumpTwo => vec![Key(KeyCode::ArrowUp)],
+                Action::Pause => vec![Key(KeyCode::KeyP), Key(KeyCode::Escape), Pad(Button::Start)],
+                Action::Restart => vec![Key(KeyCode::KeyR), Pad(Button::Start)],
+                Action::Screenshot => vec![Key(KeyCode::KeyS), Pad(Button::Select)],
+                Action::Mute => vec![Key(KeyCode::KeyM)],
+                Action::Confirm => vec![
+                    Key(KeyCode::Enter),
+                    Key(KeyCode::Space),
+                    Mouse(MouseButton::Left),
+                    Touch,
+                    Pad(Button::South),
+                ],
+                Action::Back => vec![Key(KeyCode::Escape), Pad(Button::East)],
+                Action::Up => vec![Key(KeyCode::ArrowUp), Pad(Button::DPadUp)],
+                Action::Down => vec![Key(KeyCode::ArrowDown), Pad(Button::DPadDown)],
+                Action::Left => vec![Key(KeyCode::ArrowLeft), Pad(Button::DPadLeft)],
+                Action::Right => vec![Key(KeyCode::ArrowRight), Pad(Button::DPadRight)],
+                Action::Host => vec![Key(KeyCode::KeyH), Pad(Button::North)],
+                Action::NewSeed => vec![Key(KeyCode::KeyN), Pad(Button::West)],
+            };
+            (action, bindings)
+        });
+        Self {
+            bindings: bindings.into_iter().collect(),
+        }
+    }
+}
+
+impl From<HashMap<Action, Vec<Binding>>> for InputMap {
+    fn from(saved: HashMap<Action, Vec<Binding>>) -> Self {
+        let mut map = Self::default();
+        map.bindings.extend(saved);
+        // Files written before a shortcut was reserved may still bind its key
+        for (&action, bindings) in map.bindings.iter_mut() {
+            bindings.retain(|&binding| shortcut(action, binding).is_none());
+        }
+        map
+    }
+}
+
+// Saved in `Action` order so the file reads like the controls screen
+impl From<InputMap> for BTreeMap<Action, Vec<Binding>> {
+    fn from(map: InputMap) -> Self {
+        map.bindings.into_iter().collect()
+    }
+}
+
+impl InputMap {
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Makes `binding` the action's only binding on its device, keeping the others.
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        let bindings = self.bindings.entry(action).or_default();
+        bindings.retain(|other| !other.same_device(binding));
+        bindings.push(binding);
+    }
+
+    /// Other actions that already use `binding` on a screen where `action` is read.
+    pub fn conflicts(&self, action: Action, binding: Binding) -> Vec<Action> {
+        Action::ALL
+            .into_iter()
+            .filter(|&other| {
+                other != action
+                    && other.contexts() & action.contexts() != 0
+                    && self.bindings(other).contains(&binding)
+            })
+            .collect()
+    }
+
+    pub fn unbind(&mut self, action: Action, binding: Binding) {
+        if let Some(bindings) = self.bindings.get_mut(&action) {
+            bindings.retain(|&other| other != binding);
+        }
+    }
+}
+
+impl Binding {
+    fn same_device(self, other: Binding) -> bool {
+        std::mem::discriminant(&self) == std::mem::discriminant(&other)
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            Binding::Key(key) => {
+                let name = format!("{key:?}");
+                let short = name
+                    .strip_prefix("Key")
+                    .or_else(|| name.strip_prefix("Digit"))
+                    .or_else(|| name.strip_prefix("Arrow"));
+                short.unwrap_or(&name).to_string()
+            }
+            Binding::Mouse(MouseButton::Left) => "Left click".to_string(),
+            Binding::Mouse(MouseButton::Right) => "Right click".to_string(),
+            Binding::Mouse(MouseButton::Middle) => "Middle click".to_string(),
+            Binding::Mouse(button) => format!("Mouse {button:?}"),
+            Binding::Touch => "Tap".to_string(),
+            Binding::Gamepad(button) => format!("Pad {button:?}"),
+        }
+    }
+}
+
+/// Actions newly pressed this frame, rebuilt from the raw input in `PreUpdate`.
+#[derive(Resource, Default)]
+pub struct Actions {
+    just_pressed: HashSet<Action>,
+    /// Directions the left sticks were pushed in last frame, to press them once per push.
+    stick: HashSet<Action>,
+}
+
+impl Actions {
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+}
+
+/// How far a stick has to be pushed to count as a direction.
+const STICK_THRESHOLD: f32 = 0.5;
+
+/// Reads keyboard, mouse, touch and gamepad state into [`Actions`]. Injected input events
+/// reach it the same way as real ones, through Bevy's input resources.
+pub fn update_actions(
+    settings: Res<Settings>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+    ui_buttons: Query<&Interaction, With<Button>>,
+    gamepads: Res<Gamepads>,
+    pad_buttons: Res<ButtonInput<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    mut actions: ResMut<Actions>,
+) {
+    let mut pads: Vec<Gamepad> = gamepads.iter().collect();
+    pads.sort_by_key(|pad| pad.id);
+
+    // Needs this frame's `Interaction`s, so runs after `UiSystem::Focus`
+    let over_button = ui_buttons
+        .iter()
+        .any(|interaction| *interaction != Interaction::None);
+
+    let actions = &mut *actions;
+    actions.just_pressed.clear();
+    for action in Action::ALL {
+        for &binding in settings.controls.bindings(action) {
+            match binding {
+                Binding::Key(key) => {
+                    if keyboard.just_pressed(key) {
+                        actions.just_pressed.insert(action);
+                    }
+                }
+                Binding::Mouse(button) => {
+                    if mouse.just_pressed(button) && !over_button {
+                        actions.just_pressed.insert(action);
+                    }
+                }
+                Binding::Touch => {
+                    if touches.any_just_pressed() && !over_button {
+                        actions.just_pressed.insert(action);
+                    }
+                }
+                Binding::Gamepad(button_type) => {
+                    for (index, &pad) in pads.iter().enumerate() {
+                        // Each pad flies its own bird: the second one's jumps are player two's
+                        let target = match (action, index) {
+                            (Action::Jump, 1) => Action::JumpTwo,
+                            _ => action,
+                        };
+                        if pad_buttons.just_pressed(GamepadButton::new(pad, button_type)) {
+                            actions.just_pressed.insert(target);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Left sticks navigate like the d-pad
+    let mut stick = HashSet::new();
+    for &pad in &pads {
+        let x = axes
+            .get(GamepadAxis::new(pad, GamepadAxisType::LeftStickX))
+            .unwrap_or(0.0);
+        let y = axes
+            .get(GamepadAxis::new(pad, GamepadAxisType::LeftStickY))
+            .unwrap_or(0.0);
+        for (action, value) in [
+            (Action::Right, x),
+            (Action::Left, -x),
+            (Action::Up, y),
+            (Action::Down, -y),
+        ] {
+            if value > STICK_THRESHOLD {
+                stick.insert(action);
+            }
+        }
+    }
+    for &action in stick.difference(&actions.stick) {
+        actions.just_pressed.insert(action);
+    }
+    actions.stick = stick;
+}
+
+/// How long after a run ends clicks and taps are kept off the buttons.
+const POINTER_GUARD: Duration = Duration::from_millis(400);
+
+/// Time until which UI buttons ignore clicks and taps, so one meant as a flap just as the
+/// bird crashes doesn't press RESTART.
+#[derive(Resource, Default)]
+pub struct PointerGuard(Duration);
+
+impl PointerGuard {
+    pub fn active(&self, time: &Time) -> bool {
+        time.elapsed() < self.0
+    }
+}
+
+pub fn raise_pointer_guard(time: Res<Time>, mut guard: ResMut<PointerGuard>) {
+    guard.0 = time.elapsed() + POINTER_GUARD;
+}
+
+/// Button that Confirm presses, moved with the arrow keys, d-pad or stick. `None` until
+/// the player navigates, which means the top-left button.
+#[derive(Resource, Default)]
+pub struct ButtonFocus(pub Option<Entity>);
+
+impl ButtonFocus {
+    /// The focused button, falling back to the top-left one.
+    pub fn current(
+        &self,
+        buttons: &Query<(Entity, &GlobalTransform), With<StartButton>>,
+    ) -> Option<Entity> {
+        self.0
+            .filter(|&entity| buttons.contains(entity))
+            .or_else(|| {
+                buttons
+                    .iter()
+                    .min_by(|(_, a), (_, b)| {
+                        let (a, b) = (a.translation(), b.translation());
+                        (a.y, a.x)
+                            .partial_cmp(&(b.y, b.x))
+                            .unwrap_or(std::cmp::Ordering::Equal)
+                    })
+                    .map(|(entity, _)| entity)
+            })
+    }
+}
+
+/// Moves the focus to the nearest button in the pressed direction and outlines it.
+pub fn navigate_buttons(
+    actions: Res<Actions>,
+    mut focus: ResMut<ButtonFocus>,
+    buttons: Query<(Entity, &GlobalTransform), With<StartButton>>,
+    mut borders: Query<(Entity, &mut BorderColor), With<StartButton>>,
+) {
+    let current = focus.current(&buttons);
+    if let Some((_, from)) = current.and_then(|entity| buttons.get(entity).ok()) {
+        let from = from.translation().truncate();
+        for direction in Action::ALL.iter().filter_map(|&action| {
+            actions
+                .just_pressed(action)
+                .then(|| action.direction())
+                .flatten()
+        }) {
+            // Prefer buttons straight ahead over nearer ones off to the side
+            let next = buttons
+                .iter()
+                .filter_map(|(entity, transform)| {
+                    let offset = transform.translation().truncate() - from;
+                    let ahead = offset.dot(direction);
+                    let aside = offset.perp_dot(direction).abs();
+                    (ahead > 1.0).then_some((entity, ahead + 2.0 * aside))
+                })
+                .min_by(|(_, a), (_, b)| a.total_cmp(b));
+            if let Some((entity, _)) = next {
+                focus.0 = Some(entity);
+            }
+        }
+    }
+
+    let current = focus.current(&buttons);
+    for (entity, mut border) in borders.iter_mut() {
+        let color = if Some(entity) == current {
+            Color::WHITE
+        } else {
+            Color::NONE
+        };
+        if border.0 != color {
+            border.0 = color;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::input::gamepad::{
+        GamepadAxisChangedEvent, GamepadButtonChangedEvent, GamepadConnection,
+        GamepadConnectionEvent, GamepadEvent, GamepadInfo,
+    };
+    use bevy::input::keyboard::{Key, KeyboardInput};
+    use bevy::input::{ButtonState, InputPlugin, InputSystem};
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, InputPlugin))
+            .init_resource::<Settings>()
+            .init_resource::<Actions>()
+            .init_resource::<ButtonFocus>()
+            .add_systems(PreUpdate, update_actions.after(InputSystem))
+            .add_systems(Update, navigate_buttons);
+        app
+    }
+
+    fn key(app: &mut App, key_code: KeyCode, state: ButtonState) {
+        app.world_mut().send_event(KeyboardInput {
+            key_code,
+            logical_key: Key::Unidentified(bevy::input::keyboard::NativeKey::Unidentified),
+            state,
+            window: Entity::PLACEHOLDER,
+        });
+    }
+
+    fn connect_pads(app: &mut App, count: usize) {
+        for id in 0..count {
+            app.world_mut()
+                .send_event(GamepadEvent::Connection(GamepadConnectionEvent::new(
+                    Gamepad::new(id),
+                    GamepadConnection::Connected(GamepadInfo {
+                        name: format!("Pad {id}"),
+                    }),
+                )));
+        }
+        app.update();
+    }
+
+    fn pad_button(app: &mut App, id: usize, button: GamepadButtonType, value: f32) {
+        app.world_mut()
+            .send_event(GamepadEvent::Button(GamepadButtonChangedEvent::new(
+                Gamepad::new(id),
+                button,
+                value,
+            )));
+    }
+
+    fn pressed(app: &App) -> Vec<Action> {
+        let actions = app.world().resource::<Actions>();
+        Action::ALL
+            .into_iter()
+            .filter(|&action| actions.just_pressed(action))
+            .collect()
+    }
+
+    /// Four buttons in a square, 100 units apart, in UI coordinates.
+    fn spawn_grid(app: &mut App) -> [Entity; 4] {
+        [(0.0, 0.0), (100.0, 0.0), (0.0, 100.0), (100.0, 100.0)].map(|(x, y)| {
+            app.world_mut()
+                .spawn((
+                    StartButton,
+                    GlobalTransform::from_xyz(x, y, 0.0),
+                    BorderColor::default(),
+                ))
+                .id()
+        })
+    }
+
+    #[test]
+    fn keys_press_every_action_bound_to_them_once() {
+        let mut app = app();
+        key(&mut app, KeyCode::Space, ButtonState::Pressed);
+        app.update();
+        assert_eq!(pressed(&app), [Action::Jump, Action::Confirm]);
+
+        // Held, not pressed again
+        app.update();
+        assert_eq!(pressed(&app), []);
+
+        key(&mut app, KeyCode::Space, ButtonState::Released);
+        key(&mut app, KeyCode::Escape, ButtonState::Pressed);
+        app.update();
+        assert_eq!(pressed(&app), [Action::Pause, Action::Back]);
+    }
+
+    #[test]
+    fn rebound_keys_follow_the_settings() {
+        let mut app = app();
+        app.world_mut()
+            .resource_mut::<Settings>()
+            .controls
+            .rebind(Action::Jump, Binding::Key(KeyCode::KeyW));
+        key(&mut app, KeyCode::KeyW, ButtonState::Pressed);
+        key(&mut app, KeyCode::Space, ButtonState::Pressed);
+        app.update();
+        assert_eq!(pressed(&app), [Action::Jump, Action::Confirm]);
+
+        key(&mut app, KeyCode::Space, ButtonState::Released);
+        key(&mut app, KeyCode::Space, ButtonState::Pressed);
+        app.update();
+        assert_eq!(pressed(&app), [Action::Confirm]);
+    }
+
+    #[test]
+    fn each_gamepad_jumps_for_its_own_player() {
+        let mut app = app();
+        connect_pads(&mut app, 2);
+
+        pad_button(&mut app, 1, GamepadButtonType::South, 1.0);
+        app.update();
+        assert_eq!(pressed(&app), [Action::JumpTwo, Action::Confirm]);
+
+        pad_button(&mut app, 0, GamepadButtonType::South, 1.0);
+        app.update();
+        assert_eq!(pressed(&app), [Action::Jump, Action::Confirm]);
+
+        pad_button(&mut app, 0, GamepadButtonType::North, 1.0);
+        app.update();
+        assert_eq!(pressed(&app), [Action::Host]);
+    }
+
+    #[test]
+    fn arrows_d_pad_and_stick_move_the_focus() {
+        let mut app = app();
+        connect_pads(&mut app, 1);
+        let [top_left, top_right, bottom_left, bottom_right] = spawn_grid(&mut app);
+        let focus = |app: &App| app.world().resource::<ButtonFocus>().0;
+
+        key(&mut app, KeyCode::ArrowRight, ButtonState::Pressed);
+        app.update();
+        assert_eq!(focus(&app), Some(top_right));
+
+        pad_button(&mut app, 0, GamepadButtonType::DPadDown, 1.0);
+        app.update();
+        assert_eq!(focus(&app), Some(bottom_right));
+        assert_eq!(
+            app.world().get::<BorderColor>(bottom_right).unwrap().0,
+            Color::WHITE
+        );
+        assert_eq!(
+            app.world().get::<BorderColor>(top_right).unwrap().0,
+            Color::NONE
+        );
+
+        // A stick push moves once however long it is held
+        app.world_mut()
+            .send_event(GamepadEvent::Axis(GamepadAxisChangedEvent::new(
+                Gamepad::new(0),
+                GamepadAxisType::LeftStickX,
+                -1.0,
+            )));
+        app.update();
+        app.update();
+        assert_eq!(focus(&app), Some(bottom_left));
+
+        // Nothing further up than the top row
+        key(&mut app, KeyCode::ArrowUp, ButtonState::Pressed);
+        app.update();
+        key(&mut app, KeyCode::ArrowUp, ButtonState::Released);
+        app.update();
+        key(&mut app, KeyCode::ArrowUp, ButtonState::Pressed);
+        app.update();
+        assert_eq!(focus(&app), Some(top_left));
+    }
+
+    #[test]
+    fn conflicts_only_count_actions_read_on_the_same_screen() {
+        let map = InputMap::default();
+        let key = |code| Binding::Key(code);
+        // S takes screenshots while playing, and the menus don't read it
+        assert_eq!(
+            map.conflicts(Action::Jump, key(KeyCode::KeyS)),
+            [Action::Screenshot]
+        );
+        assert_eq!(map.conflicts(Action::Confirm, key(KeyCode::KeyS)), []);
+        // Space both jumps and confirms, which never share a screen
+        assert_eq!(map.conflicts(Action::Jump, key(KeyCode::Space)), []);
+        // M mutes everywhere but in the lobby
+        assert_eq!(
+            map.conflicts(Action::Restart, key(KeyCode::KeyM)),
+            [Action::Mute]
+        );
+        assert_eq!(map.conflicts(Action::NewSeed, key(KeyCode::KeyM)), []);
+        assert_eq!(
+            map.conflicts(Action::NewSeed, key(KeyCode::KeyH)),
+            [Action::Host]
+        );
+        assert_eq!(
+            map.conflicts(Action::Host, Binding::Gamepad(GamepadButtonType::South)),
+            [Action::Confirm]
+        );
+    }
+
+    #[test]
+    fn shortcut_keys_are_reserved_where_they_are_read() {
+        let key = |code| Binding::Key(code);
+        // C starts co-op in the menu, so Confirm on C would start two games at once
+        assert_eq!(shortcut(Action::Confirm, key(KeyCode::KeyC)), Some("co-op"));
+        assert_eq!(
+            shortcut(Action::Restart, key(KeyCode::KeyC)),
+            Some("copy code")
+        );
+        assert_eq!(
+            shortcut(Action::Pause, key(KeyCode::KeyO)),
+            Some("settings")
+        );
+        // Nothing reads shortcuts while playing
+        assert_eq!(shortcut(Action::Jump, key(KeyCode::KeyC)), None);
+        assert_eq!(shortcut(Action::Host, key(KeyCode::KeyH)), None);
+        assert_eq!(shortcut(Action::Confirm, Binding::Touch), None);
+
+        for action in Action::ALL {
+            for &binding in InputMap::default().bindings(action) {
+                assert_eq!(shortcut(action, binding), None, "{action:?} {binding:?}");
+            }
+        }
+
+        // A saved file from before the shortcut was reserved loses only that binding
+        let saved: InputMap =
+            serde_json::from_str(r#"{"confirm": [{"key": "KeyC"}, {"key": "Enter"}]}"#).unwrap();
+        assert_eq!(saved.bindings(Action::Confirm), [key(KeyCode::Enter)]);
+    }
+
+    #[test]
+    fn rebinding_replaces_only_the_same_device() {
+        let mut map = InputMap::default();
+        map.rebind(Action::Jump, Binding::Key(KeyCode::KeyW));
+        assert_eq!(
+            map.bindings(Action::Jump),
+            [
+                Binding::Mouse(MouseButton::Left),
+                Binding::Touch,
+                Binding::Gamepad(GamepadButtonType::South),
+                Binding::Key(KeyCode::KeyW),
+            ]
+        );
+        // Other actions keep theirs, even where they overlapped
+        assert!(map
+            .bindings(Action::Confirm)
+            .contains(&Binding::Key(KeyCode::Space)));
+
+        map.rebind(Action::Pause, Binding::Key(KeyCode::Tab));
+        assert_eq!(
+            map.bindings(Action::Pause),
+            [
+                Binding::Gamepad(GamepadButtonType::Start),
+                Binding::Key(KeyCode::Tab),
+            ]
+        );
+        map.unbind(Action::Pause, Binding::Key(KeyCode::Tab));
+        assert_eq!(
+            map.bindings(Action::Pause),
+            [Binding::Gamepad(GamepadButtonType::Start)]
+        );
+    }
+}
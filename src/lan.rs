@@ -0,0 +1,290 @@
+use bevy::prelude::*;
+use rlappy_bev::lobby::{ClientLobby, HostLobby, LobbySocket, MatchMode, MatchStart, Member};
+
+use crate::input::{Action, Actions};
+use crate::online::OnlineMatch;
+use crate::{start_game, ActiveConfig, GameMode, GameState, MatchSeed, SeedConfig};
+
+/// Name shown to other players in LAN lobbies, from `--name` or the login name.
+#[derive(Resource)]
+pub struct PlayerName(pub String);
+
+impl Default for PlayerName {
+    fn default() -> Self {
+        let name = std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .unwrap_or_else(|_| "Player".to_string());
+        Self(name)
+    }
+}
+
+/// Present while `GameState::Lobby` is active.
+#[derive(Resource)]
+pub enum LanLobby {
+    Browsing {
+        client: ClientLobby,
+        selected: usize,
+    },
+    Hosting(HostLobby),
+    /// The lobby socket could not be opened, or the match could not be set up.
+    Unavailable(String),
+}
+
+impl LanLobby {
+    fn browse(name: &str) -> Self {
+        match LobbySocket::bind() {
+            Ok(socket) => LanLobby::Browsing {
+                client: ClientLobby::new(socket, name.to_string()),
+                selected: 0,
+            },
+            Err(err) => LanLobby::Unavailable(format!("No free lobby port: {err}")),
+        }
+    }
+
+    fn host(name: &str, seed: u64, config: rlappy_bev::sim::GameConfig) -> Self {
+        match LobbySocket::bind() {
+            Ok(socket) => LanLobby::Hosting(HostLobby::new(socket, name.to_string(), seed, config)),
+            Err(err) => LanLobby::Unavailable(format!("No free lobby port: {err}")),
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct LobbyUi;
+
+pub fn open_lobby(mut commands: Commands, name: Res<PlayerName>) {
+    commands.insert_resource(LanLobby::browse(&name.0));
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 24.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(40.0),
+            left: Val::Px(60.0),
+            ..default()
+        }),
+        LobbyUi,
+    ));
+}
+
+pub fn close_lobby(mut commands: Commands, lobby: Option<ResMut<LanLobby>>) {
+    if let Some(mut lobby) = lobby {
+        if let LanLobby::Browsing { client, .. } = &mut *lobby {
+            client.leave();
+        }
+    }
+    commands.remove_resource::<LanLobby>();
+}
+
+/// Browse with Up/Down and Confirm, ready up with Confirm once joined, or host with Host;
+/// the host cycles the mode with Left/Right, rolls a new seed with NewSeed and starts with
+/// Confirm.
+pub fn lobby_controls(
+    actions: Res<Actions>,
+    mut lobby: ResMut<LanLobby>,
+    name: Res<PlayerName>,
+    seed_config: Res<SeedConfig>,
+    mut config: ResMut<ActiveConfig>,
+    mut mode: ResMut<GameMode>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    let escape = actions.just_pressed(Action::Back);
+    let confirm = actions.just_pressed(Action::Confirm);
+    let mut start = None;
+    let mut switch_to = None;
+
+    match &mut *lobby {
+        LanLobby::Unavailable(_) => {
+            if escape {
+                next_state.set(GameState::Menu);
+            }
+        }
+        LanLobby::Browsing { client, selected } => {
+            start = client.poll();
+            if client.is_joined() {
+                if confirm {
+                    let ready = !client.is_ready();
+                    client.set_ready(ready);
+                }
+                if escape {
+                    client.leave();
+                }
+            } else {
+                let listings = client.listings();
+                if actions.just_pressed(Action::Down) {
+                    *selected += 1;
+                }
+                if actions.just_pressed(Action::Up) {
+                    *selected = selected.saturating_sub(1);
+                }
+                *selected = (*selected).min(listings.len().saturating_sub(1));
+
+                if confirm {
+                    if let Some(listing) = listings.get(*selected) {
+                        client.join(listing);
+                    }
+                } else if actions.just_pressed(Action::Host) {
+                    switch_to = Some(true);
+                } else if escape {
+                    next_state.set(GameState::Menu);
+                }
+            }
+        }
+        LanLobby::Hosting(host) => {
+            start = host.poll();
+            // Players may already be on their way, so a started lobby can't change or close
+            if start.is_none() && !host.is_starting() {
+                if actions.just_pressed(Action::Left) || actions.just_pressed(Action::Right) {
+                    host.mode = host.mode.next();
+                    host.send_roster();
+                }
+                if actions.just_pressed(Action::NewSeed) {
+                    host.seed = rand::random();
+                    host.send_roster();
+                }
+                if confirm {
+                    host.start();
+                } else if escape {
+                    switch_to = Some(false);
+                }
+            }
+        }
+    }
+
+    // Only one lobby socket at a time, so the old one is closed before the new one binds
+    if let Some(hosting) = switch_to {
+        *lobby = LanLobby::Unavailable(String::new());
+        *lobby = if hosting {
+            let seed = seed_config.0.unwrap_or_else(rand::random);
+            LanLobby::host(&name.0, seed, config.0)
+        } else {
+            LanLobby::browse(&name.0)
+        };
+    }
+
+    let Some(start) = start else {
+        return;
+    };
+    config.0 = start.config;
+    match start.mode {
+        MatchMode::Race => {
+            commands.insert_resource(MatchSeed(start.seed));
+            *mode = GameMode::Solo;
+            start_game(
+                &mut commands,
+                &asset_server,
+                &mut texture_atlas_layouts,
+                *mode,
+                &mut next_state,
+            );
+        }
+        MatchMode::Versus => match online_match(&start) {
+            // The online lobby in the menu finishes the handshake and starts play
+            Ok(online) => {
+                commands.insert_resource(online);
+                next_state.set(GameState::Menu);
+            }
+            Err(err) => *lobby = LanLobby::Unavailable(format!("Could not start the match: {err}")),
+        },
+    }
+}
+
+fn online_match(start: &MatchStart) -> std::io::Result<OnlineMatch> {
+    if start.hosting {
+        OnlineMatch::host(start.game_addr.port(), start.seed, start.config)
+    } else {
+        OnlineMatch::join(&start.game_addr.to_string())
+    }
+}
+
+pub fn update_lobby_text(lobby: Res<LanLobby>, mut query: Query<&mut Text, With<LobbyUi>>) {
+    let value = match &*lobby {
+        LanLobby::Unavailable(message) => format!("{message}\n\nEsc back"),
+        LanLobby::Browsing { client, selected } => match client.roster() {
+            Some((members, mode, seed)) => format!(
+                "LOBBY  {} on seed {seed}\n\n{}\n\nEnter {}  Esc leave",
+                mode.label(),
+                roster_lines(members),
+                if client.is_ready() {
+                    "not ready"
+                } else {
+                    "ready"
+                },
+            ),
+            None if client.is_joined() => "Joining...\n\nEsc cancel".to_string(),
+            None => {
+                let listings = client.listings();
+                let lines = if listings.is_empty() {
+                    "Looking for lobbies on the network...".to_string()
+                } else {
+                    listings
+                        .iter()
+                        .enumerate()
+                        .map(|(i, listing)| {
+                            format!(
+                                "{} {}  {}  {} playing",
+                                if i == *selected { ">" } else { " " },
+                                listing.name,
+                                listing.mode.label(),
+                                listing.members,
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                };
+                format!("LAN LOBBIES\n\n{lines}\n\nUp/Down select  Enter join  H host  Esc back")
+            }
+        },
+        LanLobby::Hosting(host) => {
+            let players = host.members().len();
+            let status = if !host.mode.players().contains(&players) {
+                format!(
+                    "{} needs {} to {} players",
+                    host.mode.label(),
+                    host.mode.players().start(),
+                    host.mode.players().end()
+                )
+            } else if host.is_starting() {
+                "Starting...".to_string()
+            } else if host.can_start() {
+                "Enter to start".to_string()
+            } else {
+                "Waiting for everyone to ready up".to_string()
+            };
+            format!(
+                "HOSTING  {} on seed {}\n\n{}\n\n{status}\nLeft/Right mode  N new seed  Esc close",
+                host.mode.label(),
+                host.seed,
+                roster_lines(&host.members()),
+            )
+        }
+    };
+
+    for mut text in query.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+fn roster_lines(members: &[Member]) -> String {
+    members
+        .iter()
+        .enumerate()
+        .map(|(i, member)| {
+            let role = if i == 0 { " (host)" } else { "" };
+            let ready = if member.ready { "ready" } else { "not ready" };
+            format!("  {}{role}  {ready}", member.name)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,401 @@
+//! Leaderboard with verified scores: clients submit a replay with their run, and the server
+//! re-simulates it and only ranks the score the inputs actually reach.
+//!
+//! The HTTP side is deliberately minimal (one request per connection, JSON bodies with a
+//! `Content-Length`), enough for the game and for `curl`:
+//! - `POST /scores` with a [`Submission`]: `201` with an [`Accepted`], `422` when the
+//!   replay doesn't reach the claimed score, `400` for anything malformed
+//! - `GET /scores`: the top [`Entry`]s, best first
+
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::replay::Replay;
+use crate::sim::GameConfig;
+
+/// Entries returned by `GET /scores`.
+pub const TOP_ENTRIES: usize = 20;
+/// Replays longer than this (an hour of play) are refused rather than re-simulated.
+pub const MAX_TICKS: u32 = 60 * 60 * 60;
+const MAX_BODY: usize = 1 << 20;
+const MAX_NAME: usize = 24;
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Submission {
+    pub name: String,
+    /// Must carry the claimed score in `final_score`.
+    pub replay: Replay,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub name: String,
+    pub score: u32,
+    pub seed: u64,
+    pub ticks: u32,
+    /// Unix time in seconds.
+    pub submitted_at: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Accepted {
+    pub score: u32,
+    /// 1-based position on the board.
+    pub rank: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ErrorBody {
+    error: String,
+}
+
+/// Why a submission was turned down.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Rejection {
+    /// The request itself was unusable (HTTP 400).
+    Malformed(String),
+    /// The replay was read fine but doesn't support the score (HTTP 422).
+    Unverified(String),
+}
+
+/// Re-simulates a submission and returns the score it earned.
+pub fn verify(submission: &Submission) -> Result<u32, Rejection> {
+    let replay = &submission.replay;
+    let name = submission.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME {
+        return Err(Rejection::Malformed(format!(
+            "name must be 1 to {MAX_NAME} characters"
+        )));
+    }
+    let Some(claimed) = replay.final_score else {
+        return Err(Rejection::Malformed(
+            "replay has no final_score".to_string(),
+        ));
+    };
+    if replay.ticks > MAX_TICKS {
+        return Err(Rejection::Malformed(format!(
+            "replay is longer than {MAX_TICKS} ticks"
+        )));
+    }
+    if replay.config != GameConfig::default() {
+        return Err(Rejection::Unverified(
+            "only runs with the default config are ranked".to_string(),
+        ));
+    }
+
+    let sim = replay.simulate();
+    if sim.score != claimed {
+        return Err(Rejection::Unverified(format!(
+            "claimed {claimed} but the replay scores {}",
+            sim.score
+        )));
+    }
+    Ok(sim.score)
+}
+
+/// Ranked entries, best score first, optionally kept in a JSON file.
+#[derive(Default)]
+pub struct Board {
+    entries: Vec<Entry>,
+    path: Option<PathBuf>,
+}
+
+impl Board {
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Loads the board from `path`, starting empty if the file doesn't exist yet.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let entries = match std::fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).map_err(io::Error::other)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err),
+        };
+        Ok(Self {
+            entries,
+            path: Some(path),
+        })
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Verifies and records a submission.
+    pub fn submit(&mut self, submission: &Submission) -> Result<Accepted, Rejection> {
+        let score = verify(submission)?;
+        let entry = Entry {
+            name: submission.name.trim().to_string(),
+            score,
+            seed: submission.replay.seed,
+            ticks: submission.replay.ticks,
+            submitted_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_secs()),
+        };
+        // Equal scores keep their order, so the earlier run ranks higher
+        let rank = self.entries.partition_point(|other| other.score >= score);
+        self.entries.insert(rank, entry);
+        if let Some(path) = &self.path {
+            let json = serde_json::to_string_pretty(&self.entries).map_err(io::Error::other);
+            if let Err(err) = json.and_then(|json| std::fs::write(path, json + "\n")) {
+                eprintln!("Failed to save leaderboard to {}: {err}", path.display());
+            }
+        }
+        Ok(Accepted {
+            score,
+            rank: rank + 1,
+        })
+    }
+}
+
+/// Answers requests on `listener` until the process exits, one thread per connection.
+pub fn serve(listener: TcpListener, board: Arc<Mutex<Board>>) {
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else { continue };
+        let board = Arc::clone(&board);
+        std::thread::spawn(move || {
+            if let Err(err) = handle(stream, &board) {
+                eprintln!("Leaderboard request failed: {err}");
+            }
+        });
+    }
+}
+
+/// Starts a server on `addr` in a background thread and returns the address it bound.
+pub fn spawn(addr: impl ToSocketAddrs, board: Board) -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind(addr)?;
+    let local = listener.local_addr()?;
+    let board = Arc::new(Mutex::new(board));
+    std::thread::spawn(move || serve(listener, board));
+    Ok(local)
+}
+
+fn handle(mut stream: TcpStream, board: &Mutex<Board>) -> io::Result<()> {
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    let (status, body) = match read_request(&mut stream) {
+        Ok((method, path, body)) => route(&method, &path, &body, board),
+        Err(err) => (400, error_json(&err.to_string())),
+    };
+    write_message(
+        &mut stream,
+        &format!("HTTP/1.1 {status} {}", reason(status)),
+        &body,
+    )
+}
+
+fn route(method: &str, path: &str, body: &[u8], board: &Mutex<Board>) -> (u16, String) {
+    let path = path.split('?').next().unwrap_or_default();
+    let mut board = board.lock().unwrap_or_else(|err| err.into_inner());
+    match (method, path) {
+        ("GET", "/scores") => {
+            let top = &board.entries()[..board.entries().len().min(TOP_ENTRIES)];
+            (200, serde_json::to_string(top).unwrap_or_default())
+        }
+        ("POST", "/scores") => {
+            let submission = match serde_json::from_slice::<Submission>(body) {
+                Ok(submission) => submission,
+                Err(err) => return (400, error_json(&err.to_string())),
+            };
+            match board.submit(&submission) {
+                Ok(accepted) => (201, serde_json::to_string(&accepted).unwrap_or_default()),
+                Err(Rejection::Malformed(reason)) => (400, error_json(&reason)),
+                Err(Rejection::Unverified(reason)) => (422, error_json(&reason)),
+            }
+        }
+        (_, "/scores") => (405, error_json("use GET or POST")),
+        _ => (404, error_json("not found")),
+    }
+}
+
+fn error_json(message: &str) -> String {
+    serde_json::to_string(&ErrorBody {
+        error: message.to_string(),
+    })
+    .unwrap_or_default()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        422 => "Unprocessable Entity",
+        _ => "Error",
+    }
+}
+
+/// Reads a request line, headers and a `Content-Length` body.
+fn read_request(stream: &mut TcpStream) -> io::Result<(String, String, Vec<u8>)> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err(io::Error::other("bad request line"));
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+    let body = read_headers_and_body(&mut reader)?;
+    Ok((method, path, body))
+}
+
+fn read_headers_and_body(reader: &mut impl BufRead) -> io::Result<Vec<u8>> {
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| io::Error::other("bad Content-Length"))?;
+            }
+        }
+    }
+    if length > MAX_BODY {
+        return Err(io::Error::other("body too large"));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(body)
+}
+
+fn write_message(stream: &mut TcpStream, start_line: &str, body: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "{start_line}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+/// Sends one request to a leaderboard at `addr` (`host:port`, with or without `http://`)
+/// and returns the status and body.
+fn request(addr: &str, method: &str, path: &str, body: &str) -> io::Result<(u16, String)> {
+    let host = addr.trim_start_matches("http://").trim_end_matches('/');
+    let mut stream = TcpStream::connect(host)?;
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    write_message(
+        &mut stream,
+        &format!("{method} {path} HTTP/1.1\r\nHost: {host}"),
+        body,
+    )?;
+
+    let mut reader = BufReader::new(stream);
+    let mut status_line = String::new();
+    reader.read_line(&mut status_line)?;
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| io::Error::other("bad status line"))?;
+    let body = read_headers_and_body(&mut reader)?;
+    Ok((status, String::from_utf8_lossy(&body).into_owned()))
+}
+
+/// Submits a run; the inner error is the server's reason for turning it down.
+pub fn submit(addr: &str, submission: &Submission) -> io::Result<Result<Accepted, String>> {
+    let body = serde_json::to_string(submission).map_err(io::Error::other)?;
+    let (status, body) = request(addr, "POST", "/scores", &body)?;
+    if status == 201 {
+        return serde_json::from_str(&body)
+            .map(Ok)
+            .map_err(io::Error::other);
+    }
+    let reason = serde_json::from_str::<ErrorBody>(&body)
+        .map_or_else(|_| format!("HTTP {status}"), |error| error.error);
+    Ok(Err(reason))
+}
+
+pub fn top(addr: &str) -> io::Result<Vec<Entry>> {
+    let (status, body) = request(addr, "GET", "/scores", "")?;
+    if status != 200 {
+        return Err(io::Error::other(format!("HTTP {status}")));
+    }
+    serde_json::from_str(&body).map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::Bot;
+    use crate::sim::Simulation;
+
+    const SEED: u64 = 7;
+
+    /// A bot run of up to a minute, with the score it really reaches.
+    fn bot_run() -> Replay {
+        let mut sim = Simulation::new(SEED);
+        let mut bot = Bot::new(0.9, SEED);
+        let mut replay = Replay::new(SEED, GameConfig::default());
+        while !sim.is_over() && replay.ticks < 60 * 60 {
+            let jump = bot.decide(sim.observation());
+            sim.step(jump);
+            replay.push(jump);
+        }
+        replay.final_score = Some(sim.score);
+        replay
+    }
+
+    fn post(addr: &str, name: &str, replay: &Replay) -> u16 {
+        let submission = Submission {
+            name: name.to_string(),
+            replay: replay.clone(),
+        };
+        let body = serde_json::to_string(&submission).unwrap();
+        request(addr, "POST", "/scores", &body).unwrap().0
+    }
+
+    #[test]
+    fn ranks_only_runs_that_reach_their_score() {
+        let addr = spawn("127.0.0.1:0", Board::in_memory())
+            .unwrap()
+            .to_string();
+        let honest = bot_run();
+        let score = honest.final_score.unwrap();
+        assert!(score > 0, "the bot should clear a few gaps");
+
+        let mut inflated = honest.clone();
+        inflated.final_score = Some(score + 5);
+        let mut edited = honest.clone();
+        edited.jumps.truncate(edited.jumps.len() / 2);
+        let mut easier = honest.clone();
+        easier.config.pipe_gap *= 1.5;
+        let mut too_long = honest.clone();
+        too_long.ticks = MAX_TICKS + 1;
+
+        assert_eq!(post(&addr, "honest", &honest), 201);
+        assert_eq!(post(&addr, "inflated", &inflated), 422);
+        assert_eq!(post(&addr, "edited", &edited), 422);
+        assert_eq!(post(&addr, "easier", &easier), 422);
+        assert_eq!(post(&addr, " ", &honest), 400);
+        assert_eq!(post(&addr, "long", &too_long), 400);
+
+        let unnamed = format!(
+            "{{\"replay\": {}}}",
+            serde_json::to_string(&honest).unwrap()
+        );
+        assert_eq!(request(&addr, "POST", "/scores", &unnamed).unwrap().0, 400);
+
+        let entries = top(&addr).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            (entries[0].name.as_str(), entries[0].score),
+            ("honest", score)
+        );
+    }
+}
@@ -0,0 +1,18 @@
+//! Game rules shared by the windowed game and the headless tools.
+
+pub mod batch;
+pub mod bot;
+pub mod challenge;
+pub mod feed;
+pub mod leaderboard;
+pub mod lobby;
+pub mod music;
+pub mod netplay;
+pub mod raster;
+pub mod replay;
+pub mod rpc;
+pub mod sim;
+pub mod solver;
+pub mod synth;
+pub mod tournament;
+pub mod tuner;
@@ -0,0 +1,781 @@
+//! LAN lobbies: hosts advertise a match over UDP broadcast, players join and ready up, and
+//! the host starts everyone on the same mode and seed.
+//!
+//! Every instance binds the first free port in [`LOBBY_PORTS`] and hosts broadcast their
+//! advert to all of them, both on the LAN broadcast address and on loopback, so several
+//! instances on one machine find each other too. Messages are small JSON documents.
+
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
+
+use crate::sim::GameConfig;
+
+pub const LOBBY_PORTS: RangeInclusive<u16> = 47800..=47815;
+/// A host runs its online match on its lobby port plus this offset.
+pub const GAME_PORT_OFFSET: u16 = 1000;
+pub const MAX_MEMBERS: usize = 8;
+
+const MAGIC: &[u8; 4] = b"RLLB";
+const ADVERT_INTERVAL: Duration = Duration::from_millis(500);
+/// Joined players repeat their state this often, which doubles as a heartbeat.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_millis(500);
+/// Hosts, and players in a host's lobby, are dropped after this long without a message.
+const MEMBER_TIMEOUT: Duration = Duration::from_secs(3);
+/// A starting host repeats the start this often to players that haven't acknowledged it.
+const START_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchMode {
+    /// Everyone plays their own run on the same course.
+    Race,
+    /// Two players head to head over the netplay protocol.
+    Versus,
+}
+
+impl MatchMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            MatchMode::Race => "Race",
+            MatchMode::Versus => "Versus",
+        }
+    }
+
+    pub fn players(self) -> RangeInclusive<usize> {
+        match self {
+            MatchMode::Race => 1..=MAX_MEMBERS,
+            MatchMode::Versus => 2..=2,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            MatchMode::Race => MatchMode::Versus,
+            MatchMode::Versus => MatchMode::Race,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Member {
+    pub name: String,
+    pub ready: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LobbyMessage {
+    Advert {
+        lobby: u64,
+        name: String,
+        members: usize,
+        mode: MatchMode,
+        seed: u64,
+    },
+    Join {
+        lobby: u64,
+        name: String,
+    },
+    /// The player's ready flag, repeated as a heartbeat while in a lobby.
+    Ready {
+        lobby: u64,
+        ready: bool,
+    },
+    Leave {
+        lobby: u64,
+    },
+    /// Sent by the host whenever the lobby changes. The host is always the first member.
+    Roster {
+        lobby: u64,
+        members: Vec<Member>,
+        mode: MatchMode,
+        seed: u64,
+    },
+    /// Repeated by the host until every player has answered with `Started` or left.
+    Start {
+        lobby: u64,
+        mode: MatchMode,
+        seed: u64,
+        config: GameConfig,
+        game_port: u16,
+    },
+    /// A player's answer to every `Start` it receives.
+    Started {
+        lobby: u64,
+    },
+}
+
+impl LobbyMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(serde_json::to_vec(self).expect("lobby messages always serialize"));
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        serde_json::from_slice(bytes.strip_prefix(MAGIC.as_slice())?).ok()
+    }
+
+    fn lobby(&self) -> u64 {
+        match self {
+            LobbyMessage::Advert { lobby, .. }
+            | LobbyMessage::Join { lobby, .. }
+            | LobbyMessage::Ready { lobby, .. }
+            | LobbyMessage::Leave { lobby }
+            | LobbyMessage::Roster { lobby, .. }
+            | LobbyMessage::Start { lobby, .. }
+            | LobbyMessage::Started { lobby } => *lobby,
+        }
+    }
+}
+
+/// What each instance needs to begin the match the host started.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MatchStart {
+    pub mode: MatchMode,
+    pub seed: u64,
+    pub config: GameConfig,
+    /// Where the host runs the online match, for modes that have one.
+    pub game_addr: SocketAddr,
+    /// Whether this instance is the one hosting it.
+    pub hosting: bool,
+}
+
+pub struct LobbySocket {
+    socket: UdpSocket,
+    /// Chance each outgoing datagram is dropped.
+    loss: f64,
+}
+
+impl LobbySocket {
+    /// Binds the first free port in [`LOBBY_PORTS`].
+    pub fn bind() -> io::Result<Self> {
+        let mut last_error = None;
+        for port in LOBBY_PORTS {
+            match UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port)) {
+                Ok(socket) => {
+                    socket.set_nonblocking(true)?;
+                    socket.set_broadcast(true)?;
+                    return Ok(Self { socket, loss: 0.0 });
+                }
+                Err(err) => last_error = Some(err),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| io::Error::other("no lobby ports")))
+    }
+
+    /// Drops `loss` of everything sent, to try the lobby against a bad network.
+    pub fn with_loss(self, loss: f64) -> Self {
+        Self { loss, ..self }
+    }
+
+    /// Whether the next datagram gets through the simulated loss.
+    fn delivers(&self) -> bool {
+        self.loss <= 0.0 || rand::random::<f64>() >= self.loss
+    }
+
+    pub fn port(&self) -> u16 {
+        self.socket.local_addr().map_or(0, |addr| addr.port())
+    }
+
+    pub fn send(&self, message: &LobbyMessage, to: SocketAddr) {
+        // Lobby traffic is repeated until it takes effect, so a lost datagram is harmless
+        if self.delivers() {
+            let _ = self.socket.send_to(&message.encode(), to);
+        }
+    }
+
+    /// Sends to every lobby port on the LAN and on this machine.
+    pub fn broadcast(&self, message: &LobbyMessage) {
+        let bytes = message.encode();
+        let own = self.port();
+        for port in LOBBY_PORTS.filter(|&port| port != own) {
+            for ip in [Ipv4Addr::BROADCAST, Ipv4Addr::LOCALHOST] {
+                if self.delivers() {
+                    let _ = self.socket.send_to(&bytes, (ip, port));
+                }
+            }
+        }
+    }
+
+    pub fn recv(&self) -> Option<(LobbyMessage, SocketAddr)> {
+        let mut buffer = [0u8; 2048];
+        loop {
+            let (len, from) = self.socket.recv_from(&mut buffer).ok()?;
+            if let Some(message) = LobbyMessage::decode(&buffer[..len]) {
+                return Some((message, from));
+            }
+        }
+    }
+}
+
+struct Guest {
+    addr: SocketAddr,
+    member: Member,
+    last_heard: Instant,
+    /// Whether the guest has acknowledged the start.
+    started: bool,
+}
+
+/// A lobby this instance is hosting. The host plays too and is always ready.
+pub struct HostLobby {
+    socket: LobbySocket,
+    id: u64,
+    name: String,
+    guests: Vec<Guest>,
+    pub mode: MatchMode,
+    pub seed: u64,
+    pub config: GameConfig,
+    last_advert: Option<Instant>,
+    /// When the start was last sent, while waiting for every guest to acknowledge it.
+    last_start: Option<Instant>,
+}
+
+impl HostLobby {
+    pub fn new(socket: LobbySocket, name: String, seed: u64, config: GameConfig) -> Self {
+        Self {
+            socket,
+            id: rand::random(),
+            name,
+            guests: Vec::new(),
+            mode: MatchMode::Race,
+            seed,
+            config,
+            last_advert: None,
+            last_start: None,
+        }
+    }
+
+    pub fn members(&self) -> Vec<Member> {
+        let host = Member {
+            name: self.name.clone(),
+            ready: true,
+        };
+        std::iter::once(host)
+            .chain(self.guests.iter().map(|guest| guest.member.clone()))
+            .collect()
+    }
+
+    /// Everyone is ready and the player count suits the mode.
+    pub fn can_start(&self) -> bool {
+        self.guests.iter().all(|guest| guest.member.ready)
+            && self.mode.players().contains(&(self.guests.len() + 1))
+    }
+
+    /// Whether the match has been started and the host is waiting for the players to
+    /// acknowledge it.
+    pub fn is_starting(&self) -> bool {
+        self.last_start.is_some()
+    }
+
+    /// Advertises the lobby and handles joins, ready changes and departures. Once started,
+    /// repeats the start instead and returns the host's own start when every player has
+    /// acknowledged it or left.
+    pub fn poll(&mut self) -> Option<MatchStart> {
+        let starting = self.is_starting();
+        let mut changed = false;
+        while let Some((message, from)) = self.socket.recv() {
+            if message.lobby() != self.id {
+                continue;
+            }
+            let known = self.guests.iter().position(|guest| guest.addr == from);
+            match (message, known) {
+                (LobbyMessage::Join { name, .. }, None)
+                    if !starting && self.guests.len() + 1 < MAX_MEMBERS =>
+                {
+                    self.guests.push(Guest {
+                        addr: from,
+                        member: Member { name, ready: false },
+                        last_heard: Instant::now(),
+                        started: false,
+                    });
+                    changed = true;
+                }
+                (LobbyMessage::Join { .. }, Some(index)) => {
+                    self.guests[index].last_heard = Instant::now();
+                }
+                // Players were told to start as they were; later changes are too late
+                (LobbyMessage::Ready { ready, .. }, Some(index)) => {
+                    let guest = &mut self.guests[index];
+                    guest.last_heard = Instant::now();
+                    if !starting {
+                        changed |= guest.member.ready != ready;
+                        guest.member.ready = ready;
+                    }
+                }
+                (LobbyMessage::Leave { .. }, Some(index)) => {
+                    self.guests.remove(index);
+                    changed = true;
+                }
+                (LobbyMessage::Started { .. }, Some(index)) => {
+                    let guest = &mut self.guests[index];
+                    guest.last_heard = Instant::now();
+                    guest.started = true;
+                }
+                _ => {}
+            }
+        }
+
+        let before = self.guests.len();
+        self.guests
+            .retain(|guest| guest.last_heard.elapsed() < MEMBER_TIMEOUT);
+        changed |= self.guests.len() != before;
+
+        if let Some(sent) = self.last_start {
+            if self.guests.iter().all(|guest| guest.started) {
+                self.last_start = None;
+                return Some(self.match_start());
+            }
+            if sent.elapsed() >= START_INTERVAL {
+                self.send_start();
+            }
+            return None;
+        }
+
+        let due = self
+            .last_advert
+            .is_none_or(|sent| sent.elapsed() >= ADVERT_INTERVAL);
+        if due {
+            self.last_advert = Some(Instant::now());
+            self.socket.broadcast(&LobbyMessage::Advert {
+                lobby: self.id,
+                name: self.name.clone(),
+                members: self.guests.len() + 1,
+                mode: self.mode,
+                seed: self.seed,
+            });
+        }
+        // The roster is also resent with every advert in case an update was lost
+        if changed || due {
+            self.send_roster();
+        }
+        None
+    }
+
+    /// Call after changing `mode` or `seed` so players see it straight away.
+    pub fn send_roster(&self) {
+        let roster = LobbyMessage::Roster {
+            lobby: self.id,
+            members: self.members(),
+            mode: self.mode,
+            seed: self.seed,
+        };
+        for guest in &self.guests {
+            self.socket.send(&roster, guest.addr);
+        }
+    }
+
+    /// Tells every player to start, if the lobby is ready, and keeps telling them from
+    /// [`poll`](Self::poll) until they all acknowledge it. Returns whether it started.
+    pub fn start(&mut self) -> bool {
+        if self.is_starting() || !self.can_start() {
+            return false;
+        }
+        for guest in &mut self.guests {
+            guest.started = false;
+        }
+        self.send_start();
+        true
+    }
+
+    fn game_port(&self) -> u16 {
+        self.socket.port() + GAME_PORT_OFFSET
+    }
+
+    fn send_start(&mut self) {
+        self.last_start = Some(Instant::now());
+        let start = LobbyMessage::Start {
+            lobby: self.id,
+            mode: self.mode,
+            seed: self.seed,
+            config: self.config,
+            game_port: self.game_port(),
+        };
+        for guest in self.guests.iter().filter(|guest| !guest.started) {
+            self.socket.send(&start, guest.addr);
+        }
+    }
+
+    fn match_start(&self) -> MatchStart {
+        MatchStart {
+            mode: self.mode,
+            seed: self.seed,
+            config: self.config,
+            game_addr: (Ipv4Addr::UNSPECIFIED, self.game_port()).into(),
+            hosting: true,
+        }
+    }
+}
+
+/// A lobby found on the network.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Listing {
+    pub addr: SocketAddr,
+    pub lobby: u64,
+    pub name: String,
+    pub members: usize,
+    pub mode: MatchMode,
+    pub seed: u64,
+}
+
+struct Joined {
+    addr: SocketAddr,
+    lobby: u64,
+    roster: Vec<Member>,
+    mode: MatchMode,
+    seed: u64,
+    last_heard: Instant,
+}
+
+/// Browses advertised lobbies and takes part in one.
+pub struct ClientLobby {
+    socket: LobbySocket,
+    name: String,
+    listings: Vec<(Listing, Instant)>,
+    joined: Option<Joined>,
+    ready: bool,
+    last_heartbeat: Option<Instant>,
+    /// Lobby whose start this instance took, to acknowledge the host's repeats of it.
+    started: Option<u64>,
+}
+
+impl ClientLobby {
+    pub fn new(socket: LobbySocket, name: String) -> Self {
+        Self {
+            socket,
+            name,
+            listings: Vec::new(),
+            joined: None,
+            ready: false,
+            last_heartbeat: None,
+            started: None,
+        }
+    }
+
+    /// Lobbies heard from recently, in the order they were found.
+    pub fn listings(&self) -> Vec<Listing> {
+        self.listings
+            .iter()
+            .map(|(listing, _)| listing.clone())
+            .collect()
+    }
+
+    /// The joined lobby's members, mode and seed, once the host has confirmed the join.
+    pub fn roster(&self) -> Option<(&[Member], MatchMode, u64)> {
+        self.joined
+            .as_ref()
+            .filter(|joined| !joined.roster.is_empty())
+            .map(|joined| (joined.roster.as_slice(), joined.mode, joined.seed))
+    }
+
+    pub fn is_joined(&self) -> bool {
+        self.joined.is_some()
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.ready
+    }
+
+    pub fn join(&mut self, listing: &Listing) {
+        self.leave();
+        self.joined = Some(Joined {
+            addr: listing.addr,
+            lobby: listing.lobby,
+            roster: Vec::new(),
+            mode: listing.mode,
+            seed: listing.seed,
+            last_heard: Instant::now(),
+        });
+        self.last_heartbeat = None;
+        self.started = None;
+    }
+
+    pub fn set_ready(&mut self, ready: bool) {
+        self.ready = ready;
+        self.last_heartbeat = None;
+    }
+
+    pub fn leave(&mut self) {
+        if let Some(joined) = self.joined.take() {
+            self.socket.send(
+                &LobbyMessage::Leave {
+                    lobby: joined.lobby,
+                },
+                joined.addr,
+            );
+        }
+        self.ready = false;
+    }
+
+    /// Handles adverts and lobby updates. Returns the match to begin once the host starts it.
+    pub fn poll(&mut self) -> Option<MatchStart> {
+        let mut start = None;
+        while let Some((message, from)) = self.socket.recv() {
+            match message {
+                LobbyMessage::Advert {
+                    lobby,
+                    name,
+                    members,
+                    mode,
+                    seed,
+                } => {
+                    let listing = Listing {
+                        addr: from,
+                        lobby,
+                        name,
+                        members,
+                        mode,
+                        seed,
+                    };
+                    // The same advert arrives both broadcast and over loopback; keep the
+                    // first address so the host always sees us coming from the same place
+                    match self
+                        .listings
+                        .iter_mut()
+                        .find(|(known, _)| known.lobby == lobby)
+                    {
+                        Some((known, heard)) => {
+                            *known = Listing {
+                                addr: known.addr,
+                                ..listing
+                            };
+                            *heard = Instant::now();
+                        }
+                        None => self.listings.push((listing, Instant::now())),
+                    }
+                }
+                LobbyMessage::Roster {
+                    lobby,
+                    members,
+                    mode,
+                    seed,
+                } => {
+                    if let Some(joined) =
+                        self.joined.as_mut().filter(|joined| joined.lobby == lobby)
+                    {
+                        joined.roster = members;
+                        joined.mode = mode;
+                        joined.seed = seed;
+                        joined.last_heard = Instant::now();
+                    }
+                }
+                // A config the game can't run is ignored like any other bad message
+                LobbyMessage::Start {
+                    lobby,
+                    mode,
+                    seed,
+                    config,
+                    game_port,
+                } if config.validate().is_ok() => {
+                    if let Some(joined) = self.joined.take_if(|joined| joined.lobby == lobby) {
+                        start = Some(MatchStart {
+                            mode,
+                            seed,
+                            config,
+                            game_addr: SocketAddr::new(joined.addr.ip(), game_port),
+                            hosting: false,
+                        });
+                        self.ready = false;
+                        self.started = Some(lobby);
+                    }
+                    // The host repeats the start until it hears this, so every copy is answered
+                    if self.started == Some(lobby) {
+                        self.socket.send(&LobbyMessage::Started { lobby }, from);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        self.listings
+            .retain(|(_, heard)| heard.elapsed() < MEMBER_TIMEOUT);
+        if self
+            .joined
+            .as_ref()
+            .is_some_and(|joined| joined.last_heard.elapsed() > MEMBER_TIMEOUT)
+        {
+            self.joined = None; // The host went away
+            self.ready = false;
+        }
+
+        if let Some(joined) = &self.joined {
+            let due = self
+                .last_heartbeat
+                .is_none_or(|sent| sent.elapsed() >= HEARTBEAT_INTERVAL);
+            if due {
+                self.last_heartbeat = Some(Instant::now());
+                let message = if joined.roster.is_empty() {
+                    LobbyMessage::Join {
+                        lobby: joined.lobby,
+                        name: self.name.clone(),
+                    }
+                } else {
+                    LobbyMessage::Ready {
+                        lobby: joined.lobby,
+                        ready: self.ready,
+                    }
+                };
+                self.socket.send(&message, joined.addr);
+            }
+        }
+
+        start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_player_starts_over_a_lossy_network() {
+        let socket = LobbySocket::bind().unwrap().with_loss(0.3);
+        let mut host = HostLobby::new(socket, "host".into(), 7, GameConfig::default());
+        let mut clients: Vec<ClientLobby> = (0..2)
+            .map(|i| {
+                let socket = LobbySocket::bind().unwrap().with_loss(0.3);
+                ClientLobby::new(socket, format!("guest {i}"))
+            })
+            .collect();
+        let mut starts: Vec<Option<MatchStart>> = vec![None; clients.len()];
+
+        let began = Instant::now();
+        let host_start = loop {
+            assert!(began.elapsed() < Duration::from_secs(20), "lobby stalled");
+            if let Some(start) = host.poll() {
+                break start;
+            }
+            if host.members().len() == 3 && host.can_start() {
+                host.start();
+            }
+            for (client, start) in clients.iter_mut().zip(&mut starts) {
+                if let Some(started) = client.poll() {
+                    *start = Some(started);
+                }
+                if start.is_some() {
+                    continue;
+                }
+                if !client.is_joined() {
+                    // Other instances on this machine may be advertising too
+                    let ours = client.listings().into_iter().find(|l| l.name == "host");
+                    if let Some(listing) = ours {
+                        client.join(&listing);
+                    }
+                } else if client.roster().is_some() && !client.is_ready() {
+                    client.set_ready(true);
+                }
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        };
+
+        // The host only goes once both players have the start, and nobody was dropped
+        assert_eq!(host.members().len(), 3);
+        assert!(host_start.hosting);
+        for start in &starts {
+            let start = start.expect("every player got the start");
+            assert_eq!((start.mode, start.seed), (host_start.mode, host_start.seed));
+            assert!(!start.hosting);
+            assert_eq!(start.game_addr.port(), host_start.game_addr.port());
+        }
+    }
+
+    /// Polls both ends until `done` holds, failing after a few seconds.
+    fn settle(
+        host: &mut HostLobby,
+        client: &mut ClientLobby,
+        done: impl Fn(&HostLobby, &ClientLobby) -> bool,
+    ) {
+        let began = Instant::now();
+        while !done(host, client) {
+            assert!(began.elapsed() < Duration::from_secs(5), "lobby stalled");
+            host.poll();
+            client.poll();
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        let messages = [
+            LobbyMessage::Advert {
+                lobby: 1,
+                name: "host".into(),
+                members: 2,
+                mode: MatchMode::Versus,
+                seed: 9,
+            },
+            LobbyMessage::Ready {
+                lobby: 1,
+                ready: true,
+            },
+            LobbyMessage::Start {
+                lobby: 1,
+                mode: MatchMode::Race,
+                seed: u64::MAX,
+                config: GameConfig::default(),
+                game_port: 48800,
+            },
+            LobbyMessage::Started { lobby: 1 },
+        ];
+        for message in messages {
+            let bytes = message.encode();
+            assert_eq!(LobbyMessage::decode(&bytes), Some(message));
+            // Other traffic on the lobby ports lacks the magic
+            assert_eq!(LobbyMessage::decode(&bytes[MAGIC.len()..]), None);
+        }
+    }
+
+    #[test]
+    fn modes_cycle_and_limit_players() {
+        assert_eq!(MatchMode::Race.next(), MatchMode::Versus);
+        assert_eq!(MatchMode::Versus.next(), MatchMode::Race);
+        assert!(MatchMode::Race.players().contains(&1));
+        assert!(!MatchMode::Versus.players().contains(&1));
+        assert!(!MatchMode::Versus.players().contains(&3));
+    }
+
+    #[test]
+    fn players_join_ready_up_and_leave() {
+        let socket = LobbySocket::bind().unwrap();
+        let mut host = HostLobby::new(socket, "roster host".into(), 3, GameConfig::default());
+        host.mode = MatchMode::Versus;
+        assert!(!host.can_start(), "versus needs a second player");
+
+        let mut client = ClientLobby::new(LobbySocket::bind().unwrap(), "guest".into());
+        settle(&mut host, &mut client, |_, client| {
+            client
+                .listings()
+                .iter()
+                .any(|listing| listing.name == "roster host")
+        });
+        let listing = client
+            .listings()
+            .into_iter()
+            .find(|listing| listing.name == "roster host")
+            .unwrap();
+        assert_eq!(
+            (listing.mode, listing.seed, listing.members),
+            (MatchMode::Versus, 3, 1)
+        );
+
+        client.join(&listing);
+        settle(&mut host, &mut client, |host, _| host.members().len() == 2);
+        assert!(!host.can_start(), "the guest isn't ready yet");
+        settle(&mut host, &mut client, |_, client| {
+            client.roster().is_some()
+        });
+
+        client.set_ready(true);
+        settle(&mut host, &mut client, |host, _| host.can_start());
+        let (members, mode, seed) = client.roster().unwrap();
+        assert_eq!(members[0].name, "roster host");
+        assert_eq!((mode, seed), (MatchMode::Versus, 3));
+
+        client.leave();
+        settle(&mut host, &mut client, |host, _| host.members().len() == 1);
+        assert!(!client.is_joined());
+    }
+}
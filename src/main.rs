@@ -1,19 +1,63 @@
+// Bevy systems routinely take many parameters and nested query filters
+#![allow(clippy::type_complexity, clippy::too_many_arguments)]
+// Lints newer than the original game code, which is left as it was
+#![allow(clippy::manual_is_multiple_of)]
+
+#[cfg(feature = "audio")]
+use bevy::audio::AddAudioSource;
+use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
+use bevy::ecs::schedule::SystemConfigs;
 use bevy::prelude::*;
 use bevy::render::view::screenshot::ScreenshotManager;
 use bevy::state::app::AppExtStates;
 use bevy::state::condition::in_state;
+use bevy::ui::UiSystem;
 use bevy::window::PrimaryWindow;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rlappy_bev::sim::{
+    self, GameConfig, BIRD_X, GROUND_HEIGHT, GROUND_THICKNESS, GROUND_WIDTH, PIPE_DESPAWN_X,
+    PIPE_HEIGHT, PIPE_SPAWN_X, PIPE_WIDTH, TICK_RATE,
+};
+use std::net::{IpAddr, Ipv4Addr};
 use std::time::Duration;
 
-const GRAVITY: f32 = -500.0;
-const BIRD_JUMP: f32 = 300.0;
-const PIPE_SPEED: f32 = 150.0;
-const PIPE_GAP: f32 = 200.0;
-const PIPE_SPAWN_INTERVAL: f32 = 2.0;
-const GROUND_HEIGHT: f32 = -250.0;
-const SPEED_INCREASE_RATE: f32 = 0.05; // Speed multiplier increase per pipe passed
-const MAX_SPEED_MULTIPLIER: f32 = 2.5; // Maximum speed multiplier
+mod adaptive;
+mod audio;
+mod background;
+mod controls;
+mod coop;
+mod demo;
+mod input;
+mod lan;
+mod online;
+mod party;
+mod remote;
+mod rounds;
+mod settings;
+mod settings_menu;
+mod share;
+mod spectate;
+mod split_screen;
+mod submit;
+mod tas;
+
+use adaptive::AdaptiveDifficulty;
+use background::BackgroundTile;
+use controls::Capture;
+use demo::{DemoFormat, DemoRecorder};
+use input::{Action, Actions, ButtonFocus, PointerGuard};
+use lan::PlayerName;
+use online::OnlineMatch;
+use party::{Party, PartySetup};
+use remote::RemoteControl;
+use rlappy_bev::rpc::RpcServer;
+use rounds::{TournamentAttempt, TournamentRun};
+use settings::{DifficultyLock, HudText, Settings};
+use share::{ChallengeField, LastCode, RunInputs};
+use spectate::Spectators;
+use submit::Leaderboard;
+use tas::Tas;
 
 #[derive(Component)]
 struct Bird {
@@ -43,23 +87,147 @@ impl AnimationConfig {
     }
 }
 
+#[derive(Component, Clone, Copy)]
+struct Pipe {
+    gap_y: f32,
+}
+
+/// Which bird a `Bird` entity is; player one is index 0.
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+struct Player(usize);
+
+/// Raw pipe count for one bird, displayed halved like `Score`.
+#[derive(Component, Default)]
+struct PlayerScore(u32);
+
+/// Marks a bird that has crashed in a multi-bird round, with the fixed time it died at.
+#[derive(Component)]
+struct Dead {
+    at: Duration,
+}
+
+/// Full-window camera; draws the whole scene, or only UI while split screen is active.
+#[derive(Component)]
+struct MainCamera;
+
 #[derive(Component)]
-struct Pipe;
+struct Ground;
 
 #[derive(Component)]
 struct StartButton;
 
+/// Menu buttons and hint, removed when leaving `GameState::Menu`.
+#[derive(Component)]
+struct MenuUi;
+
+/// What a menu button does when clicked.
+#[derive(Component, Clone, Copy)]
+enum MenuButton {
+    Play(GameMode),
+    Lan,
+    Challenge,
+    Tournament,
+    Party,
+    Controls,
+    Settings,
+}
+
+#[derive(Component)]
+struct ScoreText;
+
+/// Per-player score readout in modes with more than one bird.
+#[derive(Component)]
+struct PlayerScoreText(usize);
+
+#[derive(Component)]
+struct PauseText;
+
+/// Game over message and restart button, removed when leaving `GameState::GameOver`.
+#[derive(Component)]
+struct GameOverUi;
+
 #[derive(Component)]
 struct Velocity {
     x: f32,
 }
 
+/// A pipe's x before this tick's movement, to tell when it went past the bird.
+#[derive(Component)]
+struct PreviousX(f32);
+
 #[derive(Resource)]
 struct Score(u32);
 
+/// Things that happen during a run, for systems that react to them such as sound.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+enum GameEvent {
+    /// A bird flapped on this tick.
+    Flapped,
+    /// The birds cleared a gap.
+    Scored,
+    /// A bird hit a pipe, the ground or the ceiling.
+    Crashed,
+}
+
 #[derive(Resource)]
 struct PipeSpawnTimer(Timer);
 
+/// Physics and difficulty parameters, loaded with `--config` or the shipped defaults.
+#[derive(Resource, Default)]
+struct ActiveConfig(GameConfig);
+
+/// Seed passed with `--seed`; when absent every run picks a fresh random seed.
+#[derive(Resource, Default)]
+struct SeedConfig(Option<u64>);
+
+/// Seed chosen for the next run by a LAN lobby host; takes precedence over `--seed`.
+#[derive(Resource)]
+struct MatchSeed(u64);
+
+/// Seed of the current run.
+#[derive(Resource, Default)]
+struct GameSeed(u64);
+
+#[derive(Resource)]
+struct PipeRng(StdRng);
+
+/// Birds that can be on the course at once.
+const MAX_PLAYERS: usize = 2;
+
+/// Jump pressed since the last fixed tick, per player; consumed by `bird_jump`.
+#[derive(Resource, Default)]
+struct PendingJump([bool; MAX_PLAYERS]);
+
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+enum GameMode {
+    #[default]
+    Solo,
+    /// Two birds on one keyboard; the round ends once both have crashed.
+    Versus,
+    /// Versus with each bird in its own half of the window.
+    SplitScreen,
+    /// Two birds sharing one score; a downed bird comes back after its partner clears a few gaps.
+    Coop,
+}
+
+impl GameMode {
+    fn players(self) -> usize {
+        match self {
+            GameMode::Solo => 1,
+            GameMode::Versus | GameMode::SplitScreen | GameMode::Coop => 2,
+        }
+    }
+
+    /// Sprite tint for a player's bird, leaving the solo bird untouched.
+    fn tint(self, player: usize) -> Color {
+        match (self, player) {
+            (GameMode::Solo, _) => Color::WHITE,
+            (_, 0) => Color::srgb(0.6, 0.8, 1.0),
+            _ => Color::srgb(1.0, 0.6, 0.6),
+        }
+    }
+}
+
 #[derive(Resource)]
 struct GameDifficulty {
     speed_multiplier: f32,
@@ -82,142 +250,566 @@ enum GameState {
     Playing,
     Paused,
     GameOver,
+    /// LAN lobby browser, reached from the menu.
+    Lobby,
+    /// Between hot-seat party turns: who plays next, or the final ranking.
+    Handoff,
+    /// Rebinding screen, reached from the menu.
+    Controls,
+    /// Preferences screen, reached from the menu or the pause overlay.
+    Settings,
 }
 
 fn main() {
-    App::new()
-        .add_plugins(
-            DefaultPlugins
-                .set(ImagePlugin::default_nearest()) // prevents blurry sprites
-                .set(WindowPlugin {
-                    primary_window: Some(Window {
-                        title: "Rlappy Bird".to_string(),
-                        resolution: (800.0, 600.0).into(),
-                        ..default()
-                    }),
-                    ..default()
+    let mut seed_config = SeedConfig::default();
+    let mut config = GameConfig::default();
+    let mut custom_config = false;
+    let mut demo_format = None;
+    let mut tas_mode = false;
+    let mut adaptive_mode = false;
+    let mut ranked = false;
+    let mut host_port = None;
+    let mut join_addr = None;
+    let mut player_name = PlayerName::default();
+    let mut spectate_port = None;
+    let mut spectate_ip = IpAddr::from(Ipv4Addr::LOCALHOST);
+    let mut remote = None;
+    let mut leaderboard = None;
+    let mut tournament_path = None;
+    let mut settings_path = settings::DEFAULT_PATH.to_string();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => seed_config.0 = args.next().and_then(|value| value.parse().ok()),
+            "--config" => {
+                let path = args.next().unwrap_or_default();
+                match GameConfig::load(&path) {
+                    Ok(loaded) => {
+                        config = loaded;
+                        custom_config = true;
+                    }
+                    Err(err) => eprintln!("Failed to load config {path}, using defaults: {err}"),
+                }
+            }
+            "--record-demo" | "--record-demo=jsonl" => demo_format = Some(DemoFormat::Jsonl),
+            "--record-demo=bin" => demo_format = Some(DemoFormat::Binary),
+            "--tas" => tas_mode = true,
+            "--adaptive" => adaptive_mode = true,
+            "--ranked" => ranked = true,
+            "--host" => host_port = args.next().and_then(|value| value.parse::<u16>().ok()),
+            "--join" => join_addr = args.next(),
+            "--spectate" => spectate_port = args.next().and_then(|value| value.parse::<u16>().ok()),
+            "--spectate-bind" => {
+                let value = args.next().unwrap_or_default();
+                match value.parse() {
+                    Ok(ip) => spectate_ip = ip,
+                    Err(_) => eprintln!("Ignoring invalid --spectate-bind address: {value}"),
+                }
+            }
+            "--remote" => remote = args.next(),
+            "--name" => player_name.0 = args.next().unwrap_or(player_name.0),
+            "--tournament" => tournament_path = args.next(),
+            "--settings" => settings_path = args.next().unwrap_or(settings_path),
+            "--leaderboard" => {
+                leaderboard = args.next();
+                ranked = true;
+            }
+            _ => eprintln!("Ignoring unknown argument: {arg}"),
+        }
+    }
+
+    let fixed_seed = seed_config.0;
+    let tournament =
+        tournament_path.and_then(|path| match TournamentRun::open(&path, &player_name.0) {
+            Ok(run) => Some(run),
+            Err(err) => {
+                eprintln!("Failed to load tournament {path}: {err}");
+                None
+            }
+        });
+    let in_tournament = tournament.is_some();
+    // A TAS run can be rewound after game over and die again, so nothing that records
+    // finished runs is kept alongside it
+    if tas_mode && in_tournament {
+        eprintln!("TAS is disabled in tournaments");
+        tas_mode = false;
+    }
+    if tas_mode && adaptive_mode {
+        eprintln!("Adaptive difficulty is disabled with --tas");
+        adaptive_mode = false;
+    }
+    if tas_mode && demo_format.take().is_some() {
+        eprintln!("Demo recording is disabled with --tas");
+    }
+    let settings = Settings::load(&settings_path).unwrap_or_else(|err| {
+        eprintln!("Failed to load settings {settings_path}, using defaults: {err}");
+        Settings::defaults_at(&settings_path)
+    });
+    let difficulty_lock = if custom_config {
+        Some(DifficultyLock("set by --config"))
+    } else if ranked {
+        Some(DifficultyLock("ranked runs use Normal"))
+    } else if adaptive_mode && !in_tournament {
+        Some(DifficultyLock("set by --adaptive"))
+    } else {
+        None
+    };
+    if difficulty_lock.is_none() {
+        config = settings.difficulty.config();
+    }
+    let window = settings.window();
+
+    let mut app = App::new();
+    app.add_plugins(
+        DefaultPlugins
+            .set(ImagePlugin::default_nearest()) // prevents blurry sprites
+            .set(WindowPlugin {
+                primary_window: Some(Window {
+                    title: "Rlappy Bird".to_string(),
+                    resolution: (800.0, 600.0).into(),
+                    ..window
                 }),
+                ..default()
+            }),
+    )
+    .add_plugins(FrameTimeDiagnosticsPlugin)
+    .init_state::<GameState>()
+    .insert_resource(Time::<Fixed>::from_hz(TICK_RATE))
+    .insert_resource(Score(0))
+    .insert_resource(GameDifficulty::default())
+    .insert_resource(PipeSpawnTimer(Timer::new(
+        config.spawn_interval(),
+        TimerMode::Repeating,
+    )))
+    .insert_resource(ActiveConfig(config))
+    .insert_resource(seed_config)
+    .init_resource::<GameSeed>()
+    .insert_resource(PipeRng(StdRng::from_entropy()))
+    .init_resource::<PendingJump>()
+    .init_resource::<GameMode>()
+    .init_resource::<RunInputs>()
+    .init_resource::<share::Clipboard>()
+    .insert_resource(settings)
+    .init_resource::<Actions>()
+    .init_resource::<ButtonFocus>()
+    .init_resource::<PointerGuard>()
+    .add_systems(PreUpdate, input::update_actions.after(UiSystem::Focus))
+    .add_systems(OnExit(GameState::Playing), input::raise_pointer_guard)
+    .add_event::<GameEvent>()
+    .add_systems(
+        Update,
+        audio::mute_input
+            .run_if(not(in_state(GameState::Lobby)))
+            .run_if(not(resource_exists::<PartySetup>))
+            .run_if(not(resource_exists::<ChallengeField>))
+            .run_if(not(resource_exists::<Capture>)),
+    )
+    .insert_resource(player_name)
+    .add_systems(
+        Startup,
+        (setup, settings::spawn_hud, background::spawn_background),
+    )
+    .add_systems(
+        Update,
+        (
+            settings::apply_window.run_if(resource_changed::<Settings>),
+            settings::apply_palette,
+            background::apply_palette.run_if(resource_changed::<Settings>),
+            settings::update_hud,
+        ),
+    )
+    .add_systems(
+        OnEnter(GameState::Menu),
+        (
+            spawn_menu,
+            rounds::spawn_tournament_status.run_if(resource_exists::<TournamentRun>),
+            party::end_party.run_if(resource_exists::<Party>),
+            settings::apply_difficulty.run_if(not(resource_exists::<DifficultyLock>)),
         )
-        .init_state::<GameState>()
-        .insert_resource(Score(0))
-        .insert_resource(GameDifficulty::default())
-        .insert_resource(PipeSpawnTimer(Timer::from_seconds(
-            PIPE_SPAWN_INTERVAL,
-            TimerMode::Repeating,
-        )))
-        .add_systems(Startup, setup)
-        .add_systems(Update, (menu_system, button_system).run_if(in_state(GameState::Menu)))
-        .add_systems(
-            Update,
-            (
-                bird_movement,
-                bird_input,
-                pipe_movement,
-                spawn_pipes,
-                check_collisions,
-                update_score,
-                execute_animations,
-                pause_input,
-                screenshot_input,
-            )
-                .run_if(in_state(GameState::Playing)),
+            .run_if(not(resource_exists::<OnlineMatch>)),
+    )
+    .add_systems(
+        Update,
+        (input::navigate_buttons, menu_system, button_system)
+            .chain()
+            .run_if(in_state(GameState::Menu).and_then(not(resource_exists::<OnlineMatch>)))
+            .run_if(not(resource_exists::<ChallengeField>))
+            .run_if(not(resource_exists::<PartySetup>)),
+    )
+    .add_systems(
+        Update,
+        (
+            party::spawn_party_setup.run_if(resource_added::<PartySetup>),
+            (party::party_setup_input, party::update_party_setup).chain(),
         )
-        .add_systems(
-            Update,
-            (pause_input, screenshot_input, unpause_system)
-                .run_if(in_state(GameState::Paused)),
+            .run_if(in_state(GameState::Menu).and_then(resource_exists::<PartySetup>)),
+    )
+    .add_systems(
+        Update,
+        (
+            share::spawn_challenge_field.run_if(resource_added::<ChallengeField>),
+            (share::challenge_field_input, share::update_challenge_field).chain(),
+        )
+            .run_if(in_state(GameState::Menu).and_then(resource_exists::<ChallengeField>)),
+    )
+    .add_systems(OnExit(GameState::Menu), despawn_with::<MenuUi>)
+    .add_systems(
+        OnTransition {
+            exited: GameState::Menu,
+            entered: GameState::Playing,
+        },
+        run_setup(),
+    )
+    .add_systems(
+        OnTransition {
+            exited: GameState::Lobby,
+            entered: GameState::Playing,
+        },
+        run_setup(),
+    )
+    .add_systems(
+        OnTransition {
+            exited: GameState::Handoff,
+            entered: GameState::Playing,
+        },
+        run_setup(),
+    )
+    .add_systems(OnEnter(GameState::Lobby), lan::open_lobby)
+    .add_systems(
+        Update,
+        (lan::lobby_controls, lan::update_lobby_text)
+            .chain()
+            .run_if(in_state(GameState::Lobby)),
+    )
+    .add_systems(
+        OnExit(GameState::Lobby),
+        (lan::close_lobby, despawn_with::<lan::LobbyUi>),
+    )
+    .add_systems(
+        FixedUpdate,
+        run_tick()
+            .run_if(in_state(GameState::Playing).or_else(tas::stepping))
+            .run_if(not(resource_exists::<OnlineMatch>)),
+    )
+    .add_systems(
+        Update,
+        (
+            bird_input,
+            execute_animations,
+            background::scroll_background,
+            // Pausing would only freeze the opponent in an online match
+            pause_input.run_if(not(resource_exists::<OnlineMatch>)),
+            screenshot_input,
+        )
+            .run_if(in_state(GameState::Playing)),
+    )
+    .add_systems(
+        Update,
+        (
+            screenshot_input,
+            unpause_system,
+            settings_menu::pause_settings_input,
+        )
+            .run_if(in_state(GameState::Paused)),
+    )
+    .add_systems(OnExit(GameState::Paused), despawn_with::<PauseText>)
+    .add_systems(
+        Update,
+        (game_over_system, button_system).run_if(in_state(GameState::GameOver)),
+    )
+    .add_systems(
+        Update,
+        share::copy_challenge_code
+            .run_if(in_state(GameState::GameOver).and_then(resource_exists::<LastCode>)),
+    )
+    .add_systems(
+        OnEnter(GameState::GameOver),
+        (
+            spawn_game_over_ui,
+            share::spawn_challenge_code.run_if(not(resource_exists::<OnlineMatch>)),
+            rounds::record_attempt.run_if(resource_exists::<TournamentAttempt>),
+            party::record_turn.run_if(resource_exists::<Party>),
+            submit::submit_run.run_if(
+                resource_exists::<Leaderboard>.and_then(not(resource_exists::<OnlineMatch>)),
+            ),
+            demo::save_demo.run_if(resource_exists::<DemoRecorder>),
+            adaptive::finish_adaptive_run.run_if(resource_exists::<AdaptiveDifficulty>),
+        ),
+    )
+    .add_systems(OnExit(GameState::GameOver), despawn_with::<GameOverUi>)
+    .add_systems(
+        OnTransition {
+            exited: GameState::GameOver,
+            entered: GameState::Menu,
+        },
+        (run_teardown(), share::end_challenge, rounds::end_attempt),
+    )
+    .add_systems(
+        OnTransition {
+            exited: GameState::GameOver,
+            entered: GameState::Handoff,
+        },
+        run_teardown(),
+    )
+    .add_systems(
+        OnTransition {
+            exited: GameState::Playing,
+            entered: GameState::Menu,
+        },
+        run_abort(),
+    )
+    .add_systems(
+        OnTransition {
+            exited: GameState::Paused,
+            entered: GameState::Menu,
+        },
+        run_abort(),
+    )
+    .add_systems(OnEnter(GameState::Handoff), party::spawn_handoff)
+    .add_systems(
+        Update,
+        party::handoff_input.run_if(in_state(GameState::Handoff)),
+    )
+    .add_systems(OnExit(GameState::Handoff), despawn_with::<party::HandoffUi>)
+    .add_systems(OnEnter(GameState::Controls), controls::spawn_controls)
+    .add_systems(
+        Update,
+        (
+            (input::navigate_buttons, controls::controls_buttons)
+                .run_if(not(resource_exists::<Capture>)),
+            controls::capture_binding.run_if(resource_exists::<Capture>),
+            controls::update_controls_text,
+        )
+            .chain()
+            .run_if(in_state(GameState::Controls)),
+    )
+    .add_systems(
+        OnExit(GameState::Controls),
+        (despawn_with::<controls::ControlsUi>, controls::end_capture),
+    )
+    .add_systems(OnEnter(GameState::Settings), settings_menu::spawn_settings)
+    .add_systems(
+        Update,
+        (
+            input::navigate_buttons,
+            settings_menu::settings_buttons,
+            settings_menu::update_settings_text,
+        )
+            .chain()
+            .run_if(in_state(GameState::Settings)),
+    )
+    .add_systems(
+        OnExit(GameState::Settings),
+        despawn_with::<settings_menu::SettingsUi>,
+    )
+    .add_systems(Update, split_screen::fit_split_viewports)
+    .add_systems(
+        OnEnter(GameState::Menu),
+        online::spawn_lobby.run_if(resource_exists::<OnlineMatch>),
+    )
+    .add_systems(
+        Update,
+        online::online_lobby
+            .run_if(in_state(GameState::Menu).and_then(resource_exists::<OnlineMatch>)),
+    )
+    .add_systems(
+        FixedUpdate,
+        (online::online_tick, online::sync_online_view)
+            .chain()
+            .run_if(in_state(GameState::Playing).and_then(resource_exists::<OnlineMatch>)),
+    )
+    .add_systems(
+        FixedUpdate,
+        online::online_linger
+            .run_if(in_state(GameState::GameOver).and_then(resource_exists::<OnlineMatch>)),
+    )
+    .add_systems(
+        OnEnter(GameState::GameOver),
+        online::spawn_connection_lost.run_if(resource_exists::<OnlineMatch>),
+    )
+    .add_systems(
+        OnTransition {
+            exited: GameState::GameOver,
+            entered: GameState::Menu,
+        },
+        online::rematch.run_if(resource_exists::<OnlineMatch>),
+    )
+    .add_systems(
+        Update,
+        (spectate::accept_spectators, spectate::publish_transitions)
+            .run_if(resource_exists::<Spectators>),
+    )
+    .add_systems(
+        FixedPostUpdate,
+        spectate::publish_tick
+            .run_if(in_state(GameState::Playing).and_then(resource_exists::<Spectators>)),
+    )
+    .add_systems(
+        Update,
+        remote::remote_control.run_if(resource_exists::<RemoteControl>),
+    )
+    .add_systems(
+        Update,
+        submit::poll_submission.run_if(resource_exists::<Leaderboard>),
+    )
+    .add_systems(
+        Update,
+        coop::update_team_hud.run_if(coop::coop_active.and_then(in_state(GameState::Playing))),
+    )
+    .add_systems(
+        Update,
+        (
+            tas::tas_controls
+                .run_if(in_state(GameState::Paused).or_else(in_state(GameState::GameOver))),
+            tas::update_tas_text,
         )
+            .run_if(resource_exists::<Tas>),
+    );
+
+    #[cfg(feature = "audio")]
+    app.add_audio_source::<audio::MusicSource>()
+        .add_systems(Startup, (audio::load_effects, audio::start_music))
         .add_systems(
             Update,
-            (game_over_system, button_system).run_if(in_state(GameState::GameOver)),
-        )
-        .run();
+            (
+                (audio::play_effects, audio::check_output)
+                    .run_if(resource_exists::<audio::Effects>),
+                audio::steer_music.run_if(resource_exists::<audio::MusicPlayer>),
+            ),
+        );
+
+    if let Some(format) = demo_format {
+        app.insert_resource(DemoRecorder::new(format));
+    }
+    if tas_mode {
+        app.init_resource::<Tas>();
+    }
+    let online = match (host_port, join_addr) {
+        (Some(port), _) => {
+            let seed = fixed_seed.unwrap_or_else(rand::random);
+            let online = OnlineMatch::host(port, seed, config);
+            Some(online.map_err(|err| (format!("port {port}"), err)))
+        }
+        (None, Some(addr)) => Some(OnlineMatch::join(&addr).map_err(|err| (addr, err))),
+        (None, None) => None,
+    };
+    match online {
+        Some(Ok(online)) => {
+            app.insert_resource(online);
+        }
+        Some(Err((target, err))) => eprintln!("Online play unavailable on {target}: {err}"),
+        None => {}
+    }
+    if let Some(port) = spectate_port {
+        match Spectators::bind(spectate_ip, port) {
+            Ok(spectators) => {
+                app.insert_resource(spectators);
+            }
+            Err(err) => eprintln!("Spectator feed unavailable on {spectate_ip} port {port}: {err}"),
+        }
+    }
+    match remote.as_deref() {
+        Some("stdio") => {
+            app.insert_resource(RemoteControl::new(RpcServer::stdio()));
+        }
+        Some(port) => match port
+            .parse::<u16>()
+            .map_err(std::io::Error::other)
+            .and_then(RpcServer::tcp)
+        {
+            Ok(server) => {
+                app.insert_resource(RemoteControl::new(server));
+            }
+            Err(err) => eprintln!("Remote control unavailable on port {port}: {err}"),
+        },
+        None => {}
+    }
+    if let Some(run) = tournament {
+        app.insert_resource(run);
+    }
+    if let Some(addr) = leaderboard {
+        app.insert_resource(Leaderboard::new(addr));
+    }
+    if adaptive_mode && ranked {
+        eprintln!("Adaptive difficulty is disabled for ranked runs");
+    } else if adaptive_mode && in_tournament {
+        eprintln!("Adaptive difficulty is disabled in tournaments");
+    } else if adaptive_mode {
+        app.insert_resource(AdaptiveDifficulty::new(config));
+    }
+
+    app.run();
 }
 
 fn setup(mut commands: Commands) {
-    commands.spawn(Camera2dBundle::default());
+    commands.spawn((Camera2dBundle::default(), MainCamera));
+}
+
+fn spawn_menu(
+    mut commands: Commands,
+    tournament: Option<Res<TournamentRun>>,
+    tas: Option<Res<Tas>>,
+) {
+    let mut buttons = vec![
+        ("START", MenuButton::Play(GameMode::Solo)),
+        ("VERSUS", MenuButton::Play(GameMode::Versus)),
+        ("SPLIT", MenuButton::Play(GameMode::SplitScreen)),
+        ("CO-OP", MenuButton::Play(GameMode::Coop)),
+        ("LAN", MenuButton::Lan),
+        ("CHALLENGE", MenuButton::Challenge),
+        ("PARTY", MenuButton::Party),
+        ("CONTROLS", MenuButton::Controls),
+        ("SETTINGS", MenuButton::Settings),
+    ];
+    let mut help = "Space: start  V: versus  X: split screen  C: co-op\nL: LAN  H: challenge  Y: party  K: controls  O: settings".to_string();
+    // Party turns are scored on game over, which a TAS run can reach more than once
+    if tas.is_some() {
+        buttons.retain(|(_, action)| !matches!(action, MenuButton::Party));
+        help = help.replace("  Y: party", "");
+    }
+    if tournament.is_some() {
+        buttons.push(("TOURNAMENT", MenuButton::Tournament));
+        help.push_str("  T: tournament");
+    }
 
-    // Spawn start button
     commands
         .spawn((
-            ButtonBundle {
+            NodeBundle {
                 style: Style {
-                    width: Val::Px(200.0),
-                    height: Val::Px(65.0),
                     margin: UiRect::all(Val::Auto),
-                    justify_content: JustifyContent::Center,
+                    flex_direction: FlexDirection::Column,
                     align_items: AlignItems::Center,
+                    row_gap: Val::Px(15.0),
                     ..default()
                 },
-                background_color: Color::srgb(0.2, 0.7, 0.2).into(),
                 ..default()
             },
-            StartButton,
+            MenuUi,
         ))
         .with_children(|parent| {
-            parent.spawn(TextBundle::from_section(
-                "START",
-                TextStyle {
-                    font_size: 40.0,
-                    color: Color::WHITE,
+            // Two buttons per row so every mode fits in the window
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(500.0),
+                        flex_wrap: FlexWrap::Wrap,
+                        justify_content: JustifyContent::Center,
+                        column_gap: Val::Px(20.0),
+                        row_gap: Val::Px(15.0),
+                        ..default()
+                    },
                     ..default()
-                },
-            ));
-        });
-}
-
-fn button_system(
-    mut interaction_query: Query<
-        (&Interaction, &mut BackgroundColor),
-        (Changed<Interaction>, With<StartButton>),
-    >,
-    mut next_state: ResMut<NextState<GameState>>,
-    mut commands: Commands,
-    button_query: Query<Entity, With<StartButton>>,
-    entities_query: Query<Entity, Or<(With<Bird>, With<Pipe>, With<Text>, With<Sprite>)>>,
-    asset_server: Res<AssetServer>,
-    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
-    current_state: Res<State<GameState>>,
-    mut score: ResMut<Score>,
-    mut difficulty: ResMut<GameDifficulty>,
-) {
-    for (interaction, mut color) in &mut interaction_query {
-        match *interaction {
-            Interaction::Pressed => {
-                *color = Color::srgb(0.1, 0.5, 0.1).into();
-                
-                // Handle based on current state
-                match current_state.get() {
-                    GameState::Menu => {
-                        // Clear button
-                        for entity in button_query.iter() {
-                            commands.entity(entity).despawn_recursive();
-                        }
-                        
-                        // Start the game
-                        start_game(&mut commands, &asset_server, &mut texture_atlas_layouts, &mut next_state);
-                    }
-                    GameState::GameOver => {
-                        // Clean up all entities
-                        for entity in entities_query.iter() {
-                            commands.entity(entity).despawn();
-                        }
-
-                        // Reset score and difficulty
-                        score.0 = 0;
-                        *difficulty = GameDifficulty::default();
-
-                        // Go back to menu
-                        next_state.set(GameState::Menu);
-
-                        // Spawn start button
-                        commands
+                })
+                .with_children(|parent| {
+                    for (label, action) in buttons {
+                        parent
                             .spawn((
                                 ButtonBundle {
                                     style: Style {
-                                        width: Val::Px(200.0),
+                                        width: Val::Px(240.0),
                                         height: Val::Px(65.0),
-                                        margin: UiRect::all(Val::Auto),
+                                        // Outlined while focused for keyboard and gamepad
+                                        border: UiRect::all(Val::Px(4.0)),
                                         justify_content: JustifyContent::Center,
                                         align_items: AlignItems::Center,
                                         ..default()
@@ -226,10 +818,11 @@ fn button_system(
                                     ..default()
                                 },
                                 StartButton,
+                                action,
                             ))
                             .with_children(|parent| {
                                 parent.spawn(TextBundle::from_section(
-                                    "START",
+                                    label,
                                     TextStyle {
                                         font_size: 40.0,
                                         color: Color::WHITE,
@@ -238,7 +831,47 @@ fn button_system(
                                 ));
                             });
                     }
-                    _ => {}
+                });
+
+            parent.spawn(TextBundle::from_section(
+                format!("{help}\nP1 flaps with Space, P2 with Up"),
+                TextStyle {
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, Option<&MenuButton>),
+        (Changed<Interaction>, With<StartButton>),
+    >,
+    buttons: Query<(Entity, &GlobalTransform), With<StartButton>>,
+    menu_buttons: Query<&MenuButton>,
+    actions: Res<Actions>,
+    focus: Res<ButtonFocus>,
+    guard: Res<PointerGuard>,
+    time: Res<Time>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    current_state: Res<State<GameState>>,
+    mut mode: ResMut<GameMode>,
+    tournament: Option<Res<TournamentRun>>,
+    mut config: ResMut<ActiveConfig>,
+    party: Option<Res<Party>>,
+) {
+    let mut pressed = None;
+    for (interaction, mut color, action) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                *color = Color::srgb(0.1, 0.5, 0.1).into();
+                if !guard.active(&time) {
+                    pressed = Some(action.copied());
                 }
             }
             Interaction::Hovered => {
@@ -249,30 +882,108 @@ fn button_system(
             }
         }
     }
+    // Space or a tap would restart straight away when still flapping at the crash, so
+    // Confirm only presses menu buttons; a click or tap outside them presses the focused one
+    if *current_state.get() == GameState::Menu && actions.just_pressed(Action::Confirm) {
+        if let Some(entity) = focus.current(&buttons) {
+            pressed = Some(menu_buttons.get(entity).ok().copied());
+        }
+    }
+    let Some(action) = pressed else {
+        return;
+    };
+
+    // Handle based on current state
+    match current_state.get() {
+        GameState::Menu => match action.unwrap_or(MenuButton::Play(GameMode::Solo)) {
+            MenuButton::Play(chosen) => {
+                *mode = chosen;
+                start_game(
+                    &mut commands,
+                    &asset_server,
+                    &mut texture_atlas_layouts,
+                    chosen,
+                    &mut next_state,
+                );
+            }
+            MenuButton::Lan => next_state.set(GameState::Lobby),
+            MenuButton::Challenge => commands.init_resource::<ChallengeField>(),
+            MenuButton::Party => commands.init_resource::<PartySetup>(),
+            MenuButton::Controls => next_state.set(GameState::Controls),
+            MenuButton::Settings => next_state.set(GameState::Settings),
+            MenuButton::Tournament => {
+                if rounds::start_round(&mut commands, tournament.as_deref(), &mut config) {
+                    *mode = GameMode::Solo;
+                    start_game(
+                        &mut commands,
+                        &asset_server,
+                        &mut texture_atlas_layouts,
+                        GameMode::Solo,
+                        &mut next_state,
+                    );
+                }
+            }
+        },
+        GameState::GameOver => {
+            // Go back to menu, or on to the next party turn
+            next_state.set(after_game_over(party.is_some()));
+        }
+        _ => {}
+    }
 }
 
 fn menu_system(
     keyboard: Res<ButtonInput<KeyCode>>,
     mut next_state: ResMut<NextState<GameState>>,
     mut commands: Commands,
-    button_query: Query<Entity, With<StartButton>>,
     asset_server: Res<AssetServer>,
     mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut mode: ResMut<GameMode>,
+    tournament: Option<Res<TournamentRun>>,
+    mut config: ResMut<ActiveConfig>,
+    tas: Option<Res<Tas>>,
 ) {
-    if keyboard.just_pressed(KeyCode::Space) {
-        // Clear button
-        for entity in button_query.iter() {
-            commands.entity(entity).despawn_recursive();
+    // Space and Enter press the focused button, see `button_system`
+    let chosen = if keyboard.just_pressed(KeyCode::KeyV) {
+        GameMode::Versus
+    } else if keyboard.just_pressed(KeyCode::KeyX) {
+        GameMode::SplitScreen
+    } else if keyboard.just_pressed(KeyCode::KeyC) {
+        GameMode::Coop
+    } else if keyboard.just_pressed(KeyCode::KeyT)
+        && rounds::start_round(&mut commands, tournament.as_deref(), &mut config)
+    {
+        GameMode::Solo
+    } else {
+        if keyboard.just_pressed(KeyCode::KeyL) {
+            next_state.set(GameState::Lobby);
+        } else if keyboard.just_pressed(KeyCode::KeyH) {
+            commands.init_resource::<ChallengeField>();
+        } else if keyboard.just_pressed(KeyCode::KeyY) && tas.is_none() {
+            commands.init_resource::<PartySetup>();
+        } else if keyboard.just_pressed(KeyCode::KeyK) {
+            next_state.set(GameState::Controls);
+        } else if keyboard.just_pressed(KeyCode::KeyO) {
+            next_state.set(GameState::Settings);
         }
+        return;
+    };
 
-        start_game(&mut commands, &asset_server, &mut texture_atlas_layouts, &mut next_state);
-    }
+    *mode = chosen;
+    start_game(
+        &mut commands,
+        &asset_server,
+        &mut texture_atlas_layouts,
+        chosen,
+        &mut next_state,
+    );
 }
 
 fn start_game(
     commands: &mut Commands,
     asset_server: &Res<AssetServer>,
     texture_atlas_layouts: &mut ResMut<Assets<TextureAtlasLayout>>,
+    mode: GameMode,
     next_state: &mut ResMut<NextState<GameState>>,
 ) {
     // Load the bird sprite sheet
@@ -283,66 +994,209 @@ fn start_game(
     let layout = TextureAtlasLayout::from_grid(UVec2::splat(512), 2, 2, None, None);
     let texture_atlas_layout = texture_atlas_layouts.add(layout);
 
-    // Create animation config - animate through all 4 frames at 8 FPS
-    let animation_config = AnimationConfig::new(0, 3, 8);
+    for player in 0..mode.players() {
+        // Create animation config - animate through all 4 frames at 8 FPS
+        let animation_config = AnimationConfig::new(0, 3, 8);
+
+        // Spawn bird with animated sprite, later players drawn on top
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: mode.tint(player),
+                    ..default()
+                },
+                texture: texture.clone(),
+                transform: Transform::from_scale(Vec3::splat(0.06)).with_translation(Vec3::new(
+                    BIRD_X,
+                    0.0,
+                    player as f32 * 0.1,
+                )),
+                ..default()
+            },
+            TextureAtlas {
+                layout: texture_atlas_layout.clone(),
+                index: animation_config.first_sprite_index,
+            },
+            Bird { velocity: 0.0 },
+            Player(player),
+            PlayerScore::default(),
+            animation_config,
+        ));
+    }
+
+    // Spawn score text
+    if mode == GameMode::Solo {
+        commands.spawn((
+            TextBundle::from_section(
+                "Score: 0",
+                TextStyle {
+                    font_size: 30.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.0),
+                left: Val::Px(10.0),
+                ..default()
+            }),
+            ScoreText,
+        ));
+    } else {
+        for player in 0..mode.players() {
+            let mut style = Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.0),
+                ..default()
+            };
+            // Player one reads on the left, player two on the right
+            if player == 0 {
+                style.left = Val::Px(10.0);
+            } else {
+                style.right = Val::Px(10.0);
+            }
+            commands.spawn((
+                TextBundle::from_section(
+                    format!("P{}: 0", player + 1),
+                    TextStyle {
+                        font_size: 30.0,
+                        color: mode.tint(player),
+                        ..default()
+                    },
+                )
+                .with_style(style),
+                PlayerScoreText(player),
+            ));
+        }
+    }
 
-    // Spawn bird with animated sprite
+    // Spawn ground, coloured by the palette
     commands.spawn((
         SpriteBundle {
-            texture: texture.clone(),
-            transform: Transform::from_scale(Vec3::splat(0.06))
-                .with_translation(Vec3::new(-100.0, 0.0, 0.0)),
+            sprite: Sprite {
+                color: Color::srgb(0.3, 0.8, 0.3),
+                custom_size: Some(Vec2::new(GROUND_WIDTH, GROUND_THICKNESS)),
+                ..default()
+            },
+            transform: Transform::from_xyz(0.0, GROUND_HEIGHT, 0.0),
             ..default()
         },
-        TextureAtlas {
-            layout: texture_atlas_layout.clone(),
-            index: animation_config.first_sprite_index,
-        },
-        Bird { velocity: 0.0 },
-        animation_config,
+        Ground,
     ));
 
-    // Spawn score text
-    commands.spawn((TextBundle::from_section(
-        "Score: 0",
-        TextStyle {
-            font_size: 30.0,
-            color: Color::WHITE,
-            ..default()
-        },
+    next_state.set(GameState::Playing);
+}
+
+/// Everything that starts a run, registered on each way into `GameState::Playing`.
+fn run_setup() -> SystemConfigs {
+    (
+        // The adaptive config has to be in place before `begin_run` reads it
+        (
+            adaptive::apply_adaptive_config.run_if(resource_exists::<AdaptiveDifficulty>),
+            begin_run,
+        )
+            .chain(),
+        tas::begin_timeline.run_if(resource_exists::<Tas>),
+        split_screen::setup_split_screen.run_if(split_screen::split_screen_active),
+        coop::spawn_team_score.run_if(coop::coop_active),
     )
-    .with_style(Style {
-        position_type: PositionType::Absolute,
-        top: Val::Px(10.0),
-        left: Val::Px(10.0),
-        ..default()
-    }),));
+        .into_configs()
+}
 
-    // Spawn ground
-    commands.spawn(SpriteBundle {
-        sprite: Sprite {
-            color: Color::srgb(0.3, 0.8, 0.3),
-            custom_size: Some(Vec2::new(1000.0, 50.0)),
-            ..default()
-        },
-        transform: Transform::from_xyz(0.0, GROUND_HEIGHT, 0.0),
-        ..default()
-    });
+/// One fixed tick of a local run, in order.
+fn run_tick() -> SystemConfigs {
+    (
+        demo::record_demo_tick.run_if(resource_exists::<DemoRecorder>),
+        tas::record_tick.run_if(resource_exists::<Tas>),
+        share::record_run_input,
+        bird_jump,
+        bird_movement,
+        pipe_movement,
+        spawn_pipes,
+        check_collisions,
+        update_score,
+        coop::revive_partners.run_if(coop::coop_active),
+        adaptive::track_run.run_if(resource_exists::<AdaptiveDifficulty>),
+    )
+        .chain()
+}
 
-    next_state.set(GameState::Playing);
+/// Everything that clears a finished run, registered on each way out of it.
+fn run_teardown() -> SystemConfigs {
+    (reset_run, split_screen::teardown_split_screen).into_configs()
 }
 
-fn bird_input(keyboard: Res<ButtonInput<KeyCode>>, mut query: Query<&mut Bird>) {
-    if keyboard.just_pressed(KeyCode::Space) {
-        for mut bird in query.iter_mut() {
-            bird.velocity = BIRD_JUMP;
+/// Clears a run left before game over, skipping everything that scores or saves it.
+fn run_abort() -> SystemConfigs {
+    (
+        run_teardown(),
+        share::end_challenge,
+        rounds::end_attempt,
+        demo::discard_demo.run_if(resource_exists::<DemoRecorder>),
+    )
+        .into_configs()
+}
+
+/// Seeds the pipe generator and restarts the spawn timer so a seed always yields the same course.
+/// The timer also takes the run's spawn interval, since challenges, tournament rounds and LAN
+/// matches each bring their own config.
+fn begin_run(
+    seed_config: Res<SeedConfig>,
+    config: Res<ActiveConfig>,
+    match_seed: Option<Res<MatchSeed>>,
+    mut seed: ResMut<GameSeed>,
+    mut pipe_rng: ResMut<PipeRng>,
+    mut spawn_timer: ResMut<PipeSpawnTimer>,
+    mut pending_jump: ResMut<PendingJump>,
+    mut run_inputs: ResMut<RunInputs>,
+    mut commands: Commands,
+) {
+    seed.0 = match match_seed {
+        Some(match_seed) => {
+            commands.remove_resource::<MatchSeed>();
+            match_seed.0
+        }
+        None => seed_config.0.unwrap_or_else(rand::random),
+    };
+    pipe_rng.0 = StdRng::seed_from_u64(seed.0);
+    spawn_timer.0.set_duration(config.0.spawn_interval());
+    spawn_timer.0.reset();
+    *pending_jump = PendingJump::default();
+    run_inputs.0.clear();
+}
+
+fn bird_input(actions: Res<Actions>, mode: Res<GameMode>, mut pending_jump: ResMut<PendingJump>) {
+    if actions.just_pressed(Action::Jump) {
+        pending_jump.0[0] = true;
+    }
+    if mode.players() > 1 && actions.just_pressed(Action::JumpTwo) {
+        pending_jump.0[1] = true;
+    }
+}
+
+fn bird_jump(
+    config: Res<ActiveConfig>,
+    mut pending_jump: ResMut<PendingJump>,
+    mut query: Query<(&mut Bird, &Player), Without<Dead>>,
+    mut events: EventWriter<GameEvent>,
+) {
+    for (mut bird, player) in query.iter_mut() {
+        if pending_jump.0[player.0] {
+            bird.velocity = config.0.jump_velocity;
+            events.send(GameEvent::Flapped);
         }
     }
+    *pending_jump = PendingJump::default();
 }
 
-fn bird_movement(time: Res<Time>, mut query: Query<(&mut Transform, &mut Bird)>) {
+fn bird_movement(
+    time: Res<Time>,
+    config: Res<ActiveConfig>,
+    mut query: Query<(&mut Transform, &mut Bird), Without<Dead>>,
+) {
     for (mut transform, mut bird) in query.iter_mut() {
-        bird.velocity += GRAVITY * time.delta_seconds();
+        bird.velocity += config.0.gravity * time.delta_seconds();
         transform.translation.y += bird.velocity * time.delta_seconds();
     }
 }
@@ -373,40 +1227,39 @@ fn execute_animations(
     }
 }
 
-fn spawn_pipes(time: Res<Time>, mut timer: ResMut<PipeSpawnTimer>, mut commands: Commands) {
+fn spawn_pipes(
+    time: Res<Time>,
+    mut timer: ResMut<PipeSpawnTimer>,
+    mut pipe_rng: ResMut<PipeRng>,
+    config: Res<ActiveConfig>,
+    mut commands: Commands,
+) {
     if timer.0.tick(time.delta()).just_finished() {
-        let mut rng = rand::thread_rng();
-        let gap_y = rng.gen_range(-150.0..150.0);
+        let gap_y = sim::next_gap_y(&mut pipe_rng.0);
 
-        // Spawn top pipe
-        commands.spawn((
-            SpriteBundle {
-                sprite: Sprite {
-                    color: Color::srgb(0.0, 0.8, 0.0),
-                    custom_size: Some(Vec2::new(60.0, 400.0)),
-                    ..default()
+        // Spawn top and bottom pipe
+        for pipe_y in config.0.pipe_centres(gap_y) {
+            commands.spawn((
+                pipe_sprite(PIPE_SPAWN_X, pipe_y),
+                Pipe { gap_y },
+                Velocity {
+                    x: -config.0.pipe_speed,
                 },
-                transform: Transform::from_xyz(500.0, gap_y + PIPE_GAP / 2.0 + 200.0, 0.0),
-                ..default()
-            },
-            Pipe,
-            Velocity { x: -PIPE_SPEED },
-        ));
+                PreviousX(PIPE_SPAWN_X),
+            ));
+        }
+    }
+}
 
-        // Spawn bottom pipe
-        commands.spawn((
-            SpriteBundle {
-                sprite: Sprite {
-                    color: Color::srgb(0.0, 0.8, 0.0),
-                    custom_size: Some(Vec2::new(60.0, 400.0)),
-                    ..default()
-                },
-                transform: Transform::from_xyz(500.0, gap_y - PIPE_GAP / 2.0 - 200.0, 0.0),
-                ..default()
-            },
-            Pipe,
-            Velocity { x: -PIPE_SPEED },
-        ));
+fn pipe_sprite(x: f32, y: f32) -> SpriteBundle {
+    SpriteBundle {
+        sprite: Sprite {
+            color: Color::srgb(0.0, 0.8, 0.0),
+            custom_size: Some(Vec2::new(PIPE_WIDTH, PIPE_HEIGHT)),
+            ..default()
+        },
+        transform: Transform::from_xyz(x, y, 0.0),
+        ..default()
     }
 }
 
@@ -414,193 +1267,255 @@ fn pipe_movement(
     time: Res<Time>,
     difficulty: Res<GameDifficulty>,
     mut commands: Commands,
-    mut query: Query<(Entity, &mut Transform, &Velocity), With<Pipe>>,
+    mut query: Query<(Entity, &mut Transform, &Velocity, &mut PreviousX), With<Pipe>>,
 ) {
-    for (entity, mut transform, velocity) in query.iter_mut() {
+    for (entity, mut transform, velocity, mut previous) in query.iter_mut() {
+        previous.0 = transform.translation.x;
         transform.translation.x += velocity.x * time.delta_seconds() * difficulty.speed_multiplier;
 
         // Despawn pipes that are off screen
-        if transform.translation.x < -500.0 {
+        if transform.translation.x < PIPE_DESPAWN_X {
             commands.entity(entity).despawn();
         }
     }
 }
 
 fn check_collisions(
-    bird_query: Query<&Transform, With<Bird>>,
+    time: Res<Time>,
+    mode: Res<GameMode>,
+    mut commands: Commands,
+    mut bird_query: Query<(Entity, &Transform, &mut Sprite), (With<Bird>, Without<Dead>)>,
     pipe_query: Query<&Transform, With<Pipe>>,
     mut next_state: ResMut<NextState<GameState>>,
+    mut events: EventWriter<GameEvent>,
 ) {
-    for bird_transform in bird_query.iter() {
-        // Check ground collision
-        if bird_transform.translation.y < GROUND_HEIGHT + 25.0 {
-            next_state.set(GameState::GameOver);
-            return;
-        }
+    let mut alive = 0;
+    for (entity, bird_transform, mut sprite) in bird_query.iter_mut() {
+        let bird_pos = bird_transform.translation;
 
-        // Check ceiling collision
-        if bird_transform.translation.y > 300.0 {
+        // Check ground and ceiling collision, then pipe collision with a simple AABB
+        let crashed = sim::bounds_death(bird_pos.y).is_some()
+            || pipe_query.iter().any(|pipe_transform| {
+                let pipe_pos = pipe_transform.translation;
+                sim::hits_pipe(bird_pos.x, bird_pos.y, pipe_pos.x, pipe_pos.y)
+            });
+
+        if !crashed {
+            alive += 1;
+            continue;
+        }
+        events.send(GameEvent::Crashed);
+        if *mode == GameMode::Solo {
             next_state.set(GameState::GameOver);
             return;
         }
+        // The crashed bird freezes in place, greyed out, while the others play on
+        commands.entity(entity).insert(Dead { at: time.elapsed() });
+        sprite.color = Color::srgba(0.4, 0.4, 0.4, 0.6);
+    }
 
-        // Check pipe collision
-        for pipe_transform in pipe_query.iter() {
-            let bird_pos = bird_transform.translation;
-            let pipe_pos = pipe_transform.translation;
-
-            // Simple AABB collision
-            if (bird_pos.x - pipe_pos.x).abs() < 45.0 && (bird_pos.y - pipe_pos.y).abs() < 215.0 {
-                next_state.set(GameState::GameOver);
-                return;
-            }
-        }
+    if alive == 0 && !bird_query.is_empty() {
+        next_state.set(GameState::GameOver);
     }
 }
 
 fn update_score(
-    bird_query: Query<&Transform, With<Bird>>,
-    pipe_query: Query<&Transform, With<Pipe>>,
+    mut bird_query: Query<(&Player, &mut PlayerScore), Without<Dead>>,
+    pipe_query: Query<(&Transform, &PreviousX), With<Pipe>>,
+    config: Res<ActiveConfig>,
     mut score: ResMut<Score>,
     mut difficulty: ResMut<GameDifficulty>,
-    mut text_query: Query<&mut Text>,
+    mut text_query: Query<&mut Text, With<ScoreText>>,
+    mut player_text_query: Query<(&mut Text, &PlayerScoreText), Without<ScoreText>>,
+    mut events: EventWriter<GameEvent>,
 ) {
-    for bird_transform in bird_query.iter() {
-        for pipe_transform in pipe_query.iter() {
-            // Check if bird passed a pipe
-            if bird_transform.translation.x > pipe_transform.translation.x
-                && bird_transform.translation.x < pipe_transform.translation.x + 5.0
-            {
-                score.0 += 1;
-                
-                // Increase difficulty every 2 pipes (1 complete gap)
-                if score.0 % 2 == 0 {
-                    difficulty.pipes_passed += 1;
-                    difficulty.speed_multiplier = (1.0 + SPEED_INCREASE_RATE * difficulty.pipes_passed as f32)
-                        .min(MAX_SPEED_MULTIPLIER);
-                }
-                
-                for mut text in text_query.iter_mut() {
-                    text.sections[0].value = format!("Score: {}", score.0 / 2);
+    // Every bird flies at BIRD_X, so the course scores once and each surviving bird with it
+    if bird_query.is_empty() {
+        return;
+    }
+    for (pipe_transform, previous) in pipe_query.iter() {
+        // Check if bird passed a pipe
+        if sim::passes_bird(previous.0, pipe_transform.translation.x) {
+            score.0 += 1;
+
+            // Increase difficulty every 2 pipes (1 complete gap)
+            if score.0 % 2 == 0 {
+                events.send(GameEvent::Scored);
+                difficulty.pipes_passed += 1;
+                difficulty.speed_multiplier =
+                    config.0.speed_multiplier_for(difficulty.pipes_passed);
+            }
+
+            for mut text in text_query.iter_mut() {
+                text.sections[0].value = format!("Score: {}", score.0 / 2);
+            }
+
+            for (player, mut player_score) in bird_query.iter_mut() {
+                player_score.0 += 1;
+                for (mut text, owner) in player_text_query.iter_mut() {
+                    if owner.0 == player.0 {
+                        text.sections[0].value =
+                            format!("P{}: {}", player.0 + 1, player_score.0 / 2);
+                    }
                 }
             }
         }
     }
 }
 
-fn game_over_system(
-    keyboard: Res<ButtonInput<KeyCode>>,
-    mut next_state: ResMut<NextState<GameState>>,
+fn spawn_game_over_ui(
     mut commands: Commands,
-    entities_query: Query<Entity, Or<(With<Bird>, With<Pipe>, With<Text>, With<Sprite>)>>,
-    text_count_query: Query<&Text>,
-    button_query: Query<&StartButton>,
-    mut score: ResMut<Score>,
-    mut difficulty: ResMut<GameDifficulty>,
+    score: Res<Score>,
+    mode: Res<GameMode>,
+    bird_query: Query<(&Player, &PlayerScore, Option<&Dead>)>,
 ) {
-    // Spawn game over text and button on first frame
-    if text_count_query.iter().count() == 1 && button_query.iter().count() == 0 {
-        // Spawn game over text
-        commands.spawn((
-            TextBundle::from_section(
-                format!("Game Over! Score: {}", score.0 / 2),
+    let message = if *mode == GameMode::Solo {
+        format!("Game Over! Score: {}", score.0 / 2)
+    } else if *mode == GameMode::Coop {
+        format!("Game Over! Team score: {}", coop::team_score(&score))
+    } else {
+        let mut results: Vec<_> = bird_query
+            .iter()
+            .map(|(player, player_score, dead)| {
+                (player.0, player_score.0 / 2, dead.map(|dead| dead.at))
+            })
+            .collect();
+        results.sort_by_key(|&(player, _, _)| player);
+        let scores = results
+            .iter()
+            .map(|(player, score, _)| format!("P{}: {score}", player + 1))
+            .collect::<Vec<_>>()
+            .join("  ");
+        format!("{}\n{scores}", versus_verdict(&results))
+    };
+
+    // Spawn game over text
+    commands.spawn((
+        TextBundle::from_section(
+            message,
+            TextStyle {
+                font_size: 40.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(150.0),
+            left: Val::Px(250.0),
+            ..default()
+        }),
+        GameOverUi,
+    ));
+
+    // Spawn restart button
+    commands
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(200.0),
+                    height: Val::Px(65.0),
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(250.0),
+                    left: Val::Px(300.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::srgb(0.2, 0.7, 0.2).into(),
+                ..default()
+            },
+            StartButton,
+            GameOverUi,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "RESTART",
                 TextStyle {
                     font_size: 40.0,
                     color: Color::WHITE,
                     ..default()
                 },
-            )
-            .with_style(Style {
-                position_type: PositionType::Absolute,
-                top: Val::Px(150.0),
-                left: Val::Px(250.0),
-                ..default()
-            }),
-        ));
+            ));
+        });
+}
 
-        // Spawn restart button
-        commands
-            .spawn((
-                ButtonBundle {
-                    style: Style {
-                        width: Val::Px(200.0),
-                        height: Val::Px(65.0),
-                        position_type: PositionType::Absolute,
-                        top: Val::Px(250.0),
-                        left: Val::Px(300.0),
-                        justify_content: JustifyContent::Center,
-                        align_items: AlignItems::Center,
-                        ..default()
-                    },
-                    background_color: Color::srgb(0.2, 0.7, 0.2).into(),
-                    ..default()
-                },
-                StartButton,
-            ))
-            .with_children(|parent| {
-                parent.spawn(TextBundle::from_section(
-                    "RESTART",
-                    TextStyle {
-                        font_size: 40.0,
-                        color: Color::WHITE,
-                        ..default()
-                    },
-                ));
-            });
+/// Higher score wins; on equal scores the bird that stayed up longer does.
+fn versus_verdict(results: &[(usize, u32, Option<Duration>)]) -> String {
+    let key = |&(_, score, died): &(usize, u32, Option<Duration>)| (score, died);
+    let Some(best) = results.iter().map(key).max() else {
+        return "Game Over!".to_string();
+    };
+    let leaders: Vec<usize> = results
+        .iter()
+        .filter(|result| key(result) == best)
+        .map(|&(player, _, _)| player)
+        .collect();
+    match leaders.as_slice() {
+        [winner] => format!("Player {} wins!", winner + 1),
+        _ => "Draw!".to_string(),
     }
+}
 
-    if keyboard.just_pressed(KeyCode::KeyR) {
-        // Clean up all entities
-        for entity in entities_query.iter() {
-            commands.entity(entity).despawn();
-        }
-
-        // Reset score and difficulty
-        score.0 = 0;
-        *difficulty = GameDifficulty::default();
+fn game_over_system(
+    actions: Res<Actions>,
+    mut next_state: ResMut<NextState<GameState>>,
+    party: Option<Res<Party>>,
+) {
+    if actions.just_pressed(Action::Restart) {
+        next_state.set(after_game_over(party.is_some()));
+    }
+}
 
-        // Go back to menu
-        next_state.set(GameState::Menu);
+/// Where restarting from the game over screen leads: the menu, or the hand-off to the
+/// next player during a party.
+fn after_game_over(party: bool) -> GameState {
+    if party {
+        GameState::Handoff
+    } else {
+        GameState::Menu
+    }
+}
 
-        // Spawn start button
-        commands
-            .spawn((
-                ButtonBundle {
-                    style: Style {
-                        width: Val::Px(200.0),
-                        height: Val::Px(65.0),
-                        margin: UiRect::all(Val::Auto),
-                        justify_content: JustifyContent::Center,
-                        align_items: AlignItems::Center,
-                        ..default()
-                    },
-                    background_color: Color::srgb(0.2, 0.7, 0.2).into(),
-                    ..default()
-                },
-                StartButton,
-            ))
-            .with_children(|parent| {
-                parent.spawn(TextBundle::from_section(
-                    "START",
-                    TextStyle {
-                        font_size: 40.0,
-                        color: Color::WHITE,
-                        ..default()
-                    },
-                ));
-            });
+/// Clears the finished run when returning to the menu.
+fn reset_run(
+    mut commands: Commands,
+    entities_query: Query<
+        Entity,
+        (
+            Or<(With<Bird>, With<Pipe>, With<Text>, With<Sprite>)>,
+            Without<HudText>,
+            Without<BackgroundTile>,
+        ),
+    >,
+    mut score: ResMut<Score>,
+    mut difficulty: ResMut<GameDifficulty>,
+) {
+    // Clean up all entities
+    for entity in entities_query.iter() {
+        commands.entity(entity).despawn();
     }
+
+    // Reset score and difficulty
+    score.0 = 0;
+    *difficulty = GameDifficulty::default();
 }
 
 fn pause_input(
-    keyboard: Res<ButtonInput<KeyCode>>,
+    actions: Res<Actions>,
     mut next_state: ResMut<NextState<GameState>>,
     mut commands: Commands,
 ) {
-    if keyboard.just_pressed(KeyCode::KeyP) || keyboard.just_pressed(KeyCode::Escape) {
-        // Spawn pause text
-        commands.spawn((TextBundle::from_section(
-            "PAUSED\nPress P or Esc to Resume",
+    if actions.just_pressed(Action::Pause) {
+        pause_game(&mut commands, &mut next_state);
+    }
+}
+
+fn pause_game(commands: &mut Commands, next_state: &mut NextState<GameState>) {
+    // Spawn pause text
+    commands.spawn((
+        TextBundle::from_section(
+            "PAUSED\nPress P or Esc to Resume\nO: settings",
             TextStyle {
                 font_size: 40.0,
                 color: Color::WHITE,
@@ -612,45 +1527,40 @@ fn pause_input(
             top: Val::Px(250.0),
             left: Val::Px(280.0),
             ..default()
-        }),));
-        
-        next_state.set(GameState::Paused);
-    }
+        }),
+        PauseText,
+    ));
+    settings_menu::spawn_pause_settings_button(commands);
+
+    next_state.set(GameState::Paused);
 }
 
-fn unpause_system(
-    keyboard: Res<ButtonInput<KeyCode>>,
-    mut next_state: ResMut<NextState<GameState>>,
-    mut commands: Commands,
-    text_query: Query<Entity, With<Text>>,
-) {
-    if keyboard.just_pressed(KeyCode::KeyP) || keyboard.just_pressed(KeyCode::Escape) {
-        // Remove pause text (keep only score text)
-        let mut count = 0;
-        for entity in text_query.iter() {
-            if count > 0 {
-                commands.entity(entity).despawn();
-            }
-            count += 1;
-        }
-        
+fn unpause_system(actions: Res<Actions>, mut next_state: ResMut<NextState<GameState>>) {
+    if actions.just_pressed(Action::Pause) {
         next_state.set(GameState::Playing);
     }
 }
 
+/// Removes every entity tagged with `T`, used to tear down per-state UI.
+fn despawn_with<T: Component>(mut commands: Commands, query: Query<Entity, With<T>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
 fn screenshot_input(
-    keyboard: Res<ButtonInput<KeyCode>>,
+    actions: Res<Actions>,
     main_window: Query<Entity, With<PrimaryWindow>>,
     mut screenshot_manager: ResMut<ScreenshotManager>,
 ) {
-    if keyboard.just_pressed(KeyCode::KeyS) {
+    if actions.just_pressed(Action::Screenshot) {
         let path = format!("./screenshot-{}.png", chrono::Local::now().format("%Y%m%d-%H%M%S"));
         
         if let Ok(entity) = main_window.get_single() {
             screenshot_manager
                 .save_screenshot_to_disk(entity, path)
                 .unwrap();
-            println!("Screenshot saved!");
+            eprintln!("Screenshot saved!");
         }
     }
 }
@@ -0,0 +1,293 @@
+//! Background music generated as it plays.
+//!
+//! A four-bar loop in A minor whose tempo and layers follow the game's speed multiplier:
+//! a pad, bass and soft kick at the start of a run, then hi-hats, an arpeggio and a
+//! four-on-the-floor kick as the pipes speed up. [`Music`] is an endless iterator of
+//! samples at [`SAMPLE_RATE`], so the game streams it and tools can render it to a file.
+
+use crate::sim::MAX_SPEED_MULTIPLIER;
+use crate::synth::{envelope, sample_count, Wave, SAMPLE_RATE};
+
+/// Tempo at speed multiplier 1.
+pub const BASE_BPM: f32 = 96.0;
+/// Extra tempo per unit of speed multiplier above 1, as a fraction of [`BASE_BPM`].
+const TEMPO_PER_SPEED: f32 = 0.5;
+const STEPS_PER_BAR: u32 = 16;
+/// Chord root (MIDI note) and whether it's minor, one chord per bar: Am F C G.
+const PROGRESSION: [(u8, bool); 4] = [(45, true), (41, false), (48, false), (43, false)];
+/// Loudness while ducked, relative to normal.
+const DUCKED_GAIN: f32 = 0.3;
+/// Fraction of the way to the target gain covered each sample, about 50 ms to settle.
+const GAIN_SMOOTHING: f32 = 1.0 / 2205.0;
+
+fn midi_hz(note: u8) -> f32 {
+    440.0 * 2f32.powf((note as f32 - 69.0) / 12.0)
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Timbre {
+    Tone(Wave),
+    /// Short noise burst, for hi-hats.
+    Noise,
+    /// Sine dropping fast in pitch.
+    Kick,
+}
+
+/// One sounding note.
+struct Voice {
+    timbre: Timbre,
+    frequency: f32,
+    gain: f32,
+    /// Samples to wait before starting, so a phrase can be queued at once.
+    delay: usize,
+    age: usize,
+    len: usize,
+    phase: f32,
+    noise: u32,
+}
+
+impl Voice {
+    fn new(timbre: Timbre, frequency: f32, seconds: f32, gain: f32) -> Self {
+        Self {
+            timbre,
+            frequency,
+            gain,
+            delay: 0,
+            age: 0,
+            len: sample_count(seconds).max(1),
+            phase: 0.0,
+            noise: 0x9E37_79B9,
+        }
+    }
+
+    fn after(mut self, seconds: f32) -> Self {
+        self.delay = sample_count(seconds);
+        self
+    }
+
+    fn done(&self) -> bool {
+        self.delay == 0 && self.age >= self.len
+    }
+
+    fn next(&mut self) -> f32 {
+        if self.delay > 0 {
+            self.delay -= 1;
+            return 0.0;
+        }
+        let value = match self.timbre {
+            Timbre::Tone(wave) => {
+                self.phase = (self.phase + self.frequency / SAMPLE_RATE as f32).fract();
+                wave.at(self.phase)
+            }
+            Timbre::Noise => {
+                // xorshift32
+                self.noise ^= self.noise << 13;
+                self.noise ^= self.noise >> 17;
+                self.noise ^= self.noise << 5;
+                self.noise as f32 / u32::MAX as f32 * 2.0 - 1.0
+            }
+            Timbre::Kick => {
+                let t = self.age as f32 / self.len as f32;
+                let frequency = self.frequency * 0.3f32.powf(t);
+                self.phase = (self.phase + frequency / SAMPLE_RATE as f32).fract();
+                Wave::Sine.at(self.phase)
+            }
+        };
+        let sample = value * envelope(self.age, self.len) * self.gain;
+        self.age += 1;
+        sample
+    }
+}
+
+/// Endless soundtrack; steer it with [`set_speed`](Music::set_speed),
+/// [`set_ducked`](Music::set_ducked) and [`sting`](Music::sting) between samples.
+pub struct Music {
+    speed: f32,
+    ducked: bool,
+    gain: f32,
+    /// Sixteenth note within the loop that plays next.
+    step: u32,
+    /// Samples left in the current step.
+    until_step: usize,
+    voices: Vec<Voice>,
+    /// Samples left of the game-over sting, during which the loop rests.
+    sting: usize,
+}
+
+impl Default for Music {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            ducked: false,
+            gain: 1.0,
+            step: 0,
+            until_step: 0,
+            voices: Vec::new(),
+            sting: 0,
+        }
+    }
+}
+
+impl Music {
+    /// Follows the game's speed multiplier; new tempo and layers start on the next step.
+    pub fn set_speed(&mut self, multiplier: f32) {
+        self.speed = multiplier.clamp(0.5, 2.0 * MAX_SPEED_MULTIPLIER);
+    }
+
+    /// Fades down to a background level, or back up.
+    pub fn set_ducked(&mut self, ducked: bool) {
+        self.ducked = ducked;
+    }
+
+    pub fn tempo(&self) -> f32 {
+        BASE_BPM * (1.0 + TEMPO_PER_SPEED * (self.speed - 1.0))
+    }
+
+    /// 0 at the start of a run, 1 at the shipped game's top speed.
+    pub fn intensity(&self) -> f32 {
+        ((self.speed - 1.0) / (MAX_SPEED_MULTIPLIER - 1.0)).clamp(0.0, 1.0)
+    }
+
+    /// Rests the loop for a falling phrase, then starts it again from the top.
+    pub fn sting(&mut self) {
+        // E5 C5 A4 E4, then a low A minor chord
+        for (i, note) in [76, 72, 69, 64].into_iter().enumerate() {
+            let voice = Voice::new(Timbre::Tone(Wave::Square), midi_hz(note), 0.3, 0.1);
+            self.voices.push(voice.after(i as f32 * 0.14));
+        }
+        for note in [45, 57, 60, 64] {
+            let voice = Voice::new(Timbre::Tone(Wave::Triangle), midi_hz(note), 1.4, 0.12);
+            self.voices.push(voice.after(0.56));
+        }
+        self.sting = sample_count(2.4);
+    }
+
+    fn step_seconds(&self) -> f32 {
+        60.0 / self.tempo() / 4.0
+    }
+
+    fn play_step(&mut self) {
+        let bar = (self.step / STEPS_PER_BAR) as usize % PROGRESSION.len();
+        let beat = self.step % STEPS_PER_BAR;
+        let (root, minor) = PROGRESSION[bar];
+        let chord = [0, if minor { 3 } else { 4 }, 7];
+        let intensity = self.intensity();
+        let step = self.step_seconds();
+        let sine = Timbre::Tone(Wave::Sine);
+
+        if beat == 0 {
+            for interval in chord {
+                let frequency = midi_hz(root + 12 + interval);
+                self.voices
+                    .push(Voice::new(sine, frequency, step * 16.0, 0.05));
+            }
+        }
+        if beat.is_multiple_of(8) || (beat.is_multiple_of(4) && intensity >= 0.3) {
+            let bass = Timbre::Tone(Wave::Triangle);
+            self.voices
+                .push(Voice::new(bass, midi_hz(root), step * 3.0, 0.25));
+        }
+        if beat.is_multiple_of(8) || (beat.is_multiple_of(4) && intensity >= 0.5) {
+            self.voices
+                .push(Voice::new(Timbre::Kick, 150.0, 0.16, 0.35));
+        }
+        if (beat % 4 == 2 && intensity >= 0.2) || (beat.is_multiple_of(2) && intensity >= 0.6) {
+            self.voices.push(Voice::new(Timbre::Noise, 0.0, 0.03, 0.06));
+        }
+        let arpeggio_every = if intensity >= 0.7 { 1 } else { 2 };
+        if intensity >= 0.35 && beat.is_multiple_of(arpeggio_every) {
+            let interval = chord[(beat / arpeggio_every) as usize % chord.len()];
+            let lead = Timbre::Tone(Wave::Square);
+            let frequency = midi_hz(root + 24 + interval);
+            self.voices
+                .push(Voice::new(lead, frequency, step * 1.5, 0.05));
+        }
+    }
+}
+
+impl Iterator for Music {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.sting > 0 {
+            self.sting -= 1;
+            if self.sting == 0 {
+                self.step = 0;
+                self.until_step = 0;
+            }
+        } else {
+            if self.until_step == 0 {
+                self.play_step();
+                self.step = (self.step + 1) % (STEPS_PER_BAR * PROGRESSION.len() as u32);
+                self.until_step = sample_count(self.step_seconds()).max(1);
+            }
+            self.until_step -= 1;
+        }
+
+        let target = if self.ducked { DUCKED_GAIN } else { 1.0 };
+        self.gain += (target - self.gain) * GAIN_SMOOTHING;
+        let sum: f32 = self.voices.iter_mut().map(Voice::next).sum();
+        self.voices.retain(|voice| !voice.done());
+        Some((sum * self.gain).clamp(-1.0, 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synth;
+
+    fn render(music: &mut Music, seconds: f32) -> Vec<f32> {
+        music.take(sample_count(seconds)).collect()
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn renders_an_audible_wav() {
+        let mut music = Music::default();
+        music.set_speed(1.5);
+        let samples = render(&mut music, 4.0);
+        assert_eq!(samples.len(), 4 * SAMPLE_RATE as usize);
+        assert!(samples.iter().all(|s| (-1.0..=1.0).contains(s)));
+        assert!(rms(&samples) > 0.01, "music is silent");
+
+        let path = std::env::temp_dir().join(format!("rlappy-music-{}.wav", std::process::id()));
+        synth::write_wav(&path, &samples).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(&bytes[8..16], b"WAVEfmt ");
+        assert_eq!(&bytes[36..40], b"data");
+        let data_len = samples.len() * 2;
+        assert_eq!(bytes.len(), 44 + data_len);
+        assert_eq!(bytes[4..8], (36 + data_len as u32).to_le_bytes());
+        assert_eq!(bytes[40..44], (data_len as u32).to_le_bytes());
+    }
+
+    #[test]
+    fn tempo_rises_with_speed() {
+        let mut music = Music::default();
+        assert_eq!(music.tempo(), BASE_BPM);
+        let mut previous = music.tempo();
+        for speed in [1.25, 1.5, 2.0, MAX_SPEED_MULTIPLIER] {
+            music.set_speed(speed);
+            assert!(music.tempo() > previous, "tempo fell at speed {speed}");
+            previous = music.tempo();
+        }
+    }
+
+    #[test]
+    fn ducking_lowers_the_level() {
+        let mut normal = Music::default();
+        let mut ducked = Music::default();
+        ducked.set_ducked(true);
+        // Skip the first half second while the ducked gain settles
+        let normal = rms(&render(&mut normal, 3.0)[sample_count(0.5)..]);
+        let ducked = rms(&render(&mut ducked, 3.0)[sample_count(0.5)..]);
+        assert!(ducked < normal * 0.5, "ducked {ducked} vs normal {normal}");
+        assert!(ducked > 0.0);
+    }
+}
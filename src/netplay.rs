@@ -0,0 +1,818 @@
+//! Two-player versus over an unreliable datagram link, with rollback to hide latency.
+//!
+//! Both peers simulate the same seeded course and exchange only jump inputs. Until the
+//! opponent's input for a tick arrives it is predicted as "no jump"; when a late input
+//! turns out to be a jump, the match rolls back to the last tick on which every input was
+//! known and re-simulates from there. Each player's run is a plain [`Simulation`], so a
+//! snapshot is just a clone.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+use crate::sim::{GameConfig, Simulation};
+
+pub const PLAYERS: usize = 2;
+/// Ticks between a local press and the tick it applies to. Both peers use the same delay,
+/// which hides that much latency without any rollback.
+pub const INPUT_DELAY: u32 = 2;
+/// Ticks a peer may run ahead of the last input it has from the opponent before it waits.
+pub const MAX_PREDICTION: u32 = 12;
+/// Every packet repeats all inputs the opponent has not acknowledged, up to this many,
+/// so any packet that arrives makes up for the ones lost before it.
+const MAX_INPUTS_PER_PACKET: usize = 240;
+
+const MAGIC: &[u8; 4] = b"RLNP";
+pub const PROTOCOL_VERSION: u8 = 1;
+
+/// Both players' runs on a shared course, one tick at a time.
+#[derive(Clone, Debug)]
+pub struct MatchState {
+    pub tick: u32,
+    pub players: [Simulation; PLAYERS],
+}
+
+impl MatchState {
+    pub fn new(seed: u64, config: GameConfig) -> Self {
+        let sim = Simulation::with_config(seed, config);
+        Self {
+            tick: 0,
+            players: [sim.clone(), sim],
+        }
+    }
+
+    /// Advances both runs; a player who has crashed stays frozen.
+    pub fn step(&mut self, inputs: [bool; PLAYERS]) {
+        for (sim, jump) in self.players.iter_mut().zip(inputs) {
+            sim.step(jump);
+        }
+        self.tick += 1;
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.players.iter().all(Simulation::is_over)
+    }
+
+    /// FNV-1a over everything that shows on screen or affects later ticks, for checking
+    /// that two peers agree.
+    pub fn checksum(&self) -> u64 {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut mix = |value: u32| {
+            for byte in value.to_le_bytes() {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            }
+        };
+
+        mix(self.tick);
+        for sim in &self.players {
+            mix(sim.tick);
+            mix(sim.score);
+            mix(sim.bird.y.to_bits());
+            mix(sim.bird.velocity.to_bits());
+            mix(sim.speed_multiplier.to_bits());
+            mix(sim.death.map_or(0, |cause| cause as u32 + 1));
+            for pipe in &sim.pipes {
+                mix(pipe.x.to_bits());
+                mix(pipe.gap_y.to_bits());
+            }
+        }
+        hash
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SessionStats {
+    /// Late opponent inputs that contradicted the prediction.
+    pub rollbacks: u32,
+    pub resimulated_ticks: u32,
+    /// Ticks skipped because the opponent was more than `MAX_PREDICTION` ticks behind.
+    pub stalls: u32,
+}
+
+/// One peer's view of a match: every input known so far, the last state all inputs are
+/// known for, and the predicted state shown to the player.
+#[derive(Clone, Debug)]
+pub struct Session {
+    local: usize,
+    seed: u64,
+    inputs: [Vec<bool>; PLAYERS],
+    confirmed: MatchState,
+    current: MatchState,
+    /// Local inputs the opponent has confirmed receiving.
+    remote_acked: usize,
+    pub stats: SessionStats,
+}
+
+impl Session {
+    pub fn new(local: usize, seed: u64, config: GameConfig) -> Self {
+        let state = MatchState::new(seed, config);
+        // Both sides know nobody can press anything during the input delay
+        let delay = vec![false; INPUT_DELAY as usize];
+        Self {
+            local,
+            seed,
+            inputs: [delay.clone(), delay],
+            confirmed: state.clone(),
+            current: state,
+            remote_acked: 0,
+            stats: SessionStats::default(),
+        }
+    }
+
+    pub fn local(&self) -> usize {
+        self.local
+    }
+
+    pub fn remote(&self) -> usize {
+        PLAYERS - 1 - self.local
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Predicted state at the current tick, which is what the player sees.
+    pub fn state(&self) -> &MatchState {
+        &self.current
+    }
+
+    /// Latest state every input is known for; the same on both peers.
+    pub fn confirmed(&self) -> &MatchState {
+        &self.confirmed
+    }
+
+    /// Inputs known for `player`, one per tick from tick zero.
+    pub fn inputs(&self, player: usize) -> &[bool] {
+        &self.inputs[player]
+    }
+
+    /// Whether anything has arrived from the opponent yet.
+    pub fn heard_from_remote(&self) -> bool {
+        self.inputs[self.remote()].len() > INPUT_DELAY as usize
+    }
+
+    /// The match is decided once both birds are down on inputs both peers agree on.
+    pub fn is_finished(&self) -> bool {
+        self.confirmed.is_over()
+    }
+
+    pub fn can_advance(&self) -> bool {
+        (self.current.tick as usize) < self.inputs[self.remote()].len() + MAX_PREDICTION as usize
+    }
+
+    /// Queues the local input, which applies `INPUT_DELAY` ticks from now, and simulates
+    /// one tick. Returns `false` without doing anything while waiting for the opponent.
+    pub fn advance(&mut self, jump: bool) -> bool {
+        if !self.can_advance() {
+            self.stats.stalls += 1;
+            return false;
+        }
+        self.inputs[self.local].push(jump);
+        let inputs = self.inputs_at(self.current.tick);
+        self.current.step(inputs);
+        self.confirm();
+        true
+    }
+
+    /// Takes in the opponent's inputs, rolling back if one of them was mispredicted.
+    pub fn receive(&mut self, packet: &InputPacket) {
+        if packet.seed != self.seed {
+            return; // Left over from a previous match
+        }
+        self.remote_acked = self
+            .remote_acked
+            .max(packet.ack as usize)
+            .min(self.inputs[self.local].len());
+
+        let remote = self.remote();
+        let known = self.inputs[remote].len();
+        let start = packet.from_tick as usize;
+        if start > known {
+            return; // Reordered past a packet we have not seen; a later one repeats it
+        }
+
+        let mut mispredicted = false;
+        for (tick, &jump) in (start..).zip(&packet.inputs).skip(known - start) {
+            // Missing inputs are predicted as no jump, so only a jump can contradict them
+            mispredicted |= jump && tick < self.current.tick as usize;
+            self.inputs[remote].push(jump);
+        }
+
+        if mispredicted {
+            self.rollback();
+        } else {
+            self.confirm();
+        }
+    }
+
+    /// Local inputs the opponent has not acknowledged, plus our own acknowledgement.
+    pub fn outgoing(&self) -> InputPacket {
+        let local = &self.inputs[self.local];
+        let from = self.remote_acked.min(local.len());
+        let to = local.len().min(from + MAX_INPUTS_PER_PACKET);
+        InputPacket {
+            seed: self.seed,
+            from_tick: from as u32,
+            ack: self.inputs[self.remote()].len() as u32,
+            inputs: local[from..to].to_vec(),
+        }
+    }
+
+    fn inputs_at(&self, tick: u32) -> [bool; PLAYERS] {
+        std::array::from_fn(|player| {
+            self.inputs[player]
+                .get(tick as usize)
+                .copied()
+                .unwrap_or(false)
+        })
+    }
+
+    /// Moves the confirmed state up to the last tick both inputs are known for.
+    fn confirm(&mut self) {
+        let known = self.inputs.iter().map(Vec::len).min().unwrap_or(0);
+        let end = known.min(self.current.tick as usize) as u32;
+        while self.confirmed.tick < end && !self.confirmed.is_over() {
+            let inputs = self.inputs_at(self.confirmed.tick);
+            self.confirmed.step(inputs);
+        }
+    }
+
+    /// Restarts the shown state from the confirmed one and re-simulates to the current tick.
+    fn rollback(&mut self) {
+        self.confirm();
+        let target = self.current.tick;
+        self.current = self.confirmed.clone();
+        while self.current.tick < target {
+            let inputs = self.inputs_at(self.current.tick);
+            self.current.step(inputs);
+            self.stats.resimulated_ticks += 1;
+        }
+        self.stats.rollbacks += 1;
+    }
+}
+
+/// Re-simulates a match from both players' inputs, as a referee would, stopping early
+/// once both players are down.
+pub fn resimulate(
+    seed: u64,
+    config: GameConfig,
+    inputs: [&[bool]; PLAYERS],
+    ticks: u32,
+) -> MatchState {
+    let mut state = MatchState::new(seed, config);
+    while state.tick < ticks && !state.is_over() {
+        let tick = state.tick as usize;
+        state.step(std::array::from_fn(|player| {
+            inputs[player].get(tick).copied().unwrap_or(false)
+        }));
+    }
+    state
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct InputPacket {
+    /// Seed of the match, so packets from an earlier match are ignored.
+    pub seed: u64,
+    pub from_tick: u32,
+    /// Number of the receiver's inputs the sender has.
+    pub ack: u32,
+    pub inputs: Vec<bool>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Packet {
+    /// Sent by a joining peer until the host answers.
+    Hello,
+    /// The host's answer, fixing the course for the match.
+    Welcome {
+        seed: u64,
+        config: GameConfig,
+    },
+    Input(InputPacket),
+}
+
+impl Packet {
+    /// Little-endian: magic, protocol version, kind, then the kind's fields. Inputs are
+    /// packed eight to a byte.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(PROTOCOL_VERSION);
+        match self {
+            Packet::Hello => bytes.push(0),
+            Packet::Welcome { seed, config } => {
+                bytes.push(1);
+                bytes.extend_from_slice(&seed.to_le_bytes());
+                for value in config_fields(config) {
+                    bytes.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+            Packet::Input(input) => {
+                bytes.push(2);
+                bytes.extend_from_slice(&input.seed.to_le_bytes());
+                bytes.extend_from_slice(&input.from_tick.to_le_bytes());
+                bytes.extend_from_slice(&input.ack.to_le_bytes());
+                bytes.extend_from_slice(&(input.inputs.len() as u16).to_le_bytes());
+                for chunk in input.inputs.chunks(8) {
+                    let byte = chunk
+                        .iter()
+                        .enumerate()
+                        .fold(0u8, |byte, (bit, &jump)| byte | (jump as u8) << bit);
+                    bytes.push(byte);
+                }
+            }
+        }
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let rest = bytes.strip_prefix(MAGIC.as_slice())?;
+        let (&[version, kind], mut rest) = rest.split_first_chunk::<2>()?;
+        if version != PROTOCOL_VERSION {
+            return None;
+        }
+
+        match kind {
+            0 => Some(Packet::Hello),
+            1 => {
+                let seed = u64::from_le_bytes(take(&mut rest)?);
+                let mut fields = [0.0f32; 7];
+                for field in &mut fields {
+                    *field = f32::from_le_bytes(take(&mut rest)?);
+                }
+                Some(Packet::Welcome {
+                    seed,
+                    config: config_from_fields(fields)?,
+                })
+            }
+            2 => {
+                let seed = u64::from_le_bytes(take(&mut rest)?);
+                let from_tick = u32::from_le_bytes(take(&mut rest)?);
+                let ack = u32::from_le_bytes(take(&mut rest)?);
+                let count = u16::from_le_bytes(take(&mut rest)?) as usize;
+                if rest.len() < count.div_ceil(8) {
+                    return None;
+                }
+                let inputs = (0..count)
+                    .map(|i| rest[i / 8] & (1 << (i % 8)) != 0)
+                    .collect();
+                Some(Packet::Input(InputPacket {
+                    seed,
+                    from_tick,
+                    ack,
+                    inputs,
+                }))
+            }
+            _ => None,
+        }
+    }
+}
+
+pub(crate) fn config_fields(config: &GameConfig) -> [f32; 7] {
+    [
+        config.gravity,
+        config.jump_velocity,
+        config.pipe_speed,
+        config.pipe_gap,
+        config.pipe_spawn_interval,
+        config.speed_increase_rate,
+        config.max_speed_multiplier,
+    ]
+}
+
+/// Inverse of `config_fields`, or `None` if the config doesn't pass
+/// [`GameConfig::validate`].
+pub(crate) fn config_from_fields(fields: [f32; 7]) -> Option<GameConfig> {
+    let config = GameConfig {
+        gravity: fields[0],
+        jump_velocity: fields[1],
+        pipe_speed: fields[2],
+        pipe_gap: fields[3],
+        pipe_spawn_interval: fields[4],
+        speed_increase_rate: fields[5],
+        max_speed_multiplier: fields[6],
+    };
+    config.validate().ok().map(|()| config)
+}
+
+pub(crate) fn take<const N: usize>(bytes: &mut &[u8]) -> Option<[u8; N]> {
+    let (head, rest) = bytes.split_first_chunk::<N>()?;
+    *bytes = rest;
+    Some(*head)
+}
+
+/// Unreliable, unordered datagram transport to one peer.
+pub trait Link {
+    fn send(&mut self, datagram: &[u8]);
+    /// Next datagram received, without blocking.
+    fn recv(&mut self) -> Option<Vec<u8>>;
+}
+
+pub struct UdpLink {
+    socket: UdpSocket,
+    peer: Option<SocketAddr>,
+}
+
+impl UdpLink {
+    /// Listens on `port` and talks to whichever peer is heard from first.
+    pub fn host(port: u16) -> io::Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))?;
+        socket.set_nonblocking(true)?;
+        Ok(Self { socket, peer: None })
+    }
+
+    pub fn join(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let peer = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::other("address did not resolve"))?;
+        let local: SocketAddr = if peer.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            (std::net::Ipv6Addr::UNSPECIFIED, 0).into()
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            peer: Some(peer),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+}
+
+impl Link for UdpLink {
+    fn send(&mut self, datagram: &[u8]) {
+        if let Some(peer) = self.peer {
+            // Datagrams may be lost anyway, so a failed send is just another loss
+            let _ = self.socket.send_to(datagram, peer);
+        }
+    }
+
+    fn recv(&mut self) -> Option<Vec<u8>> {
+        let mut buffer = [0u8; 1500];
+        loop {
+            let (len, from) = self.socket.recv_from(&mut buffer).ok()?;
+            match self.peer {
+                Some(peer) if peer != from => continue,
+                _ => {
+                    self.peer = Some(from);
+                    return Some(buffer[..len].to_vec());
+                }
+            }
+        }
+    }
+}
+
+/// In-process link, for running both peers without sockets.
+pub struct MemoryLink {
+    outgoing: Sender<Vec<u8>>,
+    incoming: Receiver<Vec<u8>>,
+}
+
+pub fn memory_pair() -> (MemoryLink, MemoryLink) {
+    let (a_out, b_in) = channel();
+    let (b_out, a_in) = channel();
+    (
+        MemoryLink {
+            outgoing: a_out,
+            incoming: a_in,
+        },
+        MemoryLink {
+            outgoing: b_out,
+            incoming: b_in,
+        },
+    )
+}
+
+impl Link for MemoryLink {
+    fn send(&mut self, datagram: &[u8]) {
+        let _ = self.outgoing.send(datagram.to_vec());
+    }
+
+    fn recv(&mut self) -> Option<Vec<u8>> {
+        self.incoming.try_recv().ok()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LinkConditions {
+    /// Chance each datagram is dropped.
+    pub loss: f64,
+    pub latency: Duration,
+    /// Extra delay drawn uniformly up to this much per datagram, which also reorders them.
+    pub jitter: Duration,
+}
+
+/// Wraps a link and degrades what it sends, to try netplay against a bad connection.
+pub struct LossyLink<L> {
+    inner: L,
+    conditions: LinkConditions,
+    rng: StdRng,
+    queued: Vec<(Instant, Vec<u8>)>,
+}
+
+impl<L: Link> LossyLink<L> {
+    pub fn new(inner: L, conditions: LinkConditions, seed: u64) -> Self {
+        Self {
+            inner,
+            conditions,
+            rng: StdRng::seed_from_u64(seed),
+            queued: Vec::new(),
+        }
+    }
+
+    fn flush(&mut self) {
+        let now = Instant::now();
+        let inner = &mut self.inner;
+        self.queued.retain(|(due, datagram)| {
+            if *due <= now {
+                inner.send(datagram);
+                false
+            } else {
+                true
+            }
+        });
+    }
+}
+
+impl<L: Link> Link for LossyLink<L> {
+    fn send(&mut self, datagram: &[u8]) {
+        if !self.rng.gen_bool(self.conditions.loss.clamp(0.0, 1.0)) {
+            let jitter = self.conditions.jitter.mul_f64(self.rng.gen());
+            let due = Instant::now() + self.conditions.latency + jitter;
+            self.queued.push((due, datagram.to_vec()));
+        }
+        self.flush();
+    }
+
+    fn recv(&mut self) -> Option<Vec<u8>> {
+        self.flush();
+        self.inner.recv()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Role {
+    /// Picks the course and plays as player one.
+    Host { seed: u64, config: GameConfig },
+    /// Plays as player two on the host's course.
+    Join,
+}
+
+/// A link plus the handshake that starts a [`Session`] on both ends.
+pub struct Peer<L> {
+    link: L,
+    role: Role,
+    session: Option<Session>,
+    last_heard: Option<Instant>,
+}
+
+impl<L: Link> Peer<L> {
+    pub fn new(link: L, role: Role) -> Self {
+        Self {
+            link,
+            role,
+            session: None,
+            last_heard: None,
+        }
+    }
+
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    pub fn session(&self) -> Option<&Session> {
+        self.session.as_ref()
+    }
+
+    pub fn session_mut(&mut self) -> Option<&mut Session> {
+        self.session.as_mut()
+    }
+
+    /// Handles everything received since the last call. A joining peer also keeps asking
+    /// to join until the host answers.
+    pub fn poll(&mut self) {
+        if self.role == Role::Join && self.session.is_none() {
+            self.link.send(&Packet::Hello.encode());
+        }
+
+        while let Some(datagram) = self.link.recv() {
+            let Some(packet) = Packet::decode(&datagram) else {
+                continue;
+            };
+            self.last_heard = Some(Instant::now());
+
+            match (packet, self.role) {
+                (Packet::Hello, Role::Host { seed, config }) => {
+                    // Answer again if our first welcome was lost, but not once play is
+                    // under way, since that Hello is from a rematch we are not ready for
+                    let starting = self
+                        .session
+                        .as_ref()
+                        .is_none_or(|session| !session.heard_from_remote());
+                    if starting {
+                        self.link.send(&Packet::Welcome { seed, config }.encode());
+                        self.session
+                            .get_or_insert_with(|| Session::new(0, seed, config));
+                    }
+                }
+                (Packet::Welcome { seed, config }, Role::Join) => {
+                    self.session
+                        .get_or_insert_with(|| Session::new(1, seed, config));
+                }
+                (Packet::Input(input), _) => {
+                    if let Some(session) = &mut self.session {
+                        session.receive(&input);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Sends the inputs the opponent still needs. Call once per tick, including after the
+    /// match ends so the opponent can confirm it too.
+    pub fn send_inputs(&mut self) {
+        if let Some(session) = &self.session {
+            self.link.send(&Packet::Input(session.outgoing()).encode());
+        }
+    }
+
+    /// Time since anything was last heard from the opponent.
+    pub fn silence(&self) -> Option<Duration> {
+        self.last_heard.map(|heard| heard.elapsed())
+    }
+
+    /// Drops the current match. A host will start the next one on `seed` when the
+    /// opponent asks; a joiner starts asking again.
+    pub fn rematch(&mut self, seed: u64) {
+        self.session = None;
+        if let Role::Host {
+            seed: host_seed, ..
+        } = &mut self.role
+        {
+            *host_seed = seed;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::Bot;
+
+    const SEED: u64 = 7;
+    const TICKS: u32 = 300;
+
+    /// Both players' runs stepped directly on their inputs, without any rollback.
+    fn straight_run(inputs: [&[bool]; PLAYERS], ticks: u32) -> MatchState {
+        let mut players: [Simulation; PLAYERS] =
+            std::array::from_fn(|_| Simulation::with_config(SEED, GameConfig::default()));
+        for (sim, inputs) in players.iter_mut().zip(inputs) {
+            for tick in 0..ticks as usize {
+                sim.step(inputs.get(tick).copied().unwrap_or(false));
+            }
+        }
+        MatchState {
+            tick: ticks,
+            players,
+        }
+    }
+
+    fn deliver(link: &mut impl Link, session: &mut Session) {
+        while let Some(datagram) = link.recv() {
+            if let Some(Packet::Input(input)) = Packet::decode(&datagram) {
+                session.receive(&input);
+            }
+        }
+    }
+
+    #[test]
+    fn peers_agree_over_a_bad_link() {
+        let conditions = LinkConditions {
+            loss: 0.2,
+            latency: Duration::from_millis(5),
+            jitter: Duration::from_millis(10),
+        };
+        let (a, b) = memory_pair();
+        let mut links = [
+            LossyLink::new(a, conditions, 1),
+            LossyLink::new(b, conditions, 2),
+        ];
+        let mut sessions: [Session; PLAYERS] =
+            std::array::from_fn(|local| Session::new(local, SEED, GameConfig::default()));
+        let mut bots: [Bot; PLAYERS] = std::array::from_fn(|local| Bot::new(0.9, local as u64));
+
+        let started = Instant::now();
+        while sessions
+            .iter()
+            .any(|session| !session.is_finished() && session.confirmed().tick < TICKS)
+        {
+            assert!(started.elapsed() < Duration::from_secs(30), "peers stalled");
+            for ((link, session), bot) in links.iter_mut().zip(&mut sessions).zip(&mut bots) {
+                deliver(link, session);
+                if session.state().tick < TICKS {
+                    let local = session.local();
+                    let jump = bot.decide(session.state().players[local].observation());
+                    session.advance(jump);
+                }
+                link.send(&Packet::Input(session.outgoing()).encode());
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        // The link was bad enough to need rollbacks
+        assert!(sessions.iter().any(|session| session.stats.rollbacks > 0));
+        for session in &sessions {
+            let confirmed = session.confirmed();
+            let straight = straight_run([session.inputs(0), session.inputs(1)], confirmed.tick);
+            assert_eq!(confirmed.checksum(), straight.checksum());
+        }
+        let [a, b] = &sessions;
+        let common = a.confirmed().tick.min(b.confirmed().tick);
+        assert_eq!(
+            straight_run([a.inputs(0), a.inputs(1)], common).checksum(),
+            straight_run([b.inputs(0), b.inputs(1)], common).checksum()
+        );
+    }
+
+    #[test]
+    fn late_remote_jump_rolls_back() {
+        let mut host = Session::new(0, SEED, GameConfig::default());
+        let mut joiner = Session::new(1, SEED, GameConfig::default());
+
+        // The joiner jumps at once, but the host plays on hearing nothing
+        joiner.advance(true);
+        for _ in 0..6 {
+            host.advance(false);
+        }
+        assert_eq!(host.stats.rollbacks, 0);
+
+        host.receive(&joiner.outgoing());
+        assert_eq!(host.stats.rollbacks, 1);
+        assert_eq!(host.stats.resimulated_ticks, 6 - host.confirmed().tick);
+        let straight = straight_run([host.inputs(0), host.inputs(1)], host.state().tick);
+        assert_eq!(host.state().checksum(), straight.checksum());
+    }
+
+    #[test]
+    fn on_time_inputs_do_not_roll_back() {
+        let mut host = Session::new(0, SEED, GameConfig::default());
+        let mut joiner = Session::new(1, SEED, GameConfig::default());
+        for tick in 0..20 {
+            joiner.advance(tick % 5 == 0);
+            host.receive(&joiner.outgoing());
+            host.advance(false);
+        }
+        assert_eq!(host.stats.rollbacks, 0);
+    }
+
+    #[test]
+    fn packets_round_trip() {
+        let inputs: Vec<bool> = (0..13).map(|i| i % 3 == 0).collect();
+        let packets = [
+            Packet::Hello,
+            Packet::Welcome {
+                seed: u64::MAX,
+                config: GameConfig::default(),
+            },
+            Packet::Input(InputPacket {
+                seed: SEED,
+                from_tick: 40,
+                ack: 38,
+                inputs,
+            }),
+        ];
+        for packet in packets {
+            let bytes = packet.encode();
+            assert_eq!(Packet::decode(&bytes), Some(packet));
+            assert_eq!(Packet::decode(&bytes[..bytes.len() - 1]), None);
+        }
+    }
+
+    #[test]
+    fn welcome_with_an_unplayable_config_is_ignored() {
+        for pipe_spawn_interval in [f32::NAN, -2.0, 0.0, f32::MAX] {
+            let config = GameConfig {
+                pipe_spawn_interval,
+                ..GameConfig::default()
+            };
+            let bytes = Packet::Welcome { seed: SEED, config }.encode();
+            assert_eq!(Packet::decode(&bytes), None);
+        }
+    }
+
+    #[test]
+    fn packets_from_another_version_are_ignored() {
+        let mut bytes = Packet::Hello.encode();
+        bytes[MAGIC.len()] = PROTOCOL_VERSION + 1;
+        assert_eq!(Packet::decode(&bytes), None);
+    }
+}
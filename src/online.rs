@@ -0,0 +1,235 @@
+use bevy::prelude::*;
+use rlappy_bev::netplay::{Peer, Role, UdpLink};
+use rlappy_bev::sim::{self, Simulation};
+use std::time::Duration;
+
+use crate::{
+    pipe_sprite, start_game, Bird, Dead, GameDifficulty, GameMode, GameOverUi, GameState, MenuUi,
+    PendingJump, Pipe, Player, PlayerScore, PlayerScoreText, SeedConfig,
+};
+
+/// The opponent counts as gone after this long without a packet.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Online versus against one peer, started with `--host` or `--join`. While present, the
+/// run is simulated by the netplay session and the ECS only draws it.
+#[derive(Resource)]
+pub struct OnlineMatch {
+    peer: Peer<UdpLink>,
+    status: String,
+    connection_lost: bool,
+}
+
+impl OnlineMatch {
+    pub fn host(port: u16, seed: u64, config: sim::GameConfig) -> std::io::Result<Self> {
+        Ok(Self {
+            peer: Peer::new(UdpLink::host(port)?, Role::Host { seed, config }),
+            status: format!("Hosting on port {port}, waiting for an opponent"),
+            connection_lost: false,
+        })
+    }
+
+    pub fn join(addr: &str) -> std::io::Result<Self> {
+        Ok(Self {
+            peer: Peer::new(UdpLink::join(addr)?, Role::Join),
+            status: format!("Joining {addr}"),
+            connection_lost: false,
+        })
+    }
+}
+
+pub fn spawn_lobby(online: Res<OnlineMatch>, mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            online.status.clone(),
+            TextStyle {
+                font_size: 30.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            margin: UiRect::all(Val::Auto),
+            ..default()
+        }),
+        MenuUi,
+    ));
+}
+
+/// Waits in the menu for the handshake, then starts the match on both ends at once.
+pub fn online_lobby(
+    mut online: ResMut<OnlineMatch>,
+    mut mode: ResMut<GameMode>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    online.peer.poll();
+    if online.peer.session().is_some() {
+        online.connection_lost = false;
+        *mode = GameMode::Versus;
+        start_game(
+            &mut commands,
+            &asset_server,
+            &mut texture_atlas_layouts,
+            *mode,
+            &mut next_state,
+        );
+    }
+}
+
+/// Feeds the local jump into the session and sends our inputs; replaces the local gameplay
+/// systems for the whole fixed tick.
+pub fn online_tick(
+    mut online: ResMut<OnlineMatch>,
+    mut pending_jump: ResMut<PendingJump>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    online.peer.poll();
+    let Some(session) = online.peer.session_mut() else {
+        return;
+    };
+
+    // Whoever plays where, the local player flaps with Space
+    if session.advance(pending_jump.0[0]) {
+        *pending_jump = PendingJump::default();
+    }
+    let finished = session.is_finished();
+    online.peer.send_inputs();
+
+    if finished {
+        next_state.set(GameState::GameOver);
+    } else if online
+        .peer
+        .silence()
+        .is_some_and(|silence| silence > TIMEOUT)
+    {
+        online.connection_lost = true;
+        next_state.set(GameState::GameOver);
+    }
+}
+
+/// Keeps sending after the match so the opponent can confirm the result too.
+pub fn online_linger(mut online: ResMut<OnlineMatch>) {
+    online.peer.poll();
+    online.peer.send_inputs();
+}
+
+/// Mirrors the session's predicted state onto the bird, pipe and score entities.
+pub fn sync_online_view(
+    online: Res<OnlineMatch>,
+    mode: Res<GameMode>,
+    mut difficulty: ResMut<GameDifficulty>,
+    mut commands: Commands,
+    mut birds: Query<(
+        Entity,
+        &Player,
+        &mut Bird,
+        &mut Transform,
+        &mut PlayerScore,
+        &mut Sprite,
+        Has<Dead>,
+    )>,
+    mut pipes: Query<(Entity, &mut Transform, &mut Pipe), Without<Player>>,
+    mut texts: Query<(&mut Text, &PlayerScoreText)>,
+) {
+    let Some(session) = online.peer.session() else {
+        return;
+    };
+    let state = session.state();
+
+    for (entity, player, mut bird, mut transform, mut score, mut sprite, dead) in birds.iter_mut() {
+        let run = &state.players[player.0];
+        transform.translation.y = run.bird.y;
+        bird.velocity = run.bird.velocity;
+        score.0 = run.score * 2;
+
+        // A rollback can take a crash back as easily as it can cause one
+        if run.is_over() && !dead {
+            commands.entity(entity).insert(Dead {
+                at: sim::tick_duration() * run.tick,
+            });
+            sprite.color = Color::srgba(0.4, 0.4, 0.4, 0.6);
+        } else if !run.is_over() && dead {
+            commands.entity(entity).remove::<Dead>();
+            sprite.color = mode.tint(player.0);
+        }
+    }
+
+    for (mut text, owner) in texts.iter_mut() {
+        let you = if owner.0 == session.local() {
+            " (you)"
+        } else {
+            ""
+        };
+        let value = format!("P{}{you}: {}", owner.0 + 1, state.players[owner.0].score);
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+
+    // A crashed player's course stops moving, so draw the course of someone still flying
+    let course: &Simulation = state
+        .players
+        .iter()
+        .find(|run| !run.is_over())
+        .unwrap_or(&state.players[session.local()]);
+    difficulty.speed_multiplier = course.speed_multiplier;
+    let wanted: Vec<(f32, f32, f32)> = course
+        .pipes
+        .iter()
+        .flat_map(|pipe| {
+            course
+                .config()
+                .pipe_centres(pipe.gap_y)
+                .map(|pipe_y| (pipe.x, pipe_y, pipe.gap_y))
+        })
+        .collect();
+
+    let mut existing = pipes.iter_mut();
+    for &(x, y, gap_y) in &wanted {
+        match existing.next() {
+            Some((_, mut transform, mut pipe)) => {
+                transform.translation.x = x;
+                transform.translation.y = y;
+                pipe.gap_y = gap_y;
+            }
+            None => {
+                commands.spawn((pipe_sprite(x, y), Pipe { gap_y }));
+            }
+        }
+    }
+    for (entity, ..) in existing {
+        commands.entity(entity).despawn();
+    }
+}
+
+pub fn spawn_connection_lost(online: Res<OnlineMatch>, mut commands: Commands) {
+    if !online.connection_lost {
+        return;
+    }
+    commands.spawn((
+        TextBundle::from_section(
+            "Connection lost",
+            TextStyle {
+                font_size: 30.0,
+                color: Color::srgb(1.0, 0.5, 0.5),
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(100.0),
+            left: Val::Px(300.0),
+            ..default()
+        }),
+        GameOverUi,
+    ));
+}
+
+/// Ends the finished match; the host offers a new course for the next one.
+pub fn rematch(mut online: ResMut<OnlineMatch>, seed_config: Res<SeedConfig>) {
+    let seed = seed_config.0.unwrap_or_else(rand::random);
+    online.peer.rematch(seed);
+}
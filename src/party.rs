@@ -0,0 +1,344 @@
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::prelude::*;
+
+use crate::input::{Action, Actions};
+use crate::{start_game, GameMode, GameOverUi, GameState, MatchSeed, MenuUi, Score, SeedConfig};
+
+pub const MIN_PLAYERS: usize = 2;
+pub const MAX_PLAYERS: usize = 8;
+const MAX_NAME: usize = 16;
+
+/// Hot-seat party in progress: everyone flies the same seed once, in order.
+#[derive(Resource)]
+pub struct Party {
+    names: Vec<String>,
+    seed: u64,
+    /// Scores of the players who have had their turn, in turn order.
+    scores: Vec<u32>,
+}
+
+impl Party {
+    fn current(&self) -> Option<&str> {
+        self.names.get(self.scores.len()).map(String::as_str)
+    }
+
+    /// Names and scores, best first; equal scores share a place.
+    fn ranking(&self) -> Vec<(usize, &str, u32)> {
+        let mut order: Vec<_> = self.names.iter().zip(&self.scores).collect();
+        order.sort_by_key(|&(_, score)| std::cmp::Reverse(*score));
+        let mut ranking: Vec<(usize, &str, u32)> = Vec::new();
+        for (i, (name, &score)) in order.into_iter().enumerate() {
+            let place = match ranking.last() {
+                Some(&(place, _, previous)) if previous == score => place,
+                _ => i + 1,
+            };
+            ranking.push((place, name, score));
+        }
+        ranking
+    }
+}
+
+/// Name entry in the menu, open after clicking PARTY or pressing Y.
+#[derive(Resource, Default)]
+pub struct PartySetup {
+    names: Vec<String>,
+    typing: String,
+}
+
+#[derive(Component)]
+pub struct PartySetupText;
+
+/// "Pass to" screen and final ranking, removed when leaving `GameState::Handoff`.
+#[derive(Component)]
+pub struct HandoffUi;
+
+fn text_bundle(value: String, top: f32, left: f32) -> TextBundle {
+    TextBundle::from_section(
+        value,
+        TextStyle {
+            font_size: 24.0,
+            color: Color::WHITE,
+            ..default()
+        },
+    )
+    .with_style(Style {
+        position_type: PositionType::Absolute,
+        top: Val::Px(top),
+        left: Val::Px(left),
+        ..default()
+    })
+}
+
+pub fn spawn_party_setup(mut commands: Commands) {
+    commands.spawn((
+        text_bundle(String::new(), 40.0, 40.0),
+        PartySetupText,
+        MenuUi,
+    ));
+}
+
+/// Typing a name, Enter to add it (or to start on an empty name), Backspace and Esc.
+pub fn party_setup_input(
+    mut setup: ResMut<PartySetup>,
+    mut typed: EventReader<KeyboardInput>,
+    seed_config: Res<SeedConfig>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut commands: Commands,
+    texts: Query<Entity, With<PartySetupText>>,
+) {
+    // The key that opened the setup is still in the event queue
+    if setup.is_added() {
+        typed.clear();
+        return;
+    }
+    let mut close = false;
+    for event in typed.read() {
+        if !event.state.is_pressed() {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Character(text) => {
+                for c in text.chars().filter(|c| !c.is_control()) {
+                    if setup.typing.chars().count() < MAX_NAME {
+                        setup.typing.push(c);
+                    }
+                }
+            }
+            Key::Space if !setup.typing.is_empty() && setup.typing.chars().count() < MAX_NAME => {
+                setup.typing.push(' ');
+            }
+            // Backspace on an empty name takes back the last player
+            Key::Backspace if setup.typing.is_empty() => {
+                setup.names.pop();
+            }
+            Key::Backspace => {
+                setup.typing.pop();
+            }
+            Key::Enter => {
+                let name = setup.typing.trim().to_string();
+                setup.typing.clear();
+                if name.is_empty() {
+                    if setup.names.len() >= MIN_PLAYERS {
+                        commands.insert_resource(Party {
+                            names: std::mem::take(&mut setup.names),
+                            seed: seed_config.0.unwrap_or_else(rand::random),
+                            scores: Vec::new(),
+                        });
+                        next_state.set(GameState::Handoff);
+                        close = true;
+                    }
+                } else if setup.names.len() < MAX_PLAYERS && !setup.names.contains(&name) {
+                    setup.names.push(name);
+                }
+            }
+            Key::Escape => close = true,
+            _ => {}
+        }
+        if close {
+            break;
+        }
+    }
+
+    if close {
+        commands.remove_resource::<PartySetup>();
+        for entity in texts.iter() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+pub fn update_party_setup(
+    setup: Res<PartySetup>,
+    mut texts: Query<&mut Text, With<PartySetupText>>,
+) {
+    let mut value = String::from("PARTY\n\n");
+    for (i, name) in setup.names.iter().enumerate() {
+        value.push_str(&format!("PLAYER {}: {name}\n", i + 1));
+    }
+    if setup.names.len() < MAX_PLAYERS {
+        value.push_str(&format!(
+            "PLAYER {}: {}_\n",
+            setup.names.len() + 1,
+            setup.typing
+        ));
+    }
+    value.push_str("\nEnter: add player");
+    if setup.names.len() >= MIN_PLAYERS {
+        value.push_str("\nEnter on an empty name: start");
+    } else {
+        value.push_str(&format!("\n{MIN_PLAYERS} to {MAX_PLAYERS} players"));
+    }
+    value.push_str("\nEsc: close");
+    for mut text in texts.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+/// Shows who plays next, or the final ranking once everyone has had a turn.
+pub fn spawn_handoff(mut commands: Commands, party: Res<Party>) {
+    let value = match party.current() {
+        Some(name) => format!(
+            "Pass to PLAYER {}\n{name}\n\nSpace: start your turn\nEsc: end the party",
+            party.scores.len() + 1
+        ),
+        None => {
+            let mut value = String::from("FINAL RANKING\n\n");
+            for (place, name, score) in party.ranking() {
+                value.push_str(&format!("{place}. {name}  {score}\n"));
+            }
+            value.push_str("\nEnter: back to menu");
+            value
+        }
+    };
+    commands.spawn((text_bundle(value, 120.0, 250.0), HandoffUi));
+}
+
+pub fn handoff_input(
+    actions: Res<Actions>,
+    party: Res<Party>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut mode: ResMut<GameMode>,
+) {
+    if party.current().is_none() {
+        if actions.just_pressed(Action::Confirm) || actions.just_pressed(Action::Back) {
+            next_state.set(GameState::Menu);
+        }
+    } else if actions.just_pressed(Action::Back) {
+        next_state.set(GameState::Menu);
+    } else if actions.just_pressed(Action::Confirm) {
+        commands.insert_resource(MatchSeed(party.seed));
+        *mode = GameMode::Solo;
+        start_game(
+            &mut commands,
+            &asset_server,
+            &mut texture_atlas_layouts,
+            GameMode::Solo,
+            &mut next_state,
+        );
+    }
+}
+
+/// Writes down the finished turn and says who is next.
+pub fn record_turn(mut commands: Commands, mut party: ResMut<Party>, score: Res<Score>) {
+    let Some(name) = party.current().map(str::to_string) else {
+        return;
+    };
+    party.scores.push(score.0 / 2);
+    let next = match party.current() {
+        Some(next) => format!("Next up: {next}"),
+        None => "Everyone has played".to_string(),
+    };
+    commands.spawn((
+        text_bundle(
+            format!("{name} scored {}. {next}\nR: continue", score.0 / 2),
+            90.0,
+            250.0,
+        ),
+        GameOverUi,
+    ));
+}
+
+/// Leaving for the menu by any route ends the party.
+pub fn end_party(mut commands: Commands) {
+    commands.remove_resource::<Party>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::input::ButtonState;
+    use bevy::state::app::StatesPlugin;
+
+    fn party(names: &[&str], scores: &[u32]) -> Party {
+        Party {
+            names: names.iter().map(|name| name.to_string()).collect(),
+            seed: 1,
+            scores: scores.to_vec(),
+        }
+    }
+
+    #[test]
+    fn ranking_puts_the_best_first_and_shares_places() {
+        let party = party(&["ada", "bo", "cy", "di"], &[4, 9, 4, 1]);
+        assert_eq!(
+            party.ranking(),
+            [(1, "bo", 9), (2, "ada", 4), (2, "cy", 4), (4, "di", 1)]
+        );
+    }
+
+    #[test]
+    fn turns_go_round_in_order() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(party(&["ada", "bo"], &[]))
+            .insert_resource(Score(14));
+        assert_eq!(app.world().resource::<Party>().current(), Some("ada"));
+
+        app.world_mut().run_system_once(record_turn);
+        assert_eq!(app.world().resource::<Party>().current(), Some("bo"));
+        app.world_mut().resource_mut::<Score>().0 = 6;
+        app.world_mut().run_system_once(record_turn);
+        // Score counts both pipes of a gap
+        assert_eq!(app.world().resource::<Party>().scores, [7, 3]);
+        assert_eq!(app.world().resource::<Party>().current(), None);
+
+        // Nobody is left to record a turn for
+        app.world_mut().run_system_once(record_turn);
+        assert_eq!(app.world().resource::<Party>().scores, [7, 3]);
+    }
+
+    fn press(app: &mut App, key: Key) {
+        app.world_mut().send_event(KeyboardInput {
+            key_code: KeyCode::Unidentified(bevy::input::keyboard::NativeKeyCode::Unidentified),
+            logical_key: key,
+            state: ButtonState::Pressed,
+            window: Entity::PLACEHOLDER,
+        });
+        app.update();
+    }
+
+    fn type_name(app: &mut App, name: &str) {
+        press(app, Key::Character(name.into()));
+        press(app, Key::Enter);
+    }
+
+    #[test]
+    fn setup_needs_two_different_names() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin))
+            .init_state::<GameState>()
+            .add_event::<KeyboardInput>()
+            .insert_resource(SeedConfig(Some(5)))
+            .init_resource::<PartySetup>()
+            .add_systems(
+                Update,
+                party_setup_input.run_if(resource_exists::<PartySetup>),
+            );
+        app.update();
+
+        type_name(&mut app, "ada");
+        // Repeated names and starting alone are refused
+        type_name(&mut app, "ada");
+        press(&mut app, Key::Enter);
+        assert!(!app.world().contains_resource::<Party>());
+        assert_eq!(app.world().resource::<PartySetup>().names, ["ada"]);
+
+        type_name(&mut app, "bo");
+        press(&mut app, Key::Enter);
+        let party = app.world().resource::<Party>();
+        assert_eq!(party.names, ["ada", "bo"]);
+        assert_eq!(party.seed, 5);
+        assert!(!app.world().contains_resource::<PartySetup>());
+        app.update();
+        assert_eq!(
+            *app.world().resource::<State<GameState>>().get(),
+            GameState::Handoff
+        );
+    }
+}
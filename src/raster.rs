@@ -0,0 +1,288 @@
+//! CPU rasteriser for pixel-based agents.
+//!
+//! Draws the bird, pipes and ground of a [`Simulation`] into a small byte buffer using
+//! the same world layout as the 800x600 window, so no GPU or `bevy_render` is needed.
+
+use std::collections::VecDeque;
+
+use crate::sim::{
+    Simulation, BIRD_SIZE, BIRD_X, GROUND_HEIGHT, GROUND_THICKNESS, GROUND_WIDTH, PIPE_HEIGHT,
+    PIPE_WIDTH, WINDOW_HEIGHT, WINDOW_WIDTH,
+};
+
+type Rgb = [u8; 3];
+
+// Colours match the sprites spawned by the windowed game
+const BACKGROUND: Rgb = [43, 44, 47]; // Bevy's default ClearColor
+const PIPE_COLOR: Rgb = [0, 204, 0];
+const GROUND_COLOR: Rgb = [77, 204, 77];
+const BIRD_COLOR: Rgb = [240, 200, 40];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// One luma byte per pixel.
+    Grayscale,
+    /// Three bytes per pixel, row-major.
+    Rgb,
+}
+
+impl PixelFormat {
+    pub fn channels(self) -> usize {
+        match self {
+            PixelFormat::Grayscale => 1,
+            PixelFormat::Rgb => 3,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RasterConfig {
+    pub width: usize,
+    pub height: usize,
+    pub format: PixelFormat,
+}
+
+impl Default for RasterConfig {
+    fn default() -> Self {
+        Self {
+            width: 84,
+            height: 84,
+            format: PixelFormat::Grayscale,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    pub format: PixelFormat,
+    /// Row-major pixels, top row first.
+    pub data: Vec<u8>,
+}
+
+impl Frame {
+    /// Black frame sized for `config`, to render into.
+    pub fn new(config: &RasterConfig) -> Self {
+        Self {
+            width: config.width,
+            height: config.height,
+            format: config.format,
+            data: vec![0; config.width * config.height * config.format.channels()],
+        }
+    }
+
+    /// Value of the pixel at `column`, `row`.
+    pub fn pixel(&self, column: usize, row: usize) -> &[u8] {
+        let channels = self.format.channels();
+        let start = (row * self.width + column) * channels;
+        &self.data[start..start + channels]
+    }
+
+    fn fill_pixels(
+        &mut self,
+        columns: std::ops::Range<usize>,
+        rows: std::ops::Range<usize>,
+        color: Rgb,
+    ) {
+        let channels = self.format.channels();
+        let gray = [luma(color)];
+        let pixel: &[u8] = match self.format {
+            PixelFormat::Grayscale => &gray,
+            PixelFormat::Rgb => &color,
+        };
+        for row in rows {
+            let start = (row * self.width + columns.start) * channels;
+            let end = (row * self.width + columns.end) * channels;
+            for chunk in self.data[start..end].chunks_exact_mut(channels) {
+                chunk.copy_from_slice(pixel);
+            }
+        }
+    }
+
+    /// Fills every pixel whose centre lies inside the world-space rectangle.
+    fn fill_rect(&mut self, center_x: f32, center_y: f32, width: f32, height: f32, color: Rgb) {
+        let scale_x = self.width as f32 / WINDOW_WIDTH;
+        let scale_y = self.height as f32 / WINDOW_HEIGHT;
+
+        let left = (center_x - width / 2.0 + WINDOW_WIDTH / 2.0) * scale_x;
+        let right = (center_x + width / 2.0 + WINDOW_WIDTH / 2.0) * scale_x;
+        // Screen rows grow downwards while world y grows upwards
+        let top = (WINDOW_HEIGHT / 2.0 - center_y - height / 2.0) * scale_y;
+        let bottom = (WINDOW_HEIGHT / 2.0 - center_y + height / 2.0) * scale_y;
+
+        let columns = pixel_span(left, right, self.width);
+        let rows = pixel_span(top, bottom, self.height);
+        if !columns.is_empty() && !rows.is_empty() {
+            self.fill_pixels(columns, rows, color);
+        }
+    }
+}
+
+/// Pixels whose centres fall in `[start, end)`, clamped to the buffer.
+fn pixel_span(start: f32, end: f32, len: usize) -> std::ops::Range<usize> {
+    let first = (start - 0.5).ceil().clamp(0.0, len as f32) as usize;
+    let last = (end - 0.5).ceil().clamp(0.0, len as f32) as usize;
+    first..last.max(first)
+}
+
+fn luma([r, g, b]: Rgb) -> u8 {
+    (0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32).round() as u8
+}
+
+/// Renders the current state of `sim` into a new frame.
+pub fn render(sim: &Simulation, config: &RasterConfig) -> Frame {
+    let mut frame = Frame::new(config);
+    render_into(sim, &mut frame);
+    frame
+}
+
+/// Renders the current state of `sim` over `frame`, keeping its size, format and buffer.
+pub fn render_into(sim: &Simulation, frame: &mut Frame) {
+    frame.fill_pixels(0..frame.width, 0..frame.height, BACKGROUND);
+
+    for pipe in &sim.pipes {
+        for y in sim.config().pipe_centres(pipe.gap_y) {
+            frame.fill_rect(pipe.x, y, PIPE_WIDTH, PIPE_HEIGHT, PIPE_COLOR);
+        }
+    }
+    frame.fill_rect(
+        0.0,
+        GROUND_HEIGHT,
+        GROUND_WIDTH,
+        GROUND_THICKNESS,
+        GROUND_COLOR,
+    );
+    frame.fill_rect(BIRD_X, sim.bird.y, BIRD_SIZE, BIRD_SIZE, BIRD_COLOR);
+}
+
+/// Keeps the last `depth` frames so agents can infer motion from a single observation.
+#[derive(Clone, Debug)]
+pub struct FrameStack {
+    depth: usize,
+    frames: VecDeque<Frame>,
+}
+
+impl FrameStack {
+    pub fn new(depth: usize) -> Self {
+        Self {
+            depth: depth.max(1),
+            frames: VecDeque::with_capacity(depth.max(1)),
+        }
+    }
+
+    /// Starts a new episode by filling the whole stack with `frame`.
+    pub fn reset(&mut self, frame: Frame) {
+        self.frames.clear();
+        self.frames.resize(self.depth, frame);
+    }
+
+    pub fn push(&mut self, frame: Frame) {
+        if self.frames.is_empty() {
+            self.reset(frame);
+            return;
+        }
+        if self.frames.len() == self.depth {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+    }
+
+    /// Concatenated frame bytes, oldest first.
+    pub fn stacked(&self) -> Vec<u8> {
+        self.frames
+            .iter()
+            .flat_map(|frame| frame.data.iter().copied())
+            .collect()
+    }
+
+    pub fn frames(&self) -> impl Iterator<Item = &Frame> {
+        self.frames.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{BirdState, PipePair};
+
+    /// Bird at the origin height and one gap centred at y 0, 200 units right of centre.
+    fn scene() -> Simulation {
+        let mut sim = Simulation::new(0);
+        sim.bird = BirdState::START;
+        sim.pipes = vec![PipePair {
+            x: 200.0,
+            gap_y: 0.0,
+        }];
+        sim
+    }
+
+    #[test]
+    fn draws_bird_pipes_and_ground_where_the_window_does() {
+        let frame = render(&scene(), &RasterConfig::default());
+        assert_eq!(
+            (frame.width, frame.height, frame.data.len()),
+            (84, 84, 84 * 84)
+        );
+
+        // 84 / 800 pixels per unit: the bird covers columns 30..33 and rows 40..44
+        assert_eq!(frame.pixel(31, 42), [luma(BIRD_COLOR)]);
+        assert_eq!(frame.pixel(29, 42), [luma(BACKGROUND)]);
+        // The pipe's columns are 60..66; its gap leaves rows 28..56 open
+        assert_eq!(frame.pixel(63, 10), [luma(PIPE_COLOR)]);
+        assert_eq!(frame.pixel(63, 42), [luma(BACKGROUND)]);
+        assert_eq!(frame.pixel(63, 70), [luma(PIPE_COLOR)]);
+        assert_eq!(frame.pixel(57, 10), [luma(BACKGROUND)]);
+        // Ground rows 73..80 span the whole width
+        assert_eq!(frame.pixel(0, 77), [luma(GROUND_COLOR)]);
+        assert_eq!(frame.pixel(83, 77), [luma(GROUND_COLOR)]);
+        assert_eq!(frame.pixel(10, 82), [luma(BACKGROUND)]);
+    }
+
+    #[test]
+    fn rgb_frames_keep_the_sprite_colours() {
+        let config = RasterConfig {
+            format: PixelFormat::Rgb,
+            ..RasterConfig::default()
+        };
+        let frame = render(&scene(), &config);
+        assert_eq!(frame.data.len(), 84 * 84 * 3);
+        assert_eq!(frame.pixel(31, 42), BIRD_COLOR);
+        assert_eq!(frame.pixel(63, 10), PIPE_COLOR);
+        assert_eq!(frame.pixel(0, 77), GROUND_COLOR);
+    }
+
+    #[test]
+    fn render_into_redraws_the_whole_frame() {
+        let mut sim = scene();
+        let mut frame = render(&sim, &RasterConfig::default());
+        sim.bird.y = 200.0;
+        sim.pipes.clear();
+        render_into(&sim, &mut frame);
+        assert_eq!(frame, render(&sim, &RasterConfig::default()));
+        assert_eq!(frame.pixel(31, 42), [luma(BACKGROUND)]);
+    }
+
+    #[test]
+    fn frame_stack_is_oldest_to_newest() {
+        let config = RasterConfig {
+            width: 1,
+            height: 1,
+            format: PixelFormat::Grayscale,
+        };
+        let frame = |value| Frame {
+            data: vec![value],
+            ..Frame::new(&config)
+        };
+        let mut stack = FrameStack::new(3);
+        stack.push(frame(1));
+        assert_eq!(stack.stacked(), [1, 1, 1]);
+        stack.push(frame(2));
+        stack.push(frame(3));
+        assert_eq!(stack.stacked(), [1, 2, 3]);
+        stack.push(frame(4));
+        assert_eq!(stack.stacked(), [2, 3, 4]);
+        let newest: Vec<u8> = stack.frames().map(|frame| frame.data[0]).collect();
+        assert_eq!(newest, [2, 3, 4]);
+    }
+}
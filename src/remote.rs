@@ -0,0 +1,157 @@
+use bevy::prelude::*;
+use rlappy_bev::rpc::{
+    self, Command, RemoteMode, RpcError, RpcServer, INVALID_PARAMS, NOT_ALLOWED,
+};
+use serde_json::json;
+
+use crate::online::OnlineMatch;
+use crate::spectate::Scene;
+use crate::{
+    pause_game, start_game, GameDifficulty, GameMode, GameSeed, GameState, MatchSeed, PendingJump,
+};
+
+/// Remote control started with `--remote stdio` or `--remote <port>`.
+#[derive(Resource)]
+pub struct RemoteControl {
+    server: RpcServer,
+    /// A run asked for with `start` or `restart`, begun once the game is back in the menu.
+    queued: Option<QueuedRun>,
+}
+
+#[derive(Clone, Copy)]
+struct QueuedRun {
+    seed: Option<u64>,
+    mode: GameMode,
+}
+
+impl RemoteControl {
+    pub fn new(server: RpcServer) -> Self {
+        Self {
+            server,
+            queued: None,
+        }
+    }
+}
+
+impl From<RemoteMode> for GameMode {
+    fn from(mode: RemoteMode) -> Self {
+        match mode {
+            RemoteMode::Solo => GameMode::Solo,
+            RemoteMode::Versus => GameMode::Versus,
+            RemoteMode::SplitScreen => GameMode::SplitScreen,
+            RemoteMode::Coop => GameMode::Coop,
+        }
+    }
+}
+
+/// Answers requests, then walks a queued run through the menu into play. A run still in
+/// progress is abandoned without a game over, so it isn't scored, saved or submitted.
+pub fn remote_control(
+    mut remote: ResMut<RemoteControl>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut pending_jump: ResMut<PendingJump>,
+    mut mode: ResMut<GameMode>,
+    seed: Res<GameSeed>,
+    difficulty: Res<GameDifficulty>,
+    online: Option<Res<OnlineMatch>>,
+    scene: Scene,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    let online = online.is_some();
+    for request in remote.server.poll() {
+        let result = request.command.and_then(|command| match command {
+            Command::Jump { player } => {
+                // Online, the local bird is always player 0 whichever side it flies
+                let players = if online { 1 } else { mode.players() };
+                if player >= players {
+                    Err(RpcError::new(INVALID_PARAMS, format!("No player {player}")))
+                } else if *state.get() != GameState::Playing {
+                    Err(not_allowed(&state))
+                } else {
+                    pending_jump.0[player] = true;
+                    Ok(json!(true))
+                }
+            }
+            Command::Pause if *state.get() == GameState::Playing && !online => {
+                pause_game(&mut commands, &mut next_state);
+                Ok(json!(true))
+            }
+            Command::Resume if *state.get() == GameState::Paused => {
+                next_state.set(GameState::Playing);
+                Ok(json!(true))
+            }
+            Command::Restart | Command::Start { .. }
+                if online
+                    || matches!(
+                        state.get(),
+                        GameState::Lobby
+                            | GameState::Handoff
+                            | GameState::Controls
+                            | GameState::Settings
+                    ) =>
+            {
+                Err(not_allowed(&state))
+            }
+            Command::Restart => {
+                remote.queued = Some(QueuedRun {
+                    seed: Some(seed.0),
+                    mode: *mode,
+                });
+                Ok(json!({ "seed": seed.0 }))
+            }
+            Command::Start { seed, mode: chosen } => {
+                remote.queued = Some(QueuedRun {
+                    seed,
+                    mode: chosen.map_or(*mode, GameMode::from),
+                });
+                Ok(json!(true))
+            }
+            Command::GetState => Ok(json!({
+                "state": format!("{:?}", state.get()),
+                "mode": format!("{:?}", *mode),
+                "seed": seed.0,
+                "speed_multiplier": difficulty.speed_multiplier,
+                "birds": scene.birds(),
+                "pipes": scene.pipes(),
+            })),
+            Command::Pause | Command::Resume => Err(not_allowed(&state)),
+        });
+        if let Some(id) = request.id {
+            let line = rpc::response(&id, result);
+            remote.server.reply(request.client, &line);
+        }
+    }
+
+    let Some(run) = remote.queued else {
+        return;
+    };
+    match state.get() {
+        GameState::Menu => {
+            remote.queued = None;
+            if let Some(seed) = run.seed {
+                commands.insert_resource(MatchSeed(seed));
+            }
+            *mode = run.mode;
+            start_game(
+                &mut commands,
+                &asset_server,
+                &mut texture_atlas_layouts,
+                run.mode,
+                &mut next_state,
+            );
+        }
+        GameState::GameOver | GameState::Playing | GameState::Paused => {
+            next_state.set(GameState::Menu)
+        }
+        GameState::Lobby | GameState::Handoff | GameState::Controls | GameState::Settings => {
+            remote.queued = None
+        }
+    }
+}
+
+fn not_allowed(state: &State<GameState>) -> RpcError {
+    RpcError::new(NOT_ALLOWED, format!("Not allowed in {:?}", state.get()))
+}
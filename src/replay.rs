@@ -0,0 +1,151 @@
+//! Replay files: a seed, a config and the ticks on which the bird jumped.
+//!
+//! Replays are plain JSON so they can be diffed and edited by hand. Playing one back
+//! through [`Simulation`] reproduces the run exactly.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::sim::{GameConfig, Simulation};
+
+pub const REPLAY_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    pub seed: u64,
+    #[serde(default)]
+    pub config: GameConfig,
+    /// Total ticks simulated.
+    pub ticks: u32,
+    /// Ticks on which a jump was applied, in increasing order.
+    pub jumps: Vec<u32>,
+    /// Score recorded alongside the inputs, if any.
+    #[serde(default)]
+    pub final_score: Option<u32>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, config: GameConfig) -> Self {
+        Self {
+            version: REPLAY_VERSION,
+            seed,
+            config,
+            ticks: 0,
+            jumps: Vec::new(),
+            final_score: None,
+        }
+    }
+
+    /// Builds a replay from one jump flag per tick.
+    pub fn from_inputs(seed: u64, config: GameConfig, inputs: &[bool]) -> Self {
+        let mut replay = Self::new(seed, config);
+        for &jump in inputs {
+            replay.push(jump);
+        }
+        replay
+    }
+
+    /// Appends the input for the next tick.
+    pub fn push(&mut self, jump: bool) {
+        if jump {
+            self.jumps.push(self.ticks);
+        }
+        self.ticks += 1;
+    }
+
+    /// One jump flag per tick.
+    pub fn inputs(&self) -> Vec<bool> {
+        let mut inputs = vec![false; self.ticks as usize];
+        for &tick in &self.jumps {
+            if let Some(input) = inputs.get_mut(tick as usize) {
+                *input = true;
+            }
+        }
+        inputs
+    }
+
+    /// Plays the inputs back, stopping early if the bird dies.
+    pub fn simulate(&self) -> Simulation {
+        let mut sim = Simulation::with_config(self.seed, self.config);
+        for jump in self.inputs() {
+            if sim.is_over() {
+                break;
+            }
+            sim.step(jump);
+        }
+        sim
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        serde_json::from_reader(std::io::BufReader::new(file)).map_err(std::io::Error::other)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let json = serde_json::to_string(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, json + "\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED: u64 = 11;
+
+    /// A flap every 40 ticks, which holds the bird near the middle for a while.
+    fn inputs(ticks: usize) -> Vec<bool> {
+        (0..ticks).map(|tick| tick % 40 == 0).collect()
+    }
+
+    #[test]
+    fn inputs_round_trip_through_jump_ticks() {
+        let inputs = inputs(130);
+        let replay = Replay::from_inputs(SEED, GameConfig::default(), &inputs);
+        assert_eq!(replay.ticks, 130);
+        assert_eq!(replay.jumps, [0, 40, 80, 120]);
+        assert_eq!(replay.inputs(), inputs);
+    }
+
+    #[test]
+    fn playback_matches_the_original_run() {
+        let mut sim = Simulation::new(SEED);
+        let mut replay = Replay::new(SEED, GameConfig::default());
+        for jump in inputs(300) {
+            sim.step(jump);
+            replay.push(jump);
+        }
+        let played = replay.simulate();
+        assert_eq!(played.tick, sim.tick);
+        assert_eq!(played.bird, sim.bird);
+        assert_eq!(played.score, sim.score);
+    }
+
+    #[test]
+    fn playback_stops_when_the_bird_dies() {
+        // Never flapping hits the ground well before the inputs run out
+        let replay = Replay::from_inputs(SEED, GameConfig::default(), &[false; 600]);
+        let sim = replay.simulate();
+        assert!(sim.is_over());
+        assert!(sim.tick < 600);
+    }
+
+    #[test]
+    fn files_round_trip_and_old_files_still_load() {
+        let mut replay = Replay::from_inputs(SEED, GameConfig::default(), &inputs(90));
+        replay.final_score = Some(0);
+        let path = std::env::temp_dir().join(format!("rlappy-replay-{}.json", std::process::id()));
+        replay.save(&path).unwrap();
+        let loaded = Replay::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), replay);
+
+        // Replays from before configs and scores were recorded
+        let old: Replay =
+            serde_json::from_str(r#"{"version":1,"seed":11,"ticks":3,"jumps":[1]}"#).unwrap();
+        assert_eq!(old.config, GameConfig::default());
+        assert_eq!(old.final_score, None);
+        assert_eq!(old.inputs(), [false, true, false]);
+    }
+}
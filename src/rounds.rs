@@ -0,0 +1,180 @@
+use bevy::prelude::*;
+use rlappy_bev::sim::GameConfig;
+use rlappy_bev::tournament::{Results, Tournament};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{ActiveConfig, GameOverUi, MatchSeed, MenuUi, Score};
+
+/// Tournament loaded with `--tournament`, with this player's results so far.
+#[derive(Resource)]
+pub struct TournamentRun {
+    tournament: Tournament,
+    results: Results,
+    /// Where the results are saved after every attempt.
+    path: PathBuf,
+}
+
+impl TournamentRun {
+    /// Loads the definition and resumes from `<definition>-<player>.json` in the working
+    /// directory if an earlier session left one.
+    pub fn open(definition: &str, player: &str) -> io::Result<Self> {
+        let tournament = Tournament::load(definition)?;
+        let stem = Path::new(definition)
+            .file_stem()
+            .map_or("tournament".into(), |stem| stem.to_string_lossy());
+        let safe_player: String = player
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let path = PathBuf::from(format!("{stem}-{safe_player}.json"));
+        let results = match Results::load(&path) {
+            Ok(results) if results.belongs_to(&tournament) => results,
+            Ok(_) => {
+                return Err(io::Error::other(format!(
+                    "{} holds results for another tournament",
+                    path.display()
+                )))
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Results::new(&tournament, player),
+            Err(err) => return Err(err),
+        };
+        Ok(Self {
+            tournament,
+            results,
+            path,
+        })
+    }
+
+    fn status(&self) -> String {
+        let points = self.results.points(&self.tournament);
+        match self.results.next_attempt(&self.tournament) {
+            Some((round, attempt)) => format!(
+                "Tournament: {}\nRound {}/{} (seed {}), attempt {}/{}\nPoints: {points}  T: play",
+                self.tournament.name,
+                round + 1,
+                self.tournament.seeds.len(),
+                self.tournament.seeds[round],
+                attempt + 1,
+                self.tournament.attempts,
+            ),
+            None => format!(
+                "Tournament: {} complete\nPoints: {points}\nResults saved to {}",
+                self.tournament.name,
+                self.path.display()
+            ),
+        }
+    }
+}
+
+/// Round being played, with the config to go back to afterwards.
+#[derive(Resource)]
+pub struct TournamentAttempt {
+    round: usize,
+    previous_config: GameConfig,
+}
+
+/// Queues the next attempt's seed and config; false once every attempt has been played.
+pub fn start_round(
+    commands: &mut Commands,
+    run: Option<&TournamentRun>,
+    config: &mut ActiveConfig,
+) -> bool {
+    let Some(run) = run else {
+        return false;
+    };
+    let Some((round, _)) = run.results.next_attempt(&run.tournament) else {
+        return false;
+    };
+    commands.insert_resource(MatchSeed(run.tournament.seeds[round]));
+    commands.insert_resource(TournamentAttempt {
+        round,
+        previous_config: config.0,
+    });
+    config.0 = run.tournament.config;
+    true
+}
+
+pub fn spawn_tournament_status(mut commands: Commands, run: Res<TournamentRun>) {
+    commands.spawn((
+        TextBundle::from_section(
+            run.status(),
+            TextStyle {
+                font_size: 20.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        }),
+        MenuUi,
+    ));
+}
+
+/// Adds the finished attempt to the results file and shows where the player stands.
+pub fn record_attempt(
+    mut commands: Commands,
+    mut run: ResMut<TournamentRun>,
+    attempt: Res<TournamentAttempt>,
+    score: Res<Score>,
+) {
+    let run = &mut *run;
+    let score = score.0 / 2;
+    run.results.record(attempt.round, score);
+    if let Err(err) = run.results.save(&run.path) {
+        eprintln!(
+            "Failed to save tournament results to {}: {err}",
+            run.path.display()
+        );
+    }
+
+    let attempts = &run.results.scores[attempt.round];
+    let mut message = format!(
+        "Round {} attempt {}: {score}, points {}",
+        attempt.round + 1,
+        attempts.len(),
+        run.results.points(&run.tournament)
+    );
+    if run.results.next_attempt(&run.tournament).is_none() {
+        message.push_str("\nTournament complete!");
+    }
+    commands.spawn((
+        TextBundle::from_section(
+            message,
+            TextStyle {
+                font_size: 20.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(90.0),
+            left: Val::Px(250.0),
+            ..default()
+        }),
+        GameOverUi,
+    ));
+}
+
+/// Puts the config back once a tournament attempt is over.
+pub fn end_attempt(
+    mut commands: Commands,
+    attempt: Option<Res<TournamentAttempt>>,
+    mut config: ResMut<ActiveConfig>,
+) {
+    if let Some(attempt) = attempt {
+        config.0 = attempt.previous_config;
+        commands.remove_resource::<TournamentAttempt>();
+    }
+}
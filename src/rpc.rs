@@ -0,0 +1,294 @@
+//! Remote control: JSON-RPC 2.0 over stdin/stdout or a local TCP socket, one message per
+//! line, so another process can play the windowed game or script it.
+//!
+//! Methods:
+//! - `jump` (`{"player": 0}` optional): flap on the next fixed tick
+//! - `pause`, `resume`
+//! - `restart`: start a new run on the same seed as the last one
+//! - `start` (`{"seed": 7, "mode": "versus"}` both optional): start a new run
+//! - `get_state`: the current state, seed, scores, birds and pipes
+//!
+//! Requests without an `id` are notifications and get no response.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::{self, BufRead, ErrorKind, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Mutex;
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+/// The method exists but cannot be applied in the current game state.
+pub const NOT_ALLOWED: i64 = -32000;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Game modes a remote `start` can pick, mirroring the menu.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RemoteMode {
+    Solo,
+    Versus,
+    SplitScreen,
+    Coop,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Jump {
+        player: usize,
+    },
+    Pause,
+    Resume,
+    Restart,
+    Start {
+        seed: Option<u64>,
+        mode: Option<RemoteMode>,
+    },
+    GetState,
+}
+
+impl Command {
+    pub fn parse(method: &str, params: &Value) -> Result<Self, RpcError> {
+        #[derive(Deserialize, Default)]
+        #[serde(default, deny_unknown_fields)]
+        struct JumpParams {
+            player: usize,
+        }
+        #[derive(Deserialize, Default)]
+        #[serde(default, deny_unknown_fields)]
+        struct StartParams {
+            seed: Option<u64>,
+            mode: Option<RemoteMode>,
+        }
+
+        fn params_of<T: for<'de> Deserialize<'de> + Default>(
+            params: &Value,
+        ) -> Result<T, RpcError> {
+            if params.is_null() {
+                return Ok(T::default());
+            }
+            serde_json::from_value(params.clone())
+                .map_err(|err| RpcError::new(INVALID_PARAMS, err.to_string()))
+        }
+
+        match method {
+            "jump" => params_of::<JumpParams>(params).map(|p| Command::Jump { player: p.player }),
+            "pause" => Ok(Command::Pause),
+            "resume" => Ok(Command::Resume),
+            "restart" => Ok(Command::Restart),
+            "start" => params_of::<StartParams>(params).map(|p| Command::Start {
+                seed: p.seed,
+                mode: p.mode,
+            }),
+            "get_state" => Ok(Command::GetState),
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method {method}"),
+            )),
+        }
+    }
+}
+
+/// A request read from a client, with the id to answer it under.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Request {
+    pub client: usize,
+    /// `None` for notifications.
+    pub id: Option<Value>,
+    pub command: Result<Command, RpcError>,
+}
+
+impl Request {
+    fn parse(client: usize, line: &str) -> Self {
+        #[derive(Deserialize)]
+        struct Envelope {
+            jsonrpc: String,
+            #[serde(default)]
+            id: Option<Value>,
+            method: String,
+            #[serde(default)]
+            params: Value,
+        }
+
+        let value: Value = match serde_json::from_str(line) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self {
+                    client,
+                    id: Some(Value::Null),
+                    command: Err(RpcError::new(PARSE_ERROR, err.to_string())),
+                }
+            }
+        };
+        let id = value.get("id").cloned();
+        match serde_json::from_value::<Envelope>(value) {
+            Ok(envelope) if envelope.jsonrpc == "2.0" => Self {
+                client,
+                id: envelope.id,
+                command: Command::parse(&envelope.method, &envelope.params),
+            },
+            _ => Self {
+                client,
+                id: Some(id.unwrap_or(Value::Null)),
+                command: Err(RpcError::new(INVALID_REQUEST, "Not a JSON-RPC 2.0 request")),
+            },
+        }
+    }
+}
+
+pub fn response(id: &Value, result: Result<Value, RpcError>) -> String {
+    let message = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+    };
+    format!("{message}\n")
+}
+
+struct SocketClient {
+    id: usize,
+    stream: TcpStream,
+    buffer: Vec<u8>,
+}
+
+enum Transport {
+    /// Lines from a reader thread on stdin; responses go to stdout. The mutex only makes
+    /// the receiver `Sync`, it is never contended.
+    Stdio(Mutex<Receiver<String>>),
+    Tcp {
+        listener: TcpListener,
+        clients: Vec<SocketClient>,
+        next_id: usize,
+    },
+}
+
+/// Reads requests without blocking and sends responses back to whoever asked.
+pub struct RpcServer {
+    transport: Transport,
+}
+
+impl RpcServer {
+    pub fn stdio() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in io::stdin().lock().lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            transport: Transport::Stdio(Mutex::new(receiver)),
+        }
+    }
+
+    /// Listens on `port` on localhost only; port 0 picks a free one.
+    pub fn tcp(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            transport: Transport::Tcp {
+                listener,
+                clients: Vec::new(),
+                next_id: 0,
+            },
+        })
+    }
+
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        match &self.transport {
+            Transport::Stdio(_) => None,
+            Transport::Tcp { listener, .. } => listener.local_addr().ok(),
+        }
+    }
+
+    /// Every complete request line received since the last poll.
+    pub fn poll(&mut self) -> Vec<Request> {
+        let mut requests = Vec::new();
+        match &mut self.transport {
+            Transport::Stdio(receiver) => loop {
+                let receiver = receiver.get_mut().unwrap_or_else(|err| err.into_inner());
+                match receiver.try_recv() {
+                    Ok(line) if line.trim().is_empty() => {}
+                    Ok(line) => requests.push(Request::parse(0, &line)),
+                    Err(TryRecvError::Empty | TryRecvError::Disconnected) => break,
+                }
+            },
+            Transport::Tcp {
+                listener,
+                clients,
+                next_id,
+            } => {
+                while let Ok((stream, _)) = listener.accept() {
+                    if stream.set_nonblocking(true).is_ok() {
+                        clients.push(SocketClient {
+                            id: *next_id,
+                            stream,
+                            buffer: Vec::new(),
+                        });
+                        *next_id += 1;
+                    }
+                }
+                clients.retain_mut(|client| {
+                    let open = read_available(&mut client.stream, &mut client.buffer);
+                    while let Some(end) = client.buffer.iter().position(|&byte| byte == b'\n') {
+                        let line: Vec<u8> = client.buffer.drain(..=end).collect();
+                        let line = String::from_utf8_lossy(&line);
+                        if !line.trim().is_empty() {
+                            requests.push(Request::parse(client.id, &line));
+                        }
+                    }
+                    open
+                });
+            }
+        }
+        requests
+    }
+
+    pub fn reply(&mut self, client: usize, line: &str) {
+        match &mut self.transport {
+            Transport::Stdio(_) => {
+                let mut stdout = io::stdout().lock();
+                let _ = stdout.write_all(line.as_bytes());
+                let _ = stdout.flush();
+            }
+            Transport::Tcp { clients, .. } => {
+                if let Some(client) = clients.iter_mut().find(|c| c.id == client) {
+                    // Clients are expected to read their responses; one that doesn't loses them
+                    let _ = client.stream.write_all(line.as_bytes());
+                }
+            }
+        }
+    }
+}
+
+/// Appends whatever the socket has ready; returns false once the peer has closed it.
+fn read_available(stream: &mut TcpStream, buffer: &mut Vec<u8>) -> bool {
+    let mut chunk = [0u8; 4096];
+    loop {
+        match stream.read(&mut chunk) {
+            Ok(0) => return false,
+            Ok(len) => buffer.extend_from_slice(&chunk[..len]),
+            Err(err) if err.kind() == ErrorKind::WouldBlock => return true,
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(_) => return false,
+        }
+    }
+}
@@ -0,0 +1,533 @@
+use bevy::diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin};
+use bevy::prelude::*;
+use bevy::window::{PresentMode, PrimaryWindow, WindowMode};
+use rlappy_bev::sim::GameConfig;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::PathBuf;
+
+use crate::background::Layer;
+use crate::coop::TeamScoreText;
+use crate::input::InputMap;
+use crate::{ActiveConfig, GameDifficulty, GameState, Ground, Pipe, PlayerScoreText, ScoreText};
+
+/// Where settings are kept unless `--settings` names another file.
+pub const DEFAULT_PATH: &str = "settings.json";
+
+/// Player preferences, saved whenever one changes. Fields missing from the file keep
+/// their defaults, so files from older versions still load.
+#[derive(Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub difficulty: Difficulty,
+    pub screen: ScreenMode,
+    pub vsync: bool,
+    pub volume: Volume,
+    pub palette: Palette,
+    pub hud: Hud,
+    pub controls: InputMap,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            difficulty: Difficulty::default(),
+            screen: ScreenMode::default(),
+            vsync: true,
+            volume: Volume::default(),
+            palette: Palette::default(),
+            hud: Hud::default(),
+            controls: InputMap::default(),
+            path: PathBuf::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// Reads the settings file, starting from the defaults if there isn't one yet.
+    pub fn load(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let mut settings: Self = match std::fs::File::open(&path) {
+            Ok(file) => {
+                serde_json::from_reader(io::BufReader::new(file)).map_err(io::Error::other)?
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(err) => return Err(err),
+        };
+        settings.path = path;
+        Ok(settings)
+    }
+
+    /// Defaults that will be saved to `path`, for when the file can't be read.
+    pub fn defaults_at(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            ..default()
+        }
+    }
+
+    pub fn save(&self) {
+        let saved = serde_json::to_string_pretty(self)
+            .map_err(io::Error::other)
+            .and_then(|json| std::fs::write(&self.path, json + "\n"));
+        if let Err(err) = saved {
+            eprintln!("Failed to save settings to {}: {err}", self.path.display());
+        }
+    }
+
+    /// Window for the first frame, so a fullscreen start doesn't flash a window first.
+    pub fn window(&self) -> Window {
+        Window {
+            mode: self.screen.window_mode(),
+            present_mode: self.present_mode(),
+            ..default()
+        }
+    }
+
+    fn present_mode(&self) -> PresentMode {
+        if self.vsync {
+            PresentMode::AutoVsync
+        } else {
+            PresentMode::AutoNoVsync
+        }
+    }
+}
+
+/// Physics preset for normal play; `--config`, `--ranked` and `--adaptive` override it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    /// Wider gaps and slower pipes that speed up half as fast.
+    Easy,
+    /// The shipped game.
+    #[default]
+    Normal,
+    /// Narrower gaps and faster pipes with a higher top speed.
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    pub fn config(self) -> GameConfig {
+        let normal = GameConfig::default();
+        match self {
+            Difficulty::Easy => GameConfig {
+                pipe_gap: normal.pipe_gap + 40.0,
+                pipe_speed: normal.pipe_speed * 0.85,
+                speed_increase_rate: normal.speed_increase_rate * 0.5,
+                max_speed_multiplier: 2.0,
+                ..normal
+            },
+            Difficulty::Normal => normal,
+            Difficulty::Hard => GameConfig {
+                pipe_gap: normal.pipe_gap - 30.0,
+                pipe_speed: normal.pipe_speed * 1.15,
+                speed_increase_rate: normal.speed_increase_rate * 1.5,
+                max_speed_multiplier: 3.0,
+                ..normal
+            },
+        }
+    }
+}
+
+/// Why the difficulty preset isn't used this session, shown on the settings screen.
+#[derive(Resource)]
+pub struct DifficultyLock(pub &'static str);
+
+/// Puts the preset's config back in the menu, after tournaments, challenges and LAN
+/// matches have swapped in their own.
+pub fn apply_difficulty(settings: Res<Settings>, mut config: ResMut<ActiveConfig>) {
+    config.0 = settings.difficulty.config();
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScreenMode {
+    #[default]
+    Windowed,
+    Borderless,
+    Fullscreen,
+}
+
+impl ScreenMode {
+    pub const ALL: [ScreenMode; 3] = [
+        ScreenMode::Windowed,
+        ScreenMode::Borderless,
+        ScreenMode::Fullscreen,
+    ];
+
+    fn window_mode(self) -> WindowMode {
+        match self {
+            ScreenMode::Windowed => WindowMode::Windowed,
+            ScreenMode::Borderless => WindowMode::BorderlessFullscreen,
+            ScreenMode::Fullscreen => WindowMode::Fullscreen,
+        }
+    }
+}
+
+pub fn apply_window(settings: Res<Settings>, mut windows: Query<&mut Window, With<PrimaryWindow>>) {
+    for mut window in windows.iter_mut() {
+        let mode = settings.screen.window_mode();
+        if window.mode != mode {
+            window.mode = mode;
+        }
+        let present_mode = settings.present_mode();
+        if window.present_mode != present_mode {
+            window.present_mode = present_mode;
+        }
+    }
+}
+
+/// Volumes in percent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Volume {
+    pub master: u8,
+    pub effects: u8,
+    pub music: u8,
+    pub muted: bool,
+}
+
+impl Default for Volume {
+    fn default() -> Self {
+        Self {
+            master: 100,
+            effects: 100,
+            music: 70,
+            muted: false,
+        }
+    }
+}
+
+#[cfg(feature = "audio")]
+impl Volume {
+    /// Linear gain for sound effects, 0 while muted.
+    pub fn effects_gain(&self) -> f32 {
+        self.gain(self.effects)
+    }
+
+    /// Linear gain for the music, 0 while muted.
+    pub fn music_gain(&self) -> f32 {
+        self.gain(self.music)
+    }
+
+    fn gain(&self, channel: u8) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.master as f32 / 100.0 * channel as f32 / 100.0
+        }
+    }
+}
+
+/// Colours of the sky, pipes, ground and background layers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Palette {
+    /// The original colours.
+    #[default]
+    Classic,
+    Day,
+    Dusk,
+    Night,
+    /// Yellow pipes on black, for players who have trouble telling the greens apart.
+    HighContrast,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 5] = [
+        Palette::Classic,
+        Palette::Day,
+        Palette::Dusk,
+        Palette::Night,
+        Palette::HighContrast,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Palette::Classic => "Classic",
+            Palette::Day => "Day",
+            Palette::Dusk => "Dusk",
+            Palette::Night => "Night",
+            Palette::HighContrast => "High contrast",
+        }
+    }
+
+    pub fn sky(self) -> Color {
+        match self {
+            // Bevy's default clear colour
+            Palette::Classic => Color::srgb_u8(43, 44, 47),
+            Palette::Day => Color::srgb(0.45, 0.75, 0.95),
+            Palette::Dusk => Color::srgb(0.9, 0.55, 0.45),
+            Palette::Night => Color::srgb(0.05, 0.07, 0.18),
+            Palette::HighContrast => Color::BLACK,
+        }
+    }
+
+    pub fn pipe(self) -> Color {
+        match self {
+            Palette::Classic => Color::srgb(0.0, 0.8, 0.0),
+            Palette::Day => Color::srgb(0.1, 0.65, 0.15),
+            Palette::Dusk => Color::srgb(0.2, 0.45, 0.25),
+            Palette::Night => Color::srgb(0.15, 0.5, 0.3),
+            Palette::HighContrast => Color::srgb(1.0, 0.85, 0.0),
+        }
+    }
+
+    pub fn ground(self) -> Color {
+        match self {
+            Palette::Classic => Color::srgb(0.3, 0.8, 0.3),
+            Palette::Day => Color::srgb(0.85, 0.75, 0.45),
+            Palette::Dusk => Color::srgb(0.45, 0.3, 0.3),
+            Palette::Night => Color::srgb(0.15, 0.2, 0.25),
+            Palette::HighContrast => Color::WHITE,
+        }
+    }
+
+    /// Tint of a background layer, or `None` to leave it out.
+    pub fn layer(self, layer: Layer) -> Option<Color> {
+        match (self, layer) {
+            // Nothing behind the pipes to get in the way
+            (Palette::HighContrast, _) => None,
+            // Keeps the flat grey sky, with faint shapes in front of it
+            (Palette::Classic, Layer::Sky) => None,
+            (Palette::Classic, Layer::Clouds) => Some(Color::srgba(1.0, 1.0, 1.0, 0.06)),
+            (Palette::Classic, Layer::Hills) => Some(Color::srgb(0.21, 0.23, 0.25)),
+            (Palette::Classic, Layer::City) => Some(Color::srgb(0.17, 0.18, 0.2)),
+            (Palette::Day, Layer::Sky) => Some(Color::srgb(0.8, 0.92, 1.0)),
+            (Palette::Day, Layer::Clouds) => Some(Color::srgba(1.0, 1.0, 1.0, 0.9)),
+            (Palette::Day, Layer::Hills) => Some(Color::srgb(0.55, 0.78, 0.5)),
+            (Palette::Day, Layer::City) => Some(Color::srgb(0.6, 0.68, 0.78)),
+            (Palette::Dusk, Layer::Sky) => Some(Color::srgb(1.0, 0.78, 0.45)),
+            (Palette::Dusk, Layer::Clouds) => Some(Color::srgba(1.0, 0.8, 0.75, 0.7)),
+            (Palette::Dusk, Layer::Hills) => Some(Color::srgb(0.6, 0.38, 0.42)),
+            (Palette::Dusk, Layer::City) => Some(Color::srgb(0.35, 0.22, 0.3)),
+            (Palette::Night, Layer::Sky) => Some(Color::srgb(0.12, 0.14, 0.3)),
+            (Palette::Night, Layer::Clouds) => Some(Color::srgba(0.6, 0.65, 0.8, 0.15)),
+            (Palette::Night, Layer::Hills) => Some(Color::srgb(0.08, 0.1, 0.2)),
+            (Palette::Night, Layer::City) => Some(Color::srgb(0.04, 0.05, 0.12)),
+        }
+    }
+}
+
+/// Colours the sky and everything on the course when the palette changes, and new pipes
+/// and ground as they spawn.
+pub fn apply_palette(
+    settings: Res<Settings>,
+    mut clear_color: ResMut<ClearColor>,
+    mut pipes: Query<(&mut Sprite, Ref<Pipe>), Without<Ground>>,
+    mut ground: Query<(&mut Sprite, Ref<Ground>), Without<Pipe>>,
+) {
+    let palette = settings.palette;
+    let all = settings.is_changed();
+    if all && clear_color.0 != palette.sky() {
+        clear_color.0 = palette.sky();
+    }
+    for (mut sprite, pipe) in pipes.iter_mut() {
+        if all || pipe.is_added() {
+            sprite.color = palette.pipe();
+        }
+    }
+    for (mut sprite, marker) in ground.iter_mut() {
+        if all || marker.is_added() {
+            sprite.color = palette.ground();
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Hud {
+    pub score: bool,
+    /// Current speed multiplier during a run.
+    pub speed: bool,
+    pub fps: bool,
+}
+
+impl Default for Hud {
+    fn default() -> Self {
+        Self {
+            score: true,
+            speed: false,
+            fps: false,
+        }
+    }
+}
+
+/// Mute, speed and frame rate readout in the bottom-left corner.
+#[derive(Component)]
+pub struct HudText;
+
+pub fn spawn_hud(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 20.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        }),
+        HudText,
+    ));
+}
+
+pub fn update_hud(
+    settings: Res<Settings>,
+    state: Res<State<GameState>>,
+    difficulty: Res<GameDifficulty>,
+    diagnostics: Res<DiagnosticsStore>,
+    mut hud: Query<&mut Text, With<HudText>>,
+    mut scores: Query<
+        &mut Visibility,
+        Or<(With<ScoreText>, With<PlayerScoreText>, With<TeamScoreText>)>,
+    >,
+) {
+    let hud_settings = settings.hud;
+    let mut lines = Vec::new();
+    if settings.volume.muted {
+        lines.push("Muted".to_string());
+    }
+    if hud_settings.speed && matches!(state.get(), GameState::Playing | GameState::Paused) {
+        lines.push(format!("Speed x{:.2}", difficulty.speed_multiplier));
+    }
+    if hud_settings.fps {
+        let fps = diagnostics
+            .get(&FrameTimeDiagnosticsPlugin::FPS)
+            .and_then(|fps| fps.smoothed());
+        lines.push(fps.map_or("FPS --".to_string(), |fps| format!("FPS {fps:.0}")));
+    }
+    let value = lines.join("\n");
+    for mut text in hud.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+
+    let visibility = if hud_settings.score {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    for mut score in scores.iter_mut() {
+        if *score != visibility {
+            *score = visibility;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{Action, Binding};
+
+    /// A settings path of its own for each test, removed when dropped.
+    struct TempPath(PathBuf);
+
+    impl TempPath {
+        fn new(name: &str) -> Self {
+            let file = format!("rlappy-settings-{name}-{}.json", std::process::id());
+            Self(std::env::temp_dir().join(file))
+        }
+    }
+
+    impl Drop for TempPath {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn a_missing_file_gives_the_defaults() {
+        let path = TempPath::new("missing");
+        let settings = Settings::load(&path.0).unwrap();
+        assert_eq!(settings.difficulty, Difficulty::Normal);
+        assert_eq!(settings.screen, ScreenMode::Windowed);
+        assert!(settings.vsync);
+        assert_eq!(settings.volume, Volume::default());
+        assert_eq!(settings.palette, Palette::Classic);
+        assert_eq!(settings.hud, Hud::default());
+        assert_eq!(settings.controls, InputMap::default());
+        assert_eq!(settings.path, path.0);
+    }
+
+    #[test]
+    fn saved_settings_load_back() {
+        let path = TempPath::new("round-trip");
+        let mut settings = Settings::defaults_at(&path.0);
+        settings.difficulty = Difficulty::Hard;
+        settings.screen = ScreenMode::Borderless;
+        settings.vsync = false;
+        settings.volume.music = 20;
+        settings.palette = Palette::HighContrast;
+        settings.hud.fps = true;
+        settings
+            .controls
+            .rebind(Action::Jump, Binding::Key(KeyCode::KeyW));
+        settings.save();
+
+        let loaded = Settings::load(&path.0).unwrap();
+        assert_eq!(loaded.difficulty, Difficulty::Hard);
+        assert_eq!(loaded.screen, ScreenMode::Borderless);
+        assert!(!loaded.vsync);
+        assert_eq!(loaded.volume.music, 20);
+        assert_eq!(loaded.palette, Palette::HighContrast);
+        assert!(loaded.hud.fps);
+        assert_eq!(loaded.controls, settings.controls);
+    }
+
+    #[test]
+    fn older_files_keep_defaults_for_what_they_lack() {
+        let path = TempPath::new("partial");
+        std::fs::write(&path.0, r#"{"palette": "night", "volume": {"master": 50}}"#).unwrap();
+        let settings = Settings::load(&path.0).unwrap();
+        assert_eq!(settings.palette, Palette::Night);
+        assert_eq!(settings.volume.master, 50);
+        assert_eq!(settings.volume.music, Volume::default().music);
+        assert_eq!(settings.difficulty, Difficulty::Normal);
+        assert_eq!(settings.controls, InputMap::default());
+    }
+
+    #[test]
+    fn unreadable_files_are_errors() {
+        let path = TempPath::new("broken");
+        std::fs::write(&path.0, "{ not json").unwrap();
+        assert!(Settings::load(&path.0).is_err());
+    }
+
+    #[test]
+    fn difficulty_presets_are_playable_and_ordered() {
+        for difficulty in Difficulty::ALL {
+            assert_eq!(difficulty.config().validate(), Ok(()), "{difficulty:?}");
+        }
+        let [easy, normal, hard] = Difficulty::ALL.map(Difficulty::config);
+        assert_eq!(normal, GameConfig::default());
+        assert!(easy.pipe_gap > normal.pipe_gap && normal.pipe_gap > hard.pipe_gap);
+        assert!(easy.pipe_speed < normal.pipe_speed && normal.pipe_speed < hard.pipe_speed);
+    }
+
+    #[cfg(feature = "audio")]
+    #[test]
+    fn muting_silences_every_channel() {
+        let mut volume = Volume {
+            master: 50,
+            effects: 80,
+            music: 100,
+            muted: false,
+        };
+        assert_eq!(volume.effects_gain(), 0.4);
+        assert_eq!(volume.music_gain(), 0.5);
+        volume.muted = true;
+        assert_eq!(volume.effects_gain(), 0.0);
+        assert_eq!(volume.music_gain(), 0.0);
+    }
+}
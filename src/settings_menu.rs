@@ -0,0 +1,325 @@
+use bevy::prelude::*;
+
+use crate::input::{Action, Actions, ButtonFocus};
+use crate::settings::{Difficulty, DifficultyLock, Palette, ScreenMode, Settings};
+use crate::{pause_game, GameState, PauseText, StartButton};
+
+/// Settings screen, removed when leaving `GameState::Settings`.
+#[derive(Component)]
+pub struct SettingsUi;
+
+/// Present while the settings screen was opened from the pause overlay, so BACK returns
+/// to the paused run instead of the menu.
+#[derive(Resource)]
+pub struct SettingsFromPause;
+
+/// SETTINGS button on the pause overlay.
+#[derive(Component)]
+pub struct PauseSettingsButton;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsButton {
+    Row(Row),
+    Back,
+}
+
+/// One adjustable setting; Confirm or a click steps it forward, Left and Right step it
+/// either way.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Row {
+    Difficulty,
+    Screen,
+    Vsync,
+    MasterVolume,
+    EffectsVolume,
+    MusicVolume,
+    Mute,
+    Palette,
+    Score,
+    Speed,
+    Fps,
+}
+
+/// Value on a row's button.
+#[derive(Component)]
+pub struct RowText(Row);
+
+const ROWS: [Row; 11] = [
+    Row::Difficulty,
+    Row::Screen,
+    Row::Vsync,
+    Row::MasterVolume,
+    Row::EffectsVolume,
+    Row::MusicVolume,
+    Row::Mute,
+    Row::Palette,
+    Row::Score,
+    Row::Speed,
+    Row::Fps,
+];
+
+const VOLUME_STEP: i32 = 10;
+
+const BUTTON_COLOR: Color = Color::srgb(0.2, 0.7, 0.2);
+const BUTTON_HOVER: Color = Color::srgb(0.25, 0.85, 0.25);
+const BUTTON_PRESSED: Color = Color::srgb(0.1, 0.5, 0.1);
+
+/// Next or previous entry of `all`, wrapping around.
+fn cycle<T: Copy + PartialEq>(all: &[T], current: T, step: i32) -> T {
+    let index = all.iter().position(|&item| item == current).unwrap_or(0) as i32;
+    all[(index + step).rem_euclid(all.len() as i32) as usize]
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "On"
+    } else {
+        "Off"
+    }
+}
+
+impl Row {
+    fn label(self, settings: &Settings, lock: Option<&DifficultyLock>, paused: bool) -> String {
+        match self {
+            Row::Difficulty => match lock {
+                Some(lock) => format!("Difficulty: {:?} ({})", settings.difficulty, lock.0),
+                None if paused => {
+                    format!("Difficulty: {:?} (from the next run)", settings.difficulty)
+                }
+                None => format!("Difficulty: {:?}", settings.difficulty),
+            },
+            Row::Screen => format!("Window: {:?}", settings.screen),
+            Row::Vsync => format!("VSync: {}", on_off(settings.vsync)),
+            Row::MasterVolume => format!("Master volume: {}%", settings.volume.master),
+            Row::EffectsVolume => format!("Effects volume: {}%", settings.volume.effects),
+            Row::MusicVolume => format!("Music volume: {}%", settings.volume.music),
+            Row::Mute if settings.volume.muted => "Sound: Muted".to_string(),
+            Row::Mute => "Sound: On".to_string(),
+            Row::Palette => format!("Palette: {}", settings.palette.label()),
+            Row::Score => format!("Show score: {}", on_off(settings.hud.score)),
+            Row::Speed => format!("Show speed: {}", on_off(settings.hud.speed)),
+            Row::Fps => format!("Show FPS: {}", on_off(settings.hud.fps)),
+        }
+    }
+
+    /// Moves the setting `step` places; volumes stop at 0 and 100, everything else wraps.
+    fn step(self, settings: &mut Settings, step: i32) {
+        let volume = |value: u8| (value as i32 + step * VOLUME_STEP).clamp(0, 100) as u8;
+        match self {
+            Row::Difficulty => {
+                settings.difficulty = cycle(&Difficulty::ALL, settings.difficulty, step)
+            }
+            Row::Screen => settings.screen = cycle(&ScreenMode::ALL, settings.screen, step),
+            Row::Vsync => settings.vsync = !settings.vsync,
+            Row::MasterVolume => settings.volume.master = volume(settings.volume.master),
+            Row::EffectsVolume => settings.volume.effects = volume(settings.volume.effects),
+            Row::MusicVolume => settings.volume.music = volume(settings.volume.music),
+            Row::Mute => settings.volume.muted = !settings.volume.muted,
+            Row::Palette => settings.palette = cycle(&Palette::ALL, settings.palette, step),
+            Row::Score => settings.hud.score = !settings.hud.score,
+            Row::Speed => settings.hud.speed = !settings.hud.speed,
+            Row::Fps => settings.hud.fps = !settings.hud.fps,
+        }
+    }
+}
+
+fn button(width: f32) -> ButtonBundle {
+    ButtonBundle {
+        style: Style {
+            width: Val::Px(width),
+            height: Val::Px(36.0),
+            // Outlined while focused for keyboard and gamepad
+            border: UiRect::all(Val::Px(2.0)),
+            padding: UiRect::horizontal(Val::Px(10.0)),
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        background_color: BUTTON_COLOR.into(),
+        ..default()
+    }
+}
+
+fn label(value: String) -> TextBundle {
+    TextBundle::from_section(
+        value,
+        TextStyle {
+            font_size: 20.0,
+            color: Color::WHITE,
+            ..default()
+        },
+    )
+}
+
+pub fn spawn_settings(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    lock: Option<Res<DifficultyLock>>,
+    from_pause: Option<Res<SettingsFromPause>>,
+) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    margin: UiRect::all(Val::Auto),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(6.0),
+                    padding: UiRect::all(Val::Px(15.0)),
+                    ..default()
+                },
+                // Covers a paused run behind it
+                background_color: Color::srgba(0.0, 0.0, 0.0, 0.8).into(),
+                ..default()
+            },
+            SettingsUi,
+        ))
+        .with_children(|parent| {
+            parent.spawn(label("SETTINGS".to_string()));
+            for row in ROWS {
+                let value = row.label(&settings, lock.as_deref(), from_pause.is_some());
+                parent
+                    .spawn((button(460.0), StartButton, SettingsButton::Row(row)))
+                    .with_children(|parent| {
+                        parent.spawn((label(value), RowText(row)));
+                    });
+            }
+            parent
+                .spawn((button(200.0), StartButton, SettingsButton::Back))
+                .with_children(|parent| {
+                    parent.spawn(label("BACK".to_string()));
+                });
+            parent.spawn(label(
+                "Enter or click: change  Left/Right: adjust  Esc: back".to_string(),
+            ));
+        });
+}
+
+/// Steps the pressed or focused row and saves, or leaves on BACK or Back.
+pub fn settings_buttons(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, &SettingsButton),
+        Changed<Interaction>,
+    >,
+    buttons: Query<(Entity, &GlobalTransform), With<StartButton>>,
+    settings_buttons: Query<&SettingsButton>,
+    actions: Res<Actions>,
+    focus: Res<ButtonFocus>,
+    mut settings: ResMut<Settings>,
+    lock: Option<Res<DifficultyLock>>,
+    from_pause: Option<Res<SettingsFromPause>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut commands: Commands,
+) {
+    let mut pressed = None;
+    for (interaction, mut color, button) in &mut interaction_query {
+        *color = match *interaction {
+            Interaction::Pressed => {
+                pressed = Some((*button, 1));
+                BUTTON_PRESSED.into()
+            }
+            Interaction::Hovered => BUTTON_HOVER.into(),
+            Interaction::None => BUTTON_COLOR.into(),
+        };
+    }
+    let focused = focus
+        .current(&buttons)
+        .and_then(|entity| settings_buttons.get(entity).ok().copied());
+    if actions.just_pressed(Action::Back) {
+        pressed = Some((SettingsButton::Back, 1));
+    } else if let Some(button) = focused {
+        if actions.just_pressed(Action::Confirm) {
+            pressed = Some((button, 1));
+        } else if let SettingsButton::Row(_) = button {
+            if actions.just_pressed(Action::Right) {
+                pressed = Some((button, 1));
+            } else if actions.just_pressed(Action::Left) {
+                pressed = Some((button, -1));
+            }
+        }
+    }
+
+    match pressed {
+        Some((SettingsButton::Row(Row::Difficulty), _)) if lock.is_some() => {}
+        Some((SettingsButton::Row(row), step)) => {
+            row.step(&mut settings, step);
+            settings.save();
+        }
+        Some((SettingsButton::Back, _)) => {
+            if from_pause.is_some() {
+                commands.remove_resource::<SettingsFromPause>();
+                pause_game(&mut commands, &mut next_state);
+            } else {
+                next_state.set(GameState::Menu);
+            }
+        }
+        None => {}
+    }
+}
+
+pub fn update_settings_text(
+    settings: Res<Settings>,
+    lock: Option<Res<DifficultyLock>>,
+    from_pause: Option<Res<SettingsFromPause>>,
+    mut texts: Query<(&mut Text, &RowText)>,
+) {
+    for (mut text, row) in texts.iter_mut() {
+        let value = row
+            .0
+            .label(&settings, lock.as_deref(), from_pause.is_some());
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
+
+/// SETTINGS button and O key on the pause overlay.
+pub fn pause_settings_input(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<PauseSettingsButton>),
+    >,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut commands: Commands,
+) {
+    let mut open = keyboard.just_pressed(KeyCode::KeyO);
+    for (interaction, mut color) in &mut interaction_query {
+        *color = match *interaction {
+            Interaction::Pressed => {
+                open = true;
+                BUTTON_PRESSED.into()
+            }
+            Interaction::Hovered => BUTTON_HOVER.into(),
+            Interaction::None => BUTTON_COLOR.into(),
+        };
+    }
+    if open {
+        commands.insert_resource(SettingsFromPause);
+        next_state.set(GameState::Settings);
+    }
+}
+
+pub fn spawn_pause_settings_button(commands: &mut Commands) {
+    commands
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(360.0),
+                    left: Val::Px(300.0),
+                    width: Val::Px(200.0),
+                    height: Val::Px(50.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: BUTTON_COLOR.into(),
+                ..default()
+            },
+            PauseSettingsButton,
+            PauseText,
+        ))
+        .with_children(|parent| {
+            parent.spawn(label("SETTINGS".to_string()));
+        });
+}
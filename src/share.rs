@@ -0,0 +1,347 @@
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::prelude::*;
+use rlappy_bev::challenge::{this_version, Challenge, ChallengeMode};
+use std::sync::Mutex;
+
+use crate::tas::Tas;
+use crate::{
+    coop, start_game, ActiveConfig, GameMode, GameOverUi, GameSeed, GameState, MatchSeed, MenuUi,
+    PendingJump, Player, PlayerScore, Score,
+};
+
+/// Player one's jump flag for every fixed tick of the current run, for challenge codes.
+#[derive(Resource, Default)]
+pub struct RunInputs(pub Vec<bool>);
+
+/// The system clipboard, if there is one; a game without it still shows and prints codes.
+#[derive(Resource)]
+pub struct Clipboard(Mutex<Option<arboard::Clipboard>>);
+
+impl Default for Clipboard {
+    fn default() -> Self {
+        Self(Mutex::new(arboard::Clipboard::new().ok()))
+    }
+}
+
+impl Clipboard {
+    fn get(&self) -> Option<String> {
+        let mut clipboard = self.0.lock().ok()?;
+        clipboard.as_mut()?.get_text().ok()
+    }
+
+    fn set(&self, text: &str) -> bool {
+        let Ok(mut clipboard) = self.0.lock() else {
+            return false;
+        };
+        clipboard
+            .as_mut()
+            .is_some_and(|clipboard| clipboard.set_text(text).is_ok())
+    }
+}
+
+/// Challenge being played, with the config to go back to afterwards.
+#[derive(Resource)]
+pub struct ActiveChallenge {
+    challenge: Challenge,
+    previous_config: rlappy_bev::sim::GameConfig,
+}
+
+/// Code for the run that just ended, copied with C on the game over screen.
+#[derive(Resource)]
+pub struct LastCode(String);
+
+/// Text field for pasting a code, open in the menu after clicking CHALLENGE or pressing H.
+#[derive(Resource, Default)]
+pub struct ChallengeField {
+    text: String,
+    /// Text the preview was made for and the preview, since checking a code with inputs
+    /// re-simulates the whole run.
+    preview: Option<(String, String)>,
+}
+
+#[derive(Component)]
+pub struct ChallengeFieldText;
+
+/// Characters of the code shown in the field.
+const FIELD_WIDTH: usize = 40;
+
+impl From<GameMode> for ChallengeMode {
+    fn from(mode: GameMode) -> Self {
+        match mode {
+            GameMode::Solo => ChallengeMode::Solo,
+            GameMode::Versus => ChallengeMode::Versus,
+            GameMode::SplitScreen => ChallengeMode::SplitScreen,
+            GameMode::Coop => ChallengeMode::Coop,
+        }
+    }
+}
+
+impl From<ChallengeMode> for GameMode {
+    fn from(mode: ChallengeMode) -> Self {
+        match mode {
+            ChallengeMode::Solo => GameMode::Solo,
+            ChallengeMode::Versus => GameMode::Versus,
+            ChallengeMode::SplitScreen => GameMode::SplitScreen,
+            ChallengeMode::Coop => GameMode::Coop,
+        }
+    }
+}
+
+pub fn record_run_input(pending_jump: Res<PendingJump>, mut inputs: ResMut<RunInputs>) {
+    inputs.0.push(pending_jump.0[0]);
+}
+
+/// The number a challenge is scored on: the score, the team score in co-op, or the best
+/// bird's score in versus.
+fn run_score<'a>(
+    mode: GameMode,
+    score: &Score,
+    player_scores: impl Iterator<Item = &'a PlayerScore>,
+) -> u32 {
+    match mode {
+        GameMode::Solo => score.0 / 2,
+        GameMode::Coop => coop::team_score(score),
+        GameMode::Versus | GameMode::SplitScreen => {
+            player_scores.map(|score| score.0 / 2).max().unwrap_or(0)
+        }
+    }
+}
+
+/// Shows the finished run's code and, when a challenge was played, how it went.
+pub fn spawn_challenge_code(
+    mut commands: Commands,
+    mode: Res<GameMode>,
+    seed: Res<GameSeed>,
+    config: Res<ActiveConfig>,
+    score: Res<Score>,
+    inputs: Res<RunInputs>,
+    tas: Option<Res<Tas>>,
+    active: Option<Res<ActiveChallenge>>,
+    players: Query<&PlayerScore, With<Player>>,
+) {
+    let score = run_score(*mode, &score, players.iter());
+    let mut challenge = Challenge::new(seed.0, (*mode).into(), score, config.0);
+    let short = challenge.encode();
+    // Stepping back in TAS rewrites the run, so its live input log isn't the real one
+    if *mode == GameMode::Solo && tas.is_none() {
+        challenge.inputs = Some(inputs.0.clone());
+    }
+    let full = challenge.encode();
+    eprintln!("Challenge code: {full}");
+
+    let mut message = format!("Challenge code: {short}\nC: copy code with inputs");
+    if let Some(active) = active {
+        let theirs = active.challenge.score;
+        let verdict = if score > theirs {
+            "Challenge beaten!"
+        } else if score == theirs {
+            "Challenge tied"
+        } else {
+            "Challenge lost"
+        };
+        message = format!("{verdict} You {score}, them {theirs}\n{message}");
+    }
+    commands.spawn((
+        TextBundle::from_section(
+            message,
+            TextStyle {
+                font_size: 20.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(400.0),
+            left: Val::Px(200.0),
+            ..default()
+        }),
+        GameOverUi,
+    ));
+    commands.insert_resource(LastCode(full));
+}
+
+pub fn copy_challenge_code(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    code: Res<LastCode>,
+    clipboard: Res<Clipboard>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyC) {
+        if clipboard.set(&code.0) {
+            eprintln!("Challenge code copied");
+        } else {
+            eprintln!("No clipboard available; the code is printed above");
+        }
+    }
+}
+
+/// Puts the config back once a challenge's run is over.
+pub fn end_challenge(
+    mut commands: Commands,
+    active: Option<Res<ActiveChallenge>>,
+    mut config: ResMut<ActiveConfig>,
+) {
+    if let Some(active) = active {
+        config.0 = active.previous_config;
+        commands.remove_resource::<ActiveChallenge>();
+    }
+}
+
+pub fn spawn_challenge_field(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 22.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(40.0),
+            left: Val::Px(40.0),
+            ..default()
+        }),
+        ChallengeFieldText,
+        MenuUi,
+    ));
+}
+
+/// Typing, Ctrl+V, Backspace, Enter to play the challenge and Esc to close the field.
+pub fn challenge_field_input(
+    mut field: ResMut<ChallengeField>,
+    mut typed: EventReader<KeyboardInput>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    clipboard: Res<Clipboard>,
+    mut config: ResMut<ActiveConfig>,
+    mut mode: ResMut<GameMode>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    texts: Query<Entity, With<ChallengeFieldText>>,
+) {
+    // The key that opened the field is still in the event queue
+    if field.is_added() {
+        typed.clear();
+        return;
+    }
+    let ctrl = keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    if ctrl && keyboard.just_pressed(KeyCode::KeyV) {
+        if let Some(text) = clipboard.get() {
+            field.text.push_str(text.trim());
+        }
+    }
+
+    let mut confirm = false;
+    for event in typed.read() {
+        if !event.state.is_pressed() {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Character(text) if !ctrl => field.text.extend(
+                text.chars()
+                    .filter(|c| c.is_ascii_alphanumeric() || "-_".contains(*c)),
+            ),
+            Key::Backspace => {
+                field.text.pop();
+            }
+            Key::Enter => confirm = true,
+            Key::Escape => {
+                commands.remove_resource::<ChallengeField>();
+                for entity in texts.iter() {
+                    commands.entity(entity).despawn();
+                }
+                return;
+            }
+            _ => {}
+        }
+    }
+
+    if !confirm {
+        return;
+    }
+    let Ok(challenge) = Challenge::decode(&field.text) else {
+        return;
+    };
+    commands.remove_resource::<ChallengeField>();
+    accept_challenge(&mut commands, &mut config, &mut mode, challenge);
+    start_game(
+        &mut commands,
+        &asset_server,
+        &mut texture_atlas_layouts,
+        *mode,
+        &mut next_state,
+    );
+}
+
+/// Sets up the next run to fly `challenge`'s course, keeping the config to go back to.
+pub fn accept_challenge(
+    commands: &mut Commands,
+    config: &mut ActiveConfig,
+    mode: &mut GameMode,
+    challenge: Challenge,
+) {
+    commands.insert_resource(MatchSeed(challenge.seed));
+    *mode = challenge.mode.into();
+    commands.insert_resource(ActiveChallenge {
+        previous_config: std::mem::replace(&mut config.0, challenge.config),
+        challenge,
+    });
+}
+
+/// What the code in the field would start, or why it can't.
+fn preview(text: &str) -> String {
+    if text.is_empty() {
+        "Paste (Ctrl+V) or type a code".to_string()
+    } else {
+        match Challenge::decode(text) {
+            Ok(challenge) => {
+                let verified = match challenge.verified_score() {
+                    Some(score) if score == challenge.score => " (verified from inputs)",
+                    Some(_) => " (does NOT match its inputs)",
+                    None => "",
+                };
+                let version = if challenge.game_version == this_version() {
+                    String::new()
+                } else {
+                    format!("\nMade with version {}", challenge.game_version_string())
+                };
+                format!(
+                    "{} on seed {}, score to beat: {}{verified}{version}\nEnter: play",
+                    challenge.mode.label(),
+                    challenge.seed,
+                    challenge.score,
+                )
+            }
+            Err(err) => format!("Invalid: {err}"),
+        }
+    }
+}
+
+pub fn update_challenge_field(
+    mut field: ResMut<ChallengeField>,
+    mut texts: Query<&mut Text, With<ChallengeFieldText>>,
+) {
+    let field = &mut *field;
+    if field
+        .preview
+        .as_ref()
+        .is_none_or(|(text, _)| *text != field.text)
+    {
+        field.preview = Some((field.text.clone(), preview(&field.text)));
+    }
+    let preview = field.preview.as_ref().map_or("", |(_, preview)| preview);
+    // Codes with an input log run long, so show only their end
+    let shown = match field.text.char_indices().rev().nth(FIELD_WIDTH) {
+        Some((cut, _)) => format!("...{}", &field.text[cut + 1..]),
+        None => field.text.clone(),
+    };
+    let value = format!("CHALLENGE\n\n> {shown}_\n\n{preview}\nEsc: close");
+    for mut text in texts.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
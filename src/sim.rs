@@ -0,0 +1,389 @@
+//! Headless, fixed-tick model of a run.
+//!
+//! Mirrors the order and arithmetic of the windowed game's `FixedUpdate` systems so a
+//! seed and a jump sequence produce the same run with or without a window.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+
+pub const GRAVITY: f32 = -500.0;
+pub const BIRD_JUMP: f32 = 300.0;
+pub const PIPE_SPEED: f32 = 150.0;
+pub const PIPE_GAP: f32 = 200.0;
+pub const PIPE_SPAWN_INTERVAL: f32 = 2.0;
+pub const GROUND_HEIGHT: f32 = -250.0;
+pub const SPEED_INCREASE_RATE: f32 = 0.05; // Speed multiplier increase per pipe passed
+pub const MAX_SPEED_MULTIPLIER: f32 = 2.5; // Maximum speed multiplier
+pub const TICK_RATE: f64 = 60.0; // Fixed gameplay ticks per second
+
+// Layout of the 800x600 window, in world units centred on the origin
+pub const WINDOW_WIDTH: f32 = 800.0;
+pub const WINDOW_HEIGHT: f32 = 600.0;
+pub const BIRD_X: f32 = -100.0;
+pub const BIRD_SIZE: f32 = 30.0;
+pub const PIPE_WIDTH: f32 = 60.0;
+pub const PIPE_HEIGHT: f32 = 400.0;
+pub const PIPE_SPAWN_X: f32 = 500.0;
+pub const PIPE_DESPAWN_X: f32 = -500.0;
+pub const GAP_RANGE: f32 = 150.0; // Gap centres are drawn from -GAP_RANGE..GAP_RANGE
+pub const GROUND_WIDTH: f32 = 1000.0;
+pub const GROUND_THICKNESS: f32 = 50.0;
+pub const CEILING_HEIGHT: f32 = 300.0;
+
+/// Tunable physics and difficulty parameters. Defaults are the shipped game's constants.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameConfig {
+    pub gravity: f32,
+    pub jump_velocity: f32,
+    pub pipe_speed: f32,
+    pub pipe_gap: f32,
+    pub pipe_spawn_interval: f32,
+    pub speed_increase_rate: f32,
+    pub max_speed_multiplier: f32,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            gravity: GRAVITY,
+            jump_velocity: BIRD_JUMP,
+            pipe_speed: PIPE_SPEED,
+            pipe_gap: PIPE_GAP,
+            pipe_spawn_interval: PIPE_SPAWN_INTERVAL,
+            speed_increase_rate: SPEED_INCREASE_RATE,
+            max_speed_multiplier: MAX_SPEED_MULTIPLIER,
+        }
+    }
+}
+
+/// A [`GameConfig`] field outside the range the simulation can run with, named as in the
+/// JSON file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvalidConfig(pub &'static str);
+
+impl std::fmt::Display for InvalidConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "config field `{}` is out of range", self.0)
+    }
+}
+
+impl std::error::Error for InvalidConfig {}
+
+impl GameConfig {
+    /// Reads a JSON config; missing fields keep their defaults.
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let config: Self = serde_json::from_reader(std::io::BufReader::new(file))
+            .map_err(std::io::Error::other)?;
+        config.validate().map_err(std::io::Error::other)?;
+        Ok(config)
+    }
+
+    /// Checks that every field is finite and points the right way, so a config from a file
+    /// or from another player can't panic or stall the game.
+    pub fn validate(&self) -> Result<(), InvalidConfig> {
+        fn check(
+            name: &'static str,
+            value: f32,
+            in_range: fn(f32) -> bool,
+        ) -> Result<(), InvalidConfig> {
+            // NaN fails every comparison, so only infinities need their own test
+            if value.is_finite() && in_range(value) {
+                Ok(())
+            } else {
+                Err(InvalidConfig(name))
+            }
+        }
+        let positive = |value: f32| value > 0.0;
+        check("gravity", self.gravity, |value| value < 0.0)?;
+        check("jump_velocity", self.jump_velocity, positive)?;
+        check("pipe_speed", self.pipe_speed, positive)?;
+        check("pipe_gap", self.pipe_gap, positive)?;
+        // Also has to fit in a `Duration`
+        check("pipe_spawn_interval", self.pipe_spawn_interval, |value| {
+            value > 0.0 && Duration::try_from_secs_f32(value).is_ok()
+        })?;
+        check("speed_increase_rate", self.speed_increase_rate, |value| {
+            value >= 0.0
+        })?;
+        check("max_speed_multiplier", self.max_speed_multiplier, positive)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, json + "\n")
+    }
+
+    /// Centre heights of the top and bottom pipe around a gap.
+    pub fn pipe_centres(&self, gap_y: f32) -> [f32; 2] {
+        [
+            gap_y + self.pipe_gap / 2.0 + PIPE_HEIGHT / 2.0,
+            gap_y - self.pipe_gap / 2.0 - PIPE_HEIGHT / 2.0,
+        ]
+    }
+
+    /// Speed multiplier after `gaps_passed` gaps.
+    pub fn speed_multiplier_for(&self, gaps_passed: u32) -> f32 {
+        (1.0 + self.speed_increase_rate * gaps_passed as f32).min(self.max_speed_multiplier)
+    }
+
+    pub fn spawn_interval(&self) -> Duration {
+        Duration::from_secs_f32(self.pipe_spawn_interval)
+    }
+}
+
+/// Fixed step between gameplay ticks, identical to `Time::<Fixed>::from_hz(TICK_RATE)`.
+pub fn tick_duration() -> Duration {
+    Duration::from_secs_f64(1.0 / TICK_RATE)
+}
+
+/// Draws the next gap centre from the pipe generator.
+pub fn next_gap_y(rng: &mut StdRng) -> f32 {
+    rng.gen_range(-GAP_RANGE..GAP_RANGE)
+}
+
+/// Whether a pipe that moved from `previous_x` to `x` this tick went past the bird. Every
+/// pipe crosses exactly once, however far it moves in a tick.
+pub fn passes_bird(previous_x: f32, x: f32) -> bool {
+    previous_x > BIRD_X && BIRD_X >= x
+}
+
+/// AABB test between the bird and one pipe centre.
+pub fn hits_pipe(bird_x: f32, bird_y: f32, pipe_x: f32, pipe_y: f32) -> bool {
+    (bird_x - pipe_x).abs() < 45.0 && (bird_y - pipe_y).abs() < 215.0
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DeathCause {
+    Ground,
+    Ceiling,
+    /// Hit the pipe above the gap.
+    TopPipe,
+    /// Hit the pipe below the gap.
+    BottomPipe,
+}
+
+impl DeathCause {
+    pub fn label(self) -> &'static str {
+        match self {
+            DeathCause::Ground => "ground",
+            DeathCause::Ceiling => "ceiling",
+            DeathCause::TopPipe => "top pipe",
+            DeathCause::BottomPipe => "bottom pipe",
+        }
+    }
+}
+
+/// Ground and ceiling checks; pipes are handled separately.
+pub fn bounds_death(bird_y: f32) -> Option<DeathCause> {
+    if bird_y < GROUND_HEIGHT + GROUND_THICKNESS / 2.0 {
+        Some(DeathCause::Ground)
+    } else if bird_y > CEILING_HEIGHT {
+        Some(DeathCause::Ceiling)
+    } else {
+        None
+    }
+}
+
+/// What an agent sees on a single tick, taken before the jump input is applied.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Observation {
+    pub bird_y: f32,
+    pub bird_velocity: f32,
+    /// Horizontal distance from the bird to the centre of the next gap.
+    pub gap_dx: f32,
+    /// Vertical centre of the next gap.
+    pub gap_y: f32,
+    pub gap_size: f32,
+    pub speed_multiplier: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BirdState {
+    pub y: f32,
+    pub velocity: f32,
+}
+
+impl BirdState {
+    pub const START: BirdState = BirdState {
+        y: 0.0,
+        velocity: 0.0,
+    };
+
+    /// `bird_jump` followed by `bird_movement`.
+    pub fn step(self, jump: bool, config: &GameConfig, dt: f32) -> Self {
+        let mut velocity = if jump {
+            config.jump_velocity
+        } else {
+            self.velocity
+        };
+        velocity += config.gravity * dt;
+        Self {
+            y: self.y + velocity * dt,
+            velocity,
+        }
+    }
+}
+
+/// A top/bottom pipe pair sharing one gap.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PipePair {
+    pub x: f32,
+    pub gap_y: f32,
+}
+
+/// Result of a single tick.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StepEvents {
+    pub scored: bool,
+    pub death: Option<DeathCause>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Simulation {
+    seed: u64,
+    config: GameConfig,
+    rng: StdRng,
+    pub bird: BirdState,
+    pub pipes: Vec<PipePair>,
+    spawn_elapsed: Duration,
+    /// How far the pipes moved on the last tick.
+    pipe_shift: f32,
+    pub speed_multiplier: f32,
+    /// Gaps the bird has flown through; this is the displayed score.
+    pub score: u32,
+    pub tick: u32,
+    pub death: Option<DeathCause>,
+}
+
+impl Simulation {
+    pub fn new(seed: u64) -> Self {
+        Self::with_config(seed, GameConfig::default())
+    }
+
+    pub fn with_config(seed: u64, config: GameConfig) -> Self {
+        Self {
+            seed,
+            config,
+            rng: StdRng::seed_from_u64(seed),
+            bird: BirdState::START,
+            pipes: Vec::new(),
+            spawn_elapsed: Duration::ZERO,
+            pipe_shift: 0.0,
+            speed_multiplier: 1.0,
+            score: 0,
+            tick: 0,
+            death: None,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn config(&self) -> &GameConfig {
+        &self.config
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.death.is_some()
+    }
+
+    /// Advances one fixed tick. Does nothing once the bird has died.
+    pub fn step(&mut self, jump: bool) -> StepEvents {
+        if self.is_over() {
+            return StepEvents::default();
+        }
+
+        self.bird = self
+            .bird
+            .step(jump, &self.config, tick_duration().as_secs_f32());
+        self.advance_course();
+        let death = self.collision(self.bird.y);
+        let scored = self.update_score();
+
+        self.death = death;
+        self.tick += 1;
+        StepEvents { scored, death }
+    }
+
+    /// `pipe_movement` and `spawn_pipes`. The course does not depend on the bird's
+    /// inputs, only on how many gaps it has scored.
+    pub fn advance_course(&mut self) {
+        let delta = tick_duration();
+        let dt = delta.as_secs_f32();
+
+        self.pipe_shift = -self.config.pipe_speed * dt * self.speed_multiplier;
+        for pipe in &mut self.pipes {
+            pipe.x += self.pipe_shift;
+        }
+        self.pipes.retain(|pipe| pipe.x >= PIPE_DESPAWN_X);
+
+        self.spawn_elapsed += delta;
+        let interval = self.config.spawn_interval();
+        if self.spawn_elapsed >= interval {
+            self.spawn_elapsed -= interval;
+            let gap_y = next_gap_y(&mut self.rng);
+            self.pipes.push(PipePair {
+                x: PIPE_SPAWN_X,
+                gap_y,
+            });
+        }
+    }
+
+    /// `check_collisions` for a bird at height `bird_y` against the current course.
+    pub fn collision(&self, bird_y: f32) -> Option<DeathCause> {
+        bounds_death(bird_y).or_else(|| {
+            self.pipes.iter().find_map(|pipe| {
+                let [top, bottom] = self.config.pipe_centres(pipe.gap_y);
+                if hits_pipe(BIRD_X, bird_y, pipe.x, top) {
+                    Some(DeathCause::TopPipe)
+                } else if hits_pipe(BIRD_X, bird_y, pipe.x, bottom) {
+                    Some(DeathCause::BottomPipe)
+                } else {
+                    None
+                }
+            })
+        })
+    }
+
+    /// `update_score`. Returns whether a gap was passed this tick.
+    pub fn update_score(&mut self) -> bool {
+        let mut scored = false;
+        for pipe in &self.pipes {
+            if passes_bird(pipe.x - self.pipe_shift, pipe.x) {
+                self.score += 1;
+                self.speed_multiplier = self.config.speed_multiplier_for(self.score);
+                scored = true;
+            }
+        }
+        scored
+    }
+
+    /// Observation against the nearest gap still ahead of the bird.
+    pub fn observation(&self) -> Observation {
+        let next_gap = self
+            .pipes
+            .iter()
+            .filter(|pipe| pipe.x + PIPE_WIDTH / 2.0 > BIRD_X)
+            .min_by(|a, b| a.x.total_cmp(&b.x));
+
+        let (gap_dx, gap_y) = match next_gap {
+            Some(pipe) => (pipe.x - BIRD_X, pipe.gap_y),
+            None => (f32::MAX, 0.0),
+        };
+
+        Observation {
+            bird_y: self.bird.y,
+            bird_velocity: self.bird.velocity,
+            gap_dx,
+            gap_y,
+            gap_size: self.config.pipe_gap,
+            speed_multiplier: self.speed_multiplier,
+        }
+    }
+}
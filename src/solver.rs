@@ -0,0 +1,263 @@
+//! Breadth-first search over tick-level jump decisions.
+//!
+//! The course only depends on the seed and on how many gaps have been scored, which is
+//! the same for every surviving path, so a single [`Simulation`] drives the pipes while
+//! the search tracks bird states. States whose velocity matches exactly and whose height
+//! falls in the same `resolution` bucket are merged; that pruning is the only thing that
+//! can make the search miss a solution. Without a resolution only identical states merge,
+//! so the search covers every schedule and running out of states proves the seed
+//! impossible, at the cost of far larger layers.
+
+use std::collections::HashSet;
+
+use crate::replay::Replay;
+use crate::sim::{tick_duration, BirdState, GameConfig, Simulation, TICK_RATE};
+
+#[derive(Clone, Copy, Debug)]
+pub struct SolverOptions {
+    /// Number of gaps the schedule must fly through.
+    pub gaps: u32,
+    /// Height bucket, in world units, used to merge near-identical states; `None` merges
+    /// only states with identical bits, for an exact search.
+    pub resolution: Option<f32>,
+    /// Give up after this many ticks without reaching `gaps`.
+    pub max_ticks: u32,
+    /// Give up once a layer holds more states than this.
+    pub max_states: usize,
+}
+
+/// A resolution that can't bucket heights: zero, negative, NaN or infinite.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InvalidResolution(pub f32);
+
+impl std::fmt::Display for InvalidResolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "resolution {} is not a positive, finite number of world units",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for InvalidResolution {}
+
+impl SolverOptions {
+    pub fn new(gaps: u32, config: &GameConfig) -> Self {
+        // Allow the first gap's flight time plus a spawn interval per gap, with slack
+        let max_ticks = ((gaps + 5) as f64 * config.pipe_spawn_interval as f64 * TICK_RATE) as u32;
+        Self {
+            gaps,
+            resolution: Some(1.0),
+            max_ticks,
+            max_states: 1 << 22,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), InvalidResolution> {
+        match self.resolution {
+            Some(resolution) if !(resolution.is_finite() && resolution > 0.0) => {
+                Err(InvalidResolution(resolution))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Identifies states the search treats as the same.
+    fn key(&self, bird: BirdState) -> (u32, u64) {
+        let height = match self.resolution {
+            // In f64 so even the finest resolution can't overflow the bucket number
+            Some(resolution) => (bird.y as f64 / resolution as f64).round().to_bits(),
+            None => bird.y.to_bits() as u64,
+        };
+        (bird.velocity.to_bits(), height)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Solution {
+    /// A schedule surviving the requested number of gaps.
+    Solved(Replay),
+    /// Every state the search kept died on `tick`, before scoring gap number `gap`
+    /// (1-based). States are merged into `resolution`-sized height buckets, so this is not
+    /// a proof that the seed is impossible; a finer resolution may still find a schedule.
+    NotFound {
+        gap: u32,
+        tick: u32,
+        resolution: f32,
+    },
+    /// The exact search ran out of states: every schedule dies on `tick`, before scoring
+    /// gap number `gap` (1-based).
+    Impossible { gap: u32, tick: u32 },
+    /// `max_ticks` ran out with states still alive but too few gaps scored.
+    Exhausted { score: u32 },
+    /// The layer for `tick` outgrew `max_states`.
+    TooManyStates { tick: u32 },
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SolverStats {
+    pub ticks: u32,
+    pub expanded: u64,
+    pub peak_states: usize,
+}
+
+/// Parent index shifted left by one, with the jump decision in the low bit. Only the
+/// current layer keeps full bird states; earlier layers just keep these links.
+type Link = u32;
+
+pub fn solve(
+    seed: u64,
+    config: GameConfig,
+    options: &SolverOptions,
+) -> Result<(Solution, SolverStats), InvalidResolution> {
+    options.validate()?;
+    let dt = tick_duration().as_secs_f32();
+    let mut course = Simulation::with_config(seed, config);
+    let mut stats = SolverStats::default();
+
+    let mut frontier = vec![BirdState::START];
+    let mut history: Vec<Vec<Link>> = Vec::new();
+    let mut seen = HashSet::new();
+
+    while course.tick < options.max_ticks {
+        course.advance_course();
+        seen.clear();
+
+        let mut next = Vec::with_capacity(frontier.len() * 2);
+        let mut links = Vec::with_capacity(frontier.len() * 2);
+        for (index, state) in frontier.iter().enumerate() {
+            for jump in [false, true] {
+                stats.expanded += 1;
+                let bird = state.step(jump, &config, dt);
+                if course.collision(bird.y).is_some() {
+                    continue;
+                }
+                if seen.insert(options.key(bird)) {
+                    next.push(bird);
+                    links.push((index as u32) << 1 | jump as u32);
+                }
+            }
+        }
+
+        let score_before = course.score;
+        course.update_score();
+        course.tick += 1;
+        stats.ticks = course.tick;
+        stats.peak_states = stats.peak_states.max(next.len());
+
+        if next.is_empty() {
+            let (gap, tick) = (score_before + 1, course.tick);
+            let solution = match options.resolution {
+                Some(resolution) => Solution::NotFound {
+                    gap,
+                    tick,
+                    resolution,
+                },
+                None => Solution::Impossible { gap, tick },
+            };
+            return Ok((solution, stats));
+        }
+        if next.len() > options.max_states {
+            return Ok((Solution::TooManyStates { tick: course.tick }, stats));
+        }
+        frontier = next;
+        history.push(links);
+
+        if course.score >= options.gaps {
+            let mut replay = Replay::from_inputs(seed, config, &backtrack(&history));
+            replay.final_score = Some(course.score);
+            return Ok((Solution::Solved(replay), stats));
+        }
+    }
+
+    let solution = Solution::Exhausted {
+        score: course.score,
+    };
+    Ok((solution, stats))
+}
+
+/// Follows parent links from the first state of the last layer back to the start.
+fn backtrack(history: &[Vec<Link>]) -> Vec<bool> {
+    let mut inputs = Vec::with_capacity(history.len());
+    let mut index = 0;
+    for links in history.iter().rev() {
+        let link = links[index];
+        inputs.push(link & 1 == 1);
+        index = (link >> 1) as usize;
+    }
+    inputs.reverse();
+    inputs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pipes that cross the screen in a fraction of a second, keeping the searches short.
+    fn fast_config() -> GameConfig {
+        GameConfig {
+            pipe_speed: 2000.0,
+            ..GameConfig::default()
+        }
+    }
+
+    #[test]
+    fn solutions_replay_to_the_requested_score() {
+        let config = fast_config();
+        let options = SolverOptions::new(2, &config);
+        let (solution, _) = solve(3, config, &options).unwrap();
+        let Solution::Solved(replay) = solution else {
+            panic!("expected a schedule, got {solution:?}");
+        };
+        let sim = replay.simulate();
+        assert!(!sim.is_over());
+        assert!(sim.score >= 2);
+        assert_eq!(replay.final_score, Some(sim.score));
+    }
+
+    #[test]
+    fn invalid_resolutions_are_rejected() {
+        let config = GameConfig::default();
+        for resolution in [0.0, -1.0, f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            let options = SolverOptions {
+                resolution: Some(resolution),
+                ..SolverOptions::new(1, &config)
+            };
+            assert!(options.validate().is_err(), "{resolution}");
+            assert!(solve(1, config, &options).is_err(), "{resolution}");
+        }
+    }
+
+    #[test]
+    fn fine_resolutions_keep_heights_apart() {
+        let options = SolverOptions {
+            resolution: Some(f32::MIN_POSITIVE),
+            ..SolverOptions::new(1, &GameConfig::default())
+        };
+        let low = BirdState {
+            y: 100.0,
+            velocity: 0.0,
+        };
+        let high = BirdState { y: 200.0, ..low };
+        assert_ne!(options.key(low), options.key(high));
+    }
+
+    #[test]
+    fn exact_search_proves_a_closed_course_impossible() {
+        let config = GameConfig {
+            pipe_gap: 1.0,
+            pipe_spawn_interval: 0.25,
+            ..fast_config()
+        };
+        let options = SolverOptions {
+            resolution: None,
+            ..SolverOptions::new(1, &config)
+        };
+        let (solution, _) = solve(3, config, &options).unwrap();
+        assert!(
+            matches!(solution, Solution::Impossible { gap: 1, .. }),
+            "{solution:?}"
+        );
+    }
+}
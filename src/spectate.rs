@@ -0,0 +1,145 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::state::state::StateTransitionEvent;
+use rlappy_bev::feed::{BirdFrame, FeedMessage, FeedServer, PipeFrame, FEED_VERSION};
+use rlappy_bev::sim::{BIRD_X, PIPE_WIDTH};
+use std::net::IpAddr;
+
+use crate::{ActiveConfig, Bird, Dead, GameDifficulty, GameState, Pipe, Player, PlayerScore};
+
+/// Pipes this far past either window edge are left out of `tick` messages.
+const VISIBLE_HALF_WIDTH: f32 = 400.0 + PIPE_WIDTH / 2.0;
+
+/// Spectator feed started with `--spectate <port>`.
+#[derive(Resource)]
+pub struct Spectators {
+    server: FeedServer,
+    tick: u32,
+    /// Last score sent for each player, so `score` events fire once per gap.
+    scores: Vec<u32>,
+}
+
+impl Spectators {
+    pub fn bind(ip: IpAddr, port: u16) -> std::io::Result<Self> {
+        Ok(Self {
+            server: FeedServer::bind(ip, port)?,
+            tick: 0,
+            scores: Vec::new(),
+        })
+    }
+}
+
+pub fn accept_spectators(mut spectators: ResMut<Spectators>, state: Res<State<GameState>>) {
+    let hello = FeedMessage::Hello {
+        version: FEED_VERSION,
+        state: format!("{:?}", state.get()),
+    };
+    spectators.server.accept(&hello);
+}
+
+pub fn publish_transitions(
+    mut transitions: EventReader<StateTransitionEvent<GameState>>,
+    mut spectators: ResMut<Spectators>,
+) {
+    for transition in transitions.read() {
+        let (Some(from), Some(to)) = (transition.exited, transition.entered) else {
+            continue;
+        };
+        // Resuming from pause continues the run; anything else into Playing starts one
+        if to == GameState::Playing && from != GameState::Paused {
+            spectators.tick = 0;
+            spectators.scores.clear();
+        }
+        spectators.server.publish(&FeedMessage::State {
+            from: format!("{from:?}"),
+            to: format!("{to:?}"),
+        });
+    }
+}
+
+/// Birds and on-screen gaps as the feed and the remote-control `get_state` report them.
+#[derive(SystemParam)]
+pub struct Scene<'w, 's> {
+    config: Res<'w, ActiveConfig>,
+    birds: Query<
+        'w,
+        's,
+        (
+            &'static Player,
+            &'static Bird,
+            &'static Transform,
+            &'static PlayerScore,
+            Has<Dead>,
+        ),
+    >,
+    pipes: Query<'w, 's, (&'static Transform, &'static Pipe), Without<Player>>,
+}
+
+impl Scene<'_, '_> {
+    pub fn birds(&self) -> Vec<BirdFrame> {
+        let mut frames: Vec<BirdFrame> = self
+            .birds
+            .iter()
+            .map(|(player, bird, transform, score, dead)| BirdFrame {
+                player: player.0,
+                x: BIRD_X,
+                y: transform.translation.y,
+                velocity: bird.velocity,
+                alive: !dead,
+                score: score.0 / 2,
+            })
+            .collect();
+        frames.sort_by_key(|frame| frame.player);
+        frames
+    }
+
+    pub fn pipes(&self) -> Vec<PipeFrame> {
+        // Each gap is drawn as a top and a bottom pipe sharing x and gap_y
+        let mut gaps: Vec<PipeFrame> = Vec::new();
+        for (transform, pipe) in self.pipes.iter() {
+            let x = transform.translation.x;
+            if x.abs() > VISIBLE_HALF_WIDTH || gaps.iter().any(|gap| gap.x == x) {
+                continue;
+            }
+            gaps.push(PipeFrame {
+                x,
+                gap_y: pipe.gap_y,
+                gap: self.config.0.pipe_gap,
+            });
+        }
+        gaps.sort_by(|a, b| a.x.total_cmp(&b.x));
+        gaps
+    }
+}
+
+/// Sends the scene after every fixed tick of play, preceded by any `score` events.
+pub fn publish_tick(
+    mut spectators: ResMut<Spectators>,
+    state: Res<State<GameState>>,
+    difficulty: Res<GameDifficulty>,
+    scene: Scene,
+) {
+    let birds = scene.birds();
+    for frame in &birds {
+        if spectators.scores.len() <= frame.player {
+            spectators.scores.resize(frame.player + 1, 0);
+        }
+        if frame.score > spectators.scores[frame.player] {
+            spectators.scores[frame.player] = frame.score;
+            spectators.server.publish(&FeedMessage::Score {
+                player: frame.player,
+                score: frame.score,
+            });
+        }
+    }
+
+    let tick = spectators.tick;
+    spectators.server.publish(&FeedMessage::Tick {
+        tick,
+        state: format!("{:?}", state.get()),
+        speed_multiplier: difficulty.speed_multiplier,
+        birds,
+        pipes: scene.pipes(),
+    });
+    spectators.tick += 1;
+}
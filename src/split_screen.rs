@@ -0,0 +1,127 @@
+use bevy::prelude::*;
+use bevy::render::camera::{ScalingMode, Viewport};
+use bevy::render::view::RenderLayers;
+use bevy::window::PrimaryWindow;
+use rlappy_bev::sim::{WINDOW_HEIGHT, WINDOW_WIDTH};
+
+use crate::{Bird, GameMode, MainCamera, Player, PlayerScoreText, MAX_PLAYERS};
+
+/// World camera following one player's half of the window.
+#[derive(Component)]
+pub struct SplitCamera(usize);
+
+/// Render layer holding only player `player`'s bird. Pipes and ground stay on layer 0,
+/// which every split camera draws, so both halves show the same course.
+fn player_layer(player: usize) -> usize {
+    player + 1
+}
+
+/// Layer nothing is drawn on; the main camera keeps it while split so it only renders UI.
+const UI_ONLY_LAYER: usize = MAX_PLAYERS + 1;
+
+pub fn split_screen_active(mode: Res<GameMode>) -> bool {
+    *mode == GameMode::SplitScreen
+}
+
+/// Gives each player a camera over their half of the window and moves their bird and HUD
+/// onto it. The main camera stays on top to draw shared UI such as the game over screen.
+pub fn setup_split_screen(
+    mut commands: Commands,
+    mut main_camera: Query<(Entity, &mut Camera), With<MainCamera>>,
+    bird_query: Query<(Entity, &Player), With<Bird>>,
+    mut text_query: Query<(Entity, &PlayerScoreText, &mut Style)>,
+) {
+    let mut cameras = [Entity::PLACEHOLDER; MAX_PLAYERS];
+    for (player, camera) in cameras.iter_mut().enumerate() {
+        *camera = commands
+            .spawn((
+                Camera2dBundle {
+                    camera: Camera {
+                        // Render before the main camera, and only the first one clears,
+                        // since clearing wipes the whole window rather than the viewport
+                        order: player as isize - MAX_PLAYERS as isize,
+                        clear_color: if player == 0 {
+                            ClearColorConfig::Default
+                        } else {
+                            ClearColorConfig::None
+                        },
+                        ..default()
+                    },
+                    projection: OrthographicProjection {
+                        scaling_mode: ScalingMode::AutoMin {
+                            min_width: WINDOW_WIDTH,
+                            min_height: WINDOW_HEIGHT,
+                        },
+                        ..Camera2dBundle::default().projection
+                    },
+                    ..default()
+                },
+                RenderLayers::from_layers(&[0, player_layer(player)]),
+                SplitCamera(player),
+            ))
+            .id();
+    }
+
+    for (entity, mut camera) in main_camera.iter_mut() {
+        camera.clear_color = ClearColorConfig::None;
+        commands
+            .entity(entity)
+            .insert(RenderLayers::layer(UI_ONLY_LAYER));
+    }
+
+    for (entity, player) in bird_query.iter() {
+        commands
+            .entity(entity)
+            .insert(RenderLayers::layer(player_layer(player.0)));
+    }
+    for (entity, text, mut style) in text_query.iter_mut() {
+        commands
+            .entity(entity)
+            .insert(TargetCamera(cameras[text.0]));
+        // Each HUD sits in the top-left corner of its own viewport
+        style.left = Val::Px(10.0);
+        style.right = Val::Auto;
+    }
+}
+
+pub fn teardown_split_screen(
+    mut commands: Commands,
+    split_cameras: Query<Entity, With<SplitCamera>>,
+    mut main_camera: Query<(Entity, &mut Camera), With<MainCamera>>,
+) {
+    for entity in split_cameras.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    for (entity, mut camera) in main_camera.iter_mut() {
+        camera.clear_color = ClearColorConfig::Default;
+        commands.entity(entity).remove::<RenderLayers>();
+    }
+}
+
+/// Keeps the player viewports at half the window each, side by side, across resizes.
+pub fn fit_split_viewports(
+    window: Query<&Window, With<PrimaryWindow>>,
+    mut cameras: Query<(&mut Camera, &SplitCamera)>,
+) {
+    let Ok(window) = window.get_single() else {
+        return;
+    };
+    let size = window.physical_size();
+    let half_width = size.x / MAX_PLAYERS as u32;
+
+    for (mut camera, split) in cameras.iter_mut() {
+        let viewport = Viewport {
+            physical_position: UVec2::new(half_width * split.0 as u32, 0),
+            physical_size: UVec2::new(half_width.max(1), size.y.max(1)),
+            ..default()
+        };
+        if camera
+            .viewport
+            .as_ref()
+            .map(|current| (current.physical_position, current.physical_size))
+            != Some((viewport.physical_position, viewport.physical_size))
+        {
+            camera.viewport = Some(viewport);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+use rlappy_bev::leaderboard::{self, Accepted, Submission};
+use rlappy_bev::replay::Replay;
+use rlappy_bev::sim::GameConfig;
+use std::io;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Mutex;
+
+use crate::lan::PlayerName;
+use crate::share::RunInputs;
+use crate::tas::Tas;
+use crate::{ActiveConfig, GameMode, GameOverUi, GameSeed, Score};
+
+type Outcome = io::Result<Result<Accepted, String>>;
+
+/// Leaderboard server that solo runs are submitted to, set with `--leaderboard`.
+#[derive(Resource)]
+pub struct Leaderboard {
+    addr: String,
+    /// Answer to the submission in flight. The mutex only makes the receiver `Sync`.
+    pending: Mutex<Option<Receiver<Outcome>>>,
+}
+
+impl Leaderboard {
+    pub fn new(addr: String) -> Self {
+        Self {
+            addr,
+            pending: Mutex::new(None),
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct SubmissionText;
+
+/// Sends the finished run with its inputs, so the server can check the score by replaying it.
+pub fn submit_run(
+    mut commands: Commands,
+    board: Res<Leaderboard>,
+    mode: Res<GameMode>,
+    seed: Res<GameSeed>,
+    config: Res<ActiveConfig>,
+    score: Res<Score>,
+    inputs: Res<RunInputs>,
+    name: Res<PlayerName>,
+    tas: Option<Res<Tas>>,
+) {
+    let message = if *mode != GameMode::Solo {
+        "Only solo runs go on the leaderboard"
+    } else if tas.is_some() {
+        "TAS runs are not submitted"
+    } else if config.0 != GameConfig::default() {
+        "Custom config: not submitted"
+    } else {
+        let mut replay = Replay::from_inputs(seed.0, config.0, &inputs.0);
+        replay.final_score = Some(score.0 / 2);
+        let submission = Submission {
+            name: name.0.clone(),
+            replay,
+        };
+        let addr = board.addr.clone();
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = sender.send(leaderboard::submit(&addr, &submission));
+        });
+        *board.pending.lock().unwrap_or_else(|err| err.into_inner()) = Some(receiver);
+        "Submitting score..."
+    };
+
+    commands.spawn((
+        TextBundle::from_section(
+            message,
+            TextStyle {
+                font_size: 20.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(500.0),
+            left: Val::Px(200.0),
+            ..default()
+        }),
+        SubmissionText,
+        GameOverUi,
+    ));
+}
+
+/// Shows the server's answer once it arrives.
+pub fn poll_submission(board: Res<Leaderboard>, mut texts: Query<&mut Text, With<SubmissionText>>) {
+    let mut pending = board.pending.lock().unwrap_or_else(|err| err.into_inner());
+    let Some(receiver) = pending.as_ref() else {
+        return;
+    };
+    let message = match receiver.try_recv() {
+        Ok(Ok(Ok(accepted))) => format!("Leaderboard rank #{}", accepted.rank),
+        Ok(Ok(Err(reason))) => format!("Rejected: {reason}"),
+        Ok(Err(err)) => format!("Leaderboard unavailable: {err}"),
+        Err(TryRecvError::Empty) => return,
+        Err(TryRecvError::Disconnected) => "Leaderboard unavailable".to_string(),
+    };
+    eprintln!("{message}");
+    *pending = None;
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = message.clone();
+    }
+}
@@ -0,0 +1,149 @@
+//! Sound synthesis for the game's effects and music, so no audio files have to ship with it.
+//!
+//! Everything renders to mono `f32` samples in -1..=1 at [`SAMPLE_RATE`]; [`wav_bytes`]
+//! packs them as 16-bit PCM for players that decode WAV, or for listening offline.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::f32::consts::TAU;
+use std::io;
+use std::path::Path;
+
+pub const SAMPLE_RATE: u32 = 44_100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Effect {
+    /// Short upward chirp.
+    Flap,
+    /// Two rising notes.
+    Score,
+    /// Burst of noise over a low thump, the moment a bird crashes.
+    Hit,
+    /// Long falling tone once the run is over.
+    Die,
+    /// Menu button tick.
+    Click,
+}
+
+impl Effect {
+    pub const ALL: [Effect; 5] = [
+        Effect::Flap,
+        Effect::Score,
+        Effect::Hit,
+        Effect::Die,
+        Effect::Click,
+    ];
+
+    pub fn render(self) -> Vec<f32> {
+        match self {
+            Effect::Flap => sweep(Wave::Square, 420.0, 780.0, 0.09, 0.25),
+            Effect::Score => {
+                let mut samples = sweep(Wave::Square, 988.0, 988.0, 0.07, 0.22);
+                samples.extend(sweep(Wave::Square, 1319.0, 1319.0, 0.16, 0.22));
+                samples
+            }
+            Effect::Hit => {
+                let thump = sweep(Wave::Sine, 140.0, 50.0, 0.18, 0.8);
+                mix(&thump, &noise(0.12, 0.5))
+            }
+            Effect::Die => sweep(Wave::Triangle, 660.0, 110.0, 0.6, 0.4),
+            Effect::Click => sweep(Wave::Square, 1800.0, 1500.0, 0.025, 0.15),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Wave {
+    Sine,
+    Square,
+    Triangle,
+}
+
+impl Wave {
+    /// Value at `phase` cycles, for a phase in 0..1.
+    pub fn at(self, phase: f32) -> f32 {
+        match self {
+            Wave::Sine => (phase * TAU).sin(),
+            Wave::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Wave::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+        }
+    }
+}
+
+pub(crate) fn sample_count(seconds: f32) -> usize {
+    (seconds * SAMPLE_RATE as f32) as usize
+}
+
+/// Fades in over the first few milliseconds, then decays linearly to silence, so notes
+/// don't click at either end.
+pub(crate) fn envelope(i: usize, len: usize) -> f32 {
+    let attack = sample_count(0.004).max(1);
+    let rise = (i as f32 / attack as f32).min(1.0);
+    rise * (1.0 - i as f32 / len as f32)
+}
+
+/// Tone gliding exponentially from `from` to `to` hertz.
+pub fn sweep(wave: Wave, from: f32, to: f32, seconds: f32, gain: f32) -> Vec<f32> {
+    let len = sample_count(seconds);
+    let mut phase = 0.0;
+    (0..len)
+        .map(|i| {
+            let t = i as f32 / len as f32;
+            let frequency = from * (to / from).powf(t);
+            phase = (phase + frequency / SAMPLE_RATE as f32).fract();
+            wave.at(phase) * envelope(i, len) * gain
+        })
+        .collect()
+}
+
+/// White noise, the same every time.
+pub fn noise(seconds: f32, gain: f32) -> Vec<f32> {
+    let len = sample_count(seconds);
+    let mut rng = StdRng::seed_from_u64(0);
+    (0..len)
+        .map(|i| rng.gen_range(-1.0..=1.0) * envelope(i, len) * gain)
+        .collect()
+}
+
+/// Sums two sounds, as long as the longer one, clipped to -1..=1.
+pub fn mix(a: &[f32], b: &[f32]) -> Vec<f32> {
+    (0..a.len().max(b.len()))
+        .map(|i| {
+            let sum = a.get(i).unwrap_or(&0.0) + b.get(i).unwrap_or(&0.0);
+            sum.clamp(-1.0, 1.0)
+        })
+        .collect()
+}
+
+/// A mono 16-bit PCM WAV file holding `samples`.
+pub fn wav_bytes(samples: &[f32]) -> Vec<u8> {
+    let data_len = samples.len() as u32 * 2;
+    let mut bytes = Vec::with_capacity(44 + data_len as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // mono
+    bytes.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    bytes.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    bytes
+}
+
+pub fn write_wav(path: impl AsRef<Path>, samples: &[f32]) -> io::Result<()> {
+    std::fs::write(path, wav_bytes(samples))
+}
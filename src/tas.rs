@@ -0,0 +1,420 @@
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rlappy_bev::replay::Replay;
+
+use crate::coop::Downed;
+use crate::share::RunInputs;
+use crate::{
+    ActiveConfig, Bird, Dead, GameDifficulty, GameEvent, GameMode, GameSeed, GameState,
+    PendingJump, Pipe, PipeRng, PipeSpawnTimer, Player, PlayerScore, PlayerScoreText, Score,
+    ScoreText,
+};
+
+/// Ticks shown on either side of the current one in the timeline strip.
+const TIMELINE_RADIUS: usize = 15;
+/// Ticks moved per key press while Shift is held.
+const FAST_STEP: usize = 10;
+
+/// Tool-assisted play: the per-tick jump timeline of the current run and the tick the
+/// game is at. Present only when the game is started with `--tas`.
+#[derive(Resource, Default)]
+pub struct Tas {
+    inputs: Vec<bool>,
+    tick: usize,
+    /// Set while `tas_controls` runs `FixedUpdate` by hand outside `GameState::Playing`.
+    stepping: bool,
+}
+
+impl Tas {
+    fn input_at(&self, tick: usize) -> bool {
+        self.inputs.get(tick).copied().unwrap_or(false)
+    }
+
+    fn toggle(&mut self, tick: usize) {
+        if self.inputs.len() <= tick {
+            self.inputs.resize(tick + 1, false);
+        }
+        self.inputs[tick] = !self.inputs[tick];
+    }
+}
+
+#[derive(Component)]
+pub struct TasText;
+
+/// Run condition letting the gameplay systems run for a single hand-stepped tick.
+pub fn stepping(tas: Option<Res<Tas>>) -> bool {
+    tas.is_some_and(|tas| tas.stepping)
+}
+
+pub fn begin_timeline(mut tas: ResMut<Tas>, mut commands: Commands) {
+    *tas = Tas::default();
+
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 20.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        }),
+        TasText,
+    ));
+}
+
+/// Writes the jump applied on this tick into the timeline. Runs before `bird_jump`.
+pub fn record_tick(mut tas: ResMut<Tas>, pending_jump: Res<PendingJump>) {
+    let tick = tas.tick;
+    if tick < tas.inputs.len() {
+        tas.inputs[tick] = pending_jump.0[0];
+    } else {
+        tas.inputs.push(pending_jump.0[0]);
+    }
+    tas.tick += 1;
+}
+
+/// Frame advance (`.`), rewind (`,`), jump toggle for the next tick (`J`) and replay
+/// saving (`F5`) while paused or after dying. Shift moves ten ticks at a time.
+pub fn tas_controls(world: &mut World) {
+    let keyboard = world.resource::<ButtonInput<KeyCode>>();
+    let steps = if keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        FAST_STEP
+    } else {
+        1
+    };
+    let advance = keyboard.just_pressed(KeyCode::Period);
+    let rewind = keyboard.just_pressed(KeyCode::Comma);
+    let toggle = keyboard.just_pressed(KeyCode::KeyJ);
+    let save = keyboard.just_pressed(KeyCode::F5);
+    let game_over = *world.resource::<State<GameState>>().get() == GameState::GameOver;
+
+    if toggle {
+        let mut tas = world.resource_mut::<Tas>();
+        let tick = tas.tick;
+        tas.toggle(tick);
+    }
+
+    if advance && !game_over {
+        advance_by(world, steps);
+    }
+
+    if rewind {
+        rewind_by(world, steps);
+        if game_over {
+            world
+                .resource_mut::<NextState<GameState>>()
+                .set(GameState::Paused);
+        }
+    }
+
+    if save {
+        save_replay(world);
+    }
+}
+
+/// Plays up to `steps` ticks, stopping early if the run ends.
+fn advance_by(world: &mut World, steps: usize) {
+    for _ in 0..steps {
+        step_tick(world);
+        if matches!(
+            world.resource::<NextState<GameState>>(),
+            NextState::Pending(GameState::GameOver)
+        ) {
+            break;
+        }
+    }
+}
+
+/// Goes back `steps` ticks by restarting the run and replaying the timeline up to there.
+fn rewind_by(world: &mut World, steps: usize) {
+    let target = world.resource::<Tas>().tick.saturating_sub(steps);
+    restart_run(world);
+    for _ in 0..target {
+        step_tick(world);
+    }
+    // The replayed ticks were heard the first time round
+    world.resource_mut::<Events<GameEvent>>().clear();
+}
+
+/// Runs the `FixedUpdate` schedule once with the timeline's input for the current tick.
+fn step_tick(world: &mut World) {
+    let input = {
+        let tas = world.resource::<Tas>();
+        tas.input_at(tas.tick)
+    };
+    world.resource_mut::<PendingJump>().0[0] = input;
+
+    // Mirror what the fixed main loop does: expose the fixed clock as the generic `Time`
+    let mut fixed = *world.resource::<Time<Fixed>>();
+    fixed.advance_by(fixed.timestep());
+    let previous_time = std::mem::replace(&mut *world.resource_mut::<Time>(), fixed.as_generic());
+
+    world.resource_mut::<Tas>().stepping = true;
+    world.run_schedule(FixedUpdate);
+    world.resource_mut::<Tas>().stepping = false;
+
+    *world.resource_mut::<Time>() = previous_time;
+}
+
+/// Puts the run back on tick zero with the same seed, keeping the timeline.
+fn restart_run(world: &mut World) {
+    let pipes: Vec<Entity> = world
+        .query_filtered::<Entity, With<Pipe>>()
+        .iter(world)
+        .collect();
+    for entity in pipes {
+        world.despawn(entity);
+    }
+
+    // Crashed birds fly again, with their own colour and no gaps to their name
+    let mode = *world.resource::<GameMode>();
+    let mut birds = world.query::<(
+        Entity,
+        &mut Transform,
+        &mut Bird,
+        &mut Sprite,
+        &Player,
+        &mut PlayerScore,
+    )>();
+    let mut crashed = Vec::new();
+    for (entity, mut transform, mut bird, mut sprite, player, mut score) in birds.iter_mut(world) {
+        transform.translation.y = 0.0;
+        bird.velocity = 0.0;
+        sprite.color = mode.tint(player.0);
+        score.0 = 0;
+        crashed.push(entity);
+    }
+    for entity in crashed {
+        world.entity_mut(entity).remove::<(Dead, Downed)>();
+    }
+
+    let seed = world.resource::<GameSeed>().0;
+    world.resource_mut::<PipeRng>().0 = StdRng::seed_from_u64(seed);
+    world.resource_mut::<PipeSpawnTimer>().0.reset();
+    world.resource_mut::<Score>().0 = 0;
+    *world.resource_mut::<GameDifficulty>() = GameDifficulty::default();
+    world.resource_mut::<Tas>().tick = 0;
+    world.resource_mut::<RunInputs>().0.clear();
+
+    let mut score_text = world.query_filtered::<&mut Text, With<ScoreText>>();
+    for mut text in score_text.iter_mut(world) {
+        text.sections[0].value = "Score: 0".to_string();
+    }
+    let mut player_text = world.query::<(&mut Text, &PlayerScoreText)>();
+    for (mut text, owner) in player_text.iter_mut(world) {
+        text.sections[0].value = format!("P{}: 0", owner.0 + 1);
+    }
+}
+
+/// The run so far as a replay, scored by playing it back.
+fn timeline_replay(world: &World) -> Replay {
+    let tas = world.resource::<Tas>();
+    let seed = world.resource::<GameSeed>().0;
+    let config = world.resource::<ActiveConfig>().0;
+
+    // Toggles past the current tick were never played
+    let played: Vec<bool> = (0..tas.tick).map(|tick| tas.input_at(tick)).collect();
+    let mut replay = Replay::from_inputs(seed, config, &played);
+    replay.final_score = Some(replay.simulate().score);
+    replay
+}
+
+fn save_replay(world: &mut World) {
+    let replay = timeline_replay(world);
+    let path = format!(
+        "./replay-{}.json",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    );
+    match replay.save(&path) {
+        Ok(()) => eprintln!("Replay saved to {path} ({} ticks)", replay.ticks),
+        Err(err) => eprintln!("Failed to save replay to {path}: {err}"),
+    }
+}
+
+pub fn update_tas_text(tas: Res<Tas>, mut query: Query<&mut Text, With<TasText>>) {
+    let start = tas.tick.saturating_sub(TIMELINE_RADIUS);
+    let strip: String = (start..tas.tick + TIMELINE_RADIUS + 1)
+        .map(|tick| {
+            let mark = if tas.input_at(tick) { '|' } else { '.' };
+            if tick == tas.tick {
+                format!("[{mark}]")
+            } else {
+                mark.to_string()
+            }
+        })
+        .collect();
+
+    for mut text in query.iter_mut() {
+        text.sections[0].value = format!(
+            "TAS tick {} / {}  next: {}\n{strip}\n. step  , back  J toggle jump  Shift x{FAST_STEP}  F5 save",
+            tas.tick,
+            tas.inputs.len(),
+            if tas.input_at(tas.tick) { "JUMP" } else { "-" },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{run_tick, BIRD_X, MAX_PLAYERS};
+    use bevy::state::app::StatesPlugin;
+    use rlappy_bev::sim::{GameConfig, TICK_RATE};
+
+    /// A run in `mode` paused at tick zero, with gameplay only running when stepped by hand.
+    fn app(mode: GameMode) -> App {
+        let config = GameConfig::default();
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin))
+            .insert_state(GameState::Paused)
+            .add_event::<GameEvent>()
+            .insert_resource(Time::<Fixed>::from_hz(TICK_RATE))
+            .insert_resource(ActiveConfig(config))
+            .insert_resource(PipeSpawnTimer(Timer::new(
+                config.spawn_interval(),
+                TimerMode::Repeating,
+            )))
+            .insert_resource(GameSeed(7))
+            .insert_resource(PipeRng(StdRng::seed_from_u64(7)))
+            .insert_resource(Score(0))
+            .insert_resource(mode)
+            .init_resource::<GameDifficulty>()
+            .init_resource::<PendingJump>()
+            .init_resource::<RunInputs>()
+            .init_resource::<Tas>()
+            .add_systems(FixedUpdate, run_tick().run_if(stepping));
+        for player in 0..mode.players().min(MAX_PLAYERS) {
+            app.world_mut().spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: mode.tint(player),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(BIRD_X, 0.0, 0.0),
+                    ..default()
+                },
+                Bird { velocity: 0.0 },
+                Player(player),
+                PlayerScore::default(),
+            ));
+        }
+        app.update();
+        app
+    }
+
+    /// Ticks between flaps that keep a bird hovering: a flap takes 1.2 s to fall back.
+    const FLAP_EVERY: usize = 72;
+
+    /// Player one flaps every `FLAP_EVERY` ticks until `until`.
+    fn hover(world: &mut World, until: usize) {
+        let mut tas = world.resource_mut::<Tas>();
+        for tick in (0..until).step_by(FLAP_EVERY) {
+            tas.toggle(tick);
+        }
+    }
+
+    /// Height, velocity, crashed, colour and score of each bird, player one first.
+    fn birds(world: &mut World) -> Vec<(f32, f32, bool, Color, u32)> {
+        let mut birds: Vec<_> = world
+            .query::<(&Player, &Transform, &Bird, Has<Dead>, &Sprite, &PlayerScore)>()
+            .iter(world)
+            .map(|(player, transform, bird, dead, sprite, score)| {
+                let state = (
+                    transform.translation.y,
+                    bird.velocity,
+                    dead,
+                    sprite.color,
+                    score.0,
+                );
+                (player.0, state)
+            })
+            .collect();
+        birds.sort_by_key(|(player, _)| *player);
+        birds.into_iter().map(|(_, state)| state).collect()
+    }
+
+    fn pipes(world: &mut World) -> Vec<(f32, f32)> {
+        world
+            .query_filtered::<&Transform, With<Pipe>>()
+            .iter(world)
+            .map(|transform| (transform.translation.x, transform.translation.y))
+            .collect()
+    }
+
+    #[test]
+    fn rewinding_returns_to_the_same_state() {
+        let mut app = app(GameMode::Solo);
+        let world = app.world_mut();
+        hover(world, 400);
+
+        advance_by(world, 150);
+        let tick = world.resource::<Tas>().tick;
+        let snapshot = (birds(world), pipes(world), world.resource::<Score>().0);
+        assert_eq!(tick, 150);
+        assert!(!snapshot.1.is_empty(), "no pipes spawned yet");
+
+        advance_by(world, 40);
+        world.resource_mut::<Events<GameEvent>>().clear();
+        rewind_by(world, 40);
+        assert_eq!(world.resource::<Tas>().tick, tick);
+        assert_eq!(world.resource::<RunInputs>().0.len(), tick);
+        assert_eq!(
+            (birds(world), pipes(world), world.resource::<Score>().0),
+            snapshot
+        );
+        assert!(
+            world.resource::<Events<GameEvent>>().is_empty(),
+            "replayed ticks made sounds"
+        );
+    }
+
+    #[test]
+    fn rewinding_past_a_crash_brings_the_bird_back() {
+        let mut app = app(GameMode::Versus);
+        let world = app.world_mut();
+        // Player one keeps flapping while player two falls to the ground
+        hover(world, 400);
+        advance_by(world, 100);
+        let crashed = world.resource::<Tas>().tick;
+        assert!(birds(world).iter().any(|bird| bird.2), "nobody crashed");
+
+        rewind_by(world, crashed);
+        assert_eq!(world.resource::<Tas>().tick, 0);
+        for (player, bird) in birds(world).into_iter().enumerate() {
+            assert_eq!(bird, (0.0, 0.0, false, GameMode::Versus.tint(player), 0));
+        }
+        assert_eq!(world.query::<&Downed>().iter(world).count(), 0);
+    }
+
+    #[test]
+    fn saved_replays_hold_only_the_ticks_played() {
+        let mut app = app(GameMode::Solo);
+        let world = app.world_mut();
+        hover(world, 400);
+        advance_by(world, 120);
+        rewind_by(world, 30);
+        // A toggle ahead of the current tick hasn't been played yet
+        let tick = world.resource::<Tas>().tick;
+        world.resource_mut::<Tas>().toggle(tick + 5);
+
+        let replay = timeline_replay(world);
+        assert_eq!(replay.ticks as usize, tick);
+        let expected: Vec<bool> = (0..tick).map(|tick| tick % FLAP_EVERY == 0).collect();
+        assert_eq!(replay.inputs(), expected);
+        assert_eq!(replay.seed, 7);
+        assert_eq!(replay.final_score, Some(replay.simulate().score));
+
+        let path = std::env::temp_dir().join(format!("rlappy-tas-{}.json", std::process::id()));
+        replay.save(&path).unwrap();
+        let loaded = Replay::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), replay);
+    }
+}
@@ -0,0 +1,309 @@
+//! Tournaments: a fixed list of seeds, a number of attempts on each, and a rule turning
+//! the attempts into points. Each player's scores go in their own results file, and
+//! [`standings`] ranks any number of those files against the same definition.
+
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::Path;
+
+use crate::sim::GameConfig;
+
+/// How a seed's attempts become points; a player's total is the sum over all seeds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scoring {
+    /// Best attempt on each seed.
+    #[default]
+    Best,
+    /// Every attempt counts.
+    Total,
+    /// Mean of the attempts played on each seed.
+    Average,
+}
+
+impl Scoring {
+    /// Points for one seed, or `None` before its first attempt.
+    pub fn points(self, attempts: &[u32]) -> Option<f64> {
+        let best = *attempts.iter().max()?;
+        let sum: u32 = attempts.iter().sum();
+        Some(match self {
+            Scoring::Best => best as f64,
+            Scoring::Total => sum as f64,
+            // Rounded to hundredths so tables and CSV stay readable
+            Scoring::Average => (sum as f64 * 100.0 / attempts.len() as f64).round() / 100.0,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Tournament {
+    pub name: String,
+    /// One round per seed, played in order.
+    pub seeds: Vec<u64>,
+    /// Attempts each player gets on every seed.
+    pub attempts: u32,
+    #[serde(default)]
+    pub scoring: Scoring,
+    /// Config every round is played with; defaults to the shipped game.
+    #[serde(default)]
+    pub config: GameConfig,
+}
+
+impl Tournament {
+    /// Reads a JSON definition and checks it has at least one seed and attempt, and a
+    /// playable config.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let tournament: Self =
+            serde_json::from_reader(io::BufReader::new(file)).map_err(io::Error::other)?;
+        if tournament.seeds.is_empty() || tournament.attempts == 0 {
+            return Err(io::Error::other(
+                "a tournament needs at least one seed and one attempt",
+            ));
+        }
+        tournament.config.validate().map_err(io::Error::other)?;
+        Ok(tournament)
+    }
+}
+
+/// One player's scores in a tournament, in gaps.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Results {
+    pub tournament: String,
+    pub player: String,
+    /// The tournament's seeds, so results can't be mixed up between editions.
+    pub seeds: Vec<u64>,
+    /// Attempt scores for each seed, in the order they were played.
+    pub scores: Vec<Vec<u32>>,
+}
+
+impl Results {
+    pub fn new(tournament: &Tournament, player: &str) -> Self {
+        Self {
+            tournament: tournament.name.clone(),
+            player: player.to_string(),
+            seeds: tournament.seeds.clone(),
+            scores: vec![Vec::new(); tournament.seeds.len()],
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        serde_json::from_reader(io::BufReader::new(file)).map_err(io::Error::other)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        std::fs::write(path, json + "\n")
+    }
+
+    pub fn belongs_to(&self, tournament: &Tournament) -> bool {
+        self.tournament == tournament.name
+            && self.seeds == tournament.seeds
+            && self.scores.len() == tournament.seeds.len()
+    }
+
+    /// Round (seed index) and 0-based attempt to play next, or `None` once all are done.
+    pub fn next_attempt(&self, tournament: &Tournament) -> Option<(usize, u32)> {
+        self.scores
+            .iter()
+            .enumerate()
+            .find(|(_, attempts)| (attempts.len() as u32) < tournament.attempts)
+            .map(|(round, attempts)| (round, attempts.len() as u32))
+    }
+
+    pub fn record(&mut self, round: usize, score: u32) {
+        if let Some(attempts) = self.scores.get_mut(round) {
+            attempts.push(score);
+        }
+    }
+
+    pub fn points(&self, tournament: &Tournament) -> f64 {
+        self.scores
+            .iter()
+            .filter_map(|attempts| tournament.scoring.points(attempts))
+            .sum()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Standing {
+    /// 1-based; players on equal points share a rank.
+    pub rank: usize,
+    pub player: String,
+    pub points: f64,
+    /// Points on each seed, `None` where the player hasn't played yet.
+    pub rounds: Vec<Option<f64>>,
+    pub attempts_played: u32,
+}
+
+/// Ranks players by points, best first. Fails if a results file is for another
+/// tournament or a player appears twice.
+pub fn standings(tournament: &Tournament, results: &[Results]) -> Result<Vec<Standing>, String> {
+    let mut standings = Vec::new();
+    for result in results {
+        if !result.belongs_to(tournament) {
+            return Err(format!(
+                "results of {} are for another tournament ({})",
+                result.player, result.tournament
+            ));
+        }
+        if standings
+            .iter()
+            .any(|standing: &Standing| standing.player == result.player)
+        {
+            return Err(format!("{} has more than one results file", result.player));
+        }
+        standings.push(Standing {
+            rank: 0,
+            player: result.player.clone(),
+            points: result.points(tournament),
+            rounds: result
+                .scores
+                .iter()
+                .map(|attempts| tournament.scoring.points(attempts))
+                .collect(),
+            attempts_played: result.scores.iter().map(|a| a.len() as u32).sum(),
+        });
+    }
+
+    standings.sort_by(|a, b| b.points.total_cmp(&a.points));
+    for i in 0..standings.len() {
+        standings[i].rank = match i {
+            0 => 1,
+            _ if standings[i].points == standings[i - 1].points => standings[i - 1].rank,
+            _ => i + 1,
+        };
+    }
+    Ok(standings)
+}
+
+/// Standings as CSV, one column per seed; unplayed rounds are left empty.
+pub fn standings_csv(tournament: &Tournament, standings: &[Standing]) -> String {
+    let mut csv = String::from("rank,player,points,attempts");
+    for seed in &tournament.seeds {
+        csv.push_str(&format!(",seed {seed}"));
+    }
+    csv.push('\n');
+    for standing in standings {
+        csv.push_str(&format!(
+            "{},{},{},{}",
+            standing.rank,
+            csv_field(&standing.player),
+            standing.points,
+            standing.attempts_played
+        ));
+        for round in &standing.rounds {
+            csv.push(',');
+            if let Some(points) = round {
+                csv.push_str(&points.to_string());
+            }
+        }
+        csv.push('\n');
+    }
+    csv
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tournament(scoring: Scoring) -> Tournament {
+        Tournament {
+            name: "cup".into(),
+            seeds: vec![10, 20],
+            attempts: 2,
+            scoring,
+            config: GameConfig::default(),
+        }
+    }
+
+    fn results(tournament: &Tournament, player: &str, scores: [&[u32]; 2]) -> Results {
+        let mut results = Results::new(tournament, player);
+        for (round, attempts) in scores.iter().enumerate() {
+            for &score in *attempts {
+                results.record(round, score);
+            }
+        }
+        results
+    }
+
+    #[test]
+    fn scoring_rules_turn_attempts_into_points() {
+        let attempts = [3, 8, 4];
+        assert_eq!(Scoring::Best.points(&attempts), Some(8.0));
+        assert_eq!(Scoring::Total.points(&attempts), Some(15.0));
+        assert_eq!(Scoring::Average.points(&[1, 2, 2]), Some(1.67));
+        for scoring in [Scoring::Best, Scoring::Total, Scoring::Average] {
+            assert_eq!(scoring.points(&[]), None);
+        }
+    }
+
+    #[test]
+    fn attempts_are_played_round_by_round() {
+        let tournament = tournament(Scoring::Best);
+        let mut results = Results::new(&tournament, "ada");
+        assert_eq!(results.next_attempt(&tournament), Some((0, 0)));
+        results.record(0, 5);
+        assert_eq!(results.next_attempt(&tournament), Some((0, 1)));
+        results.record(0, 7);
+        assert_eq!(results.next_attempt(&tournament), Some((1, 0)));
+        results.record(1, 2);
+        results.record(1, 1);
+        assert_eq!(results.next_attempt(&tournament), None);
+        assert_eq!(results.points(&tournament), 9.0);
+        assert!(results.belongs_to(&tournament));
+    }
+
+    #[test]
+    fn standings_rank_by_points_and_share_ties() {
+        let tournament = tournament(Scoring::Total);
+        let players = [
+            results(&tournament, "ada", [&[5, 5], &[1]]),
+            results(&tournament, "bo", [&[9, 4], &[]]),
+            results(&tournament, "cy", [&[11], &[0]]),
+        ];
+        let standings = standings(&tournament, &players).unwrap();
+        let table: Vec<(usize, &str, f64)> = standings
+            .iter()
+            .map(|standing| (standing.rank, standing.player.as_str(), standing.points))
+            .collect();
+        assert_eq!(table, [(1, "bo", 13.0), (2, "ada", 11.0), (2, "cy", 11.0)]);
+        assert_eq!(standings[0].rounds, [Some(13.0), None]);
+        assert_eq!(standings[0].attempts_played, 2);
+
+        let csv = standings_csv(&tournament, &standings);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "rank,player,points,attempts,seed 10,seed 20");
+        assert_eq!(lines[1], "1,bo,13,2,13,");
+    }
+
+    #[test]
+    fn standings_refuse_mixed_up_results() {
+        let tournament = tournament(Scoring::Best);
+        let other = Tournament {
+            seeds: vec![10, 30],
+            ..tournament.clone()
+        };
+        let stranger = results(&other, "ada", [&[1], &[2]]);
+        assert!(standings(&tournament, &[stranger]).is_err());
+
+        let twice = results(&tournament, "ada", [&[1], &[2]]);
+        assert!(standings(&tournament, &[twice.clone(), twice]).is_err());
+    }
+
+    #[test]
+    fn csv_quotes_awkward_names() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a, b"), "\"a, b\"");
+        assert_eq!(csv_field("the \"ace\""), "\"the \"\"ace\"\"\"");
+    }
+}
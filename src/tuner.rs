@@ -0,0 +1,346 @@
+//! Searches difficulty parameters so bot games hit a target score distribution.
+//!
+//! Every candidate is evaluated on the same seeds, so differences between candidates
+//! come from the parameters rather than from luck. The search samples the space at
+//! random first, then repeatedly perturbs the best candidate in a shrinking window.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use crate::batch::{percentile, run_batch, BatchOptions};
+use crate::sim::{GameConfig, InvalidConfig};
+
+/// A point of the score distribution the tuned config should produce.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Statistic {
+    Mean,
+    /// Nearest-rank percentile, 0 to 100.
+    Percentile(f64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Target {
+    pub statistic: Statistic,
+    pub score: f64,
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    /// Parses `median=25`, `mean=12.5` or `p90=60`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected <statistic>=<score>, got {s}"))?;
+        let score = value
+            .parse()
+            .map_err(|_| format!("invalid score in target {s}"))?;
+        let statistic = match name {
+            "mean" => Statistic::Mean,
+            "median" => Statistic::Percentile(50.0),
+            _ => {
+                let p: f64 = name
+                    .strip_prefix('p')
+                    .and_then(|p| p.parse().ok())
+                    .filter(|p| (0.0..=100.0).contains(p))
+                    .ok_or_else(|| format!("unknown statistic {name}, use mean, median or pNN"))?;
+                Statistic::Percentile(p)
+            }
+        };
+        Ok(Self { statistic, score })
+    }
+}
+
+impl Target {
+    pub fn label(&self) -> String {
+        match self.statistic {
+            Statistic::Mean => "mean".to_string(),
+            Statistic::Percentile(50.0) => "median".to_string(),
+            Statistic::Percentile(p) => format!("p{p}"),
+        }
+    }
+
+    /// Value of this statistic for already sorted scores.
+    pub fn measure(&self, sorted: &[u32]) -> f64 {
+        if sorted.is_empty() {
+            return 0.0;
+        }
+        match self.statistic {
+            Statistic::Mean => {
+                sorted.iter().map(|&score| score as f64).sum::<f64>() / sorted.len() as f64
+            }
+            Statistic::Percentile(p) => percentile(sorted, p) as f64,
+        }
+    }
+}
+
+/// Ranges the tuner may move each parameter within.
+#[derive(Clone, Debug)]
+pub struct TuneSpace {
+    pub pipe_gap: RangeInclusive<f32>,
+    pub speed_increase_rate: RangeInclusive<f32>,
+    pub max_speed_multiplier: RangeInclusive<f32>,
+}
+
+impl Default for TuneSpace {
+    fn default() -> Self {
+        Self {
+            pipe_gap: 120.0..=280.0,
+            speed_increase_rate: 0.0..=0.15,
+            max_speed_multiplier: 1.0..=3.5,
+        }
+    }
+}
+
+impl TuneSpace {
+    /// Checks that no range is empty and that the configs at either end of the space,
+    /// built on `base`, are valid. Every field's check is a plain bound, so the configs in
+    /// between are valid too.
+    pub fn validate(&self, base: &GameConfig) -> Result<(), InvalidConfig> {
+        for (name, range) in [
+            ("pipe_gap", &self.pipe_gap),
+            ("speed_increase_rate", &self.speed_increase_rate),
+            ("max_speed_multiplier", &self.max_speed_multiplier),
+        ] {
+            // Also catches a NaN end
+            if range.is_empty() {
+                return Err(InvalidConfig(name));
+            }
+        }
+        for end in [RangeInclusive::start, RangeInclusive::end] {
+            GameConfig {
+                pipe_gap: *end(&self.pipe_gap),
+                speed_increase_rate: *end(&self.speed_increase_rate),
+                max_speed_multiplier: *end(&self.max_speed_multiplier),
+                ..*base
+            }
+            .validate()?;
+        }
+        Ok(())
+    }
+
+    fn sample(&self, rng: &mut StdRng, base: GameConfig) -> GameConfig {
+        GameConfig {
+            pipe_gap: rng.gen_range(self.pipe_gap.clone()),
+            speed_increase_rate: rng.gen_range(self.speed_increase_rate.clone()),
+            max_speed_multiplier: rng.gen_range(self.max_speed_multiplier.clone()),
+            ..base
+        }
+    }
+
+    /// Moves each parameter by up to `scale` of its range around `from`.
+    fn perturb(&self, rng: &mut StdRng, from: GameConfig, scale: f32) -> GameConfig {
+        let mut nudge = |value: f32, range: &RangeInclusive<f32>| {
+            let span = (range.end() - range.start()) * scale;
+            (value + rng.gen_range(-span..=span)).clamp(*range.start(), *range.end())
+        };
+        GameConfig {
+            pipe_gap: nudge(from.pipe_gap, &self.pipe_gap),
+            speed_increase_rate: nudge(from.speed_increase_rate, &self.speed_increase_rate),
+            max_speed_multiplier: nudge(from.max_speed_multiplier, &self.max_speed_multiplier),
+            ..from
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TunerOptions {
+    /// Random samples before local refinement starts.
+    pub exploration: u32,
+    /// Perturbations of the best candidate after exploration.
+    pub refinement: u32,
+    /// Seed for the search itself; the evaluated courses come from `batch.base_seed`.
+    pub seed: u64,
+    pub batch: BatchOptions,
+}
+
+impl Default for TunerOptions {
+    fn default() -> Self {
+        Self {
+            exploration: 30,
+            refinement: 60,
+            seed: 0,
+            batch: BatchOptions {
+                games: 400,
+                ..BatchOptions::default()
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Evaluation {
+    pub config: GameConfig,
+    /// Sum of squared relative errors over all targets; 0 is a perfect match.
+    pub loss: f64,
+    /// Measured value for each target, in the same order.
+    pub achieved: Vec<f64>,
+}
+
+pub fn evaluate(config: GameConfig, targets: &[Target], batch: &BatchOptions) -> Evaluation {
+    let mut scores: Vec<u32> = run_batch(config, batch)
+        .iter()
+        .map(|result| result.score)
+        .collect();
+    scores.sort_unstable();
+
+    let achieved: Vec<f64> = targets
+        .iter()
+        .map(|target| target.measure(&scores))
+        .collect();
+    let loss = targets
+        .iter()
+        .zip(&achieved)
+        .map(|(target, actual)| ((actual - target.score) / target.score.max(1.0)).powi(2))
+        .sum();
+
+    Evaluation {
+        config,
+        loss,
+        achieved,
+    }
+}
+
+/// Returns the best config found. `progress` is called after every evaluation with the
+/// evaluation number and the best result so far.
+pub fn tune(
+    base: GameConfig,
+    space: &TuneSpace,
+    targets: &[Target],
+    options: &TunerOptions,
+    mut progress: impl FnMut(u32, &Evaluation),
+) -> Evaluation {
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    let mut best = evaluate(base, targets, &options.batch);
+    progress(0, &best);
+
+    let total = options.exploration + options.refinement;
+    for round in 1..=total {
+        let candidate = if round <= options.exploration {
+            space.sample(&mut rng, base)
+        } else {
+            // Shrink the search window linearly from 20% to 2% of each range
+            let t = (round - options.exploration) as f32 / options.refinement.max(1) as f32;
+            space.perturb(&mut rng, best.config, 0.2 - 0.18 * t)
+        };
+
+        let evaluation = evaluate(candidate, targets, &options.batch);
+        if evaluation.loss < best.loss {
+            best = evaluation;
+        }
+        progress(round, &best);
+    }
+
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn targets_parse_and_measure() {
+        let median: Target = "median=3".parse().unwrap();
+        assert_eq!(median.statistic, Statistic::Percentile(50.0));
+        assert_eq!(median.label(), "median");
+        let mean: Target = "mean=2.5".parse().unwrap();
+        let p90: Target = "p90=9".parse().unwrap();
+        assert_eq!(p90.label(), "p90");
+        for bad in ["median", "p101=5", "best=5", "mean=lots"] {
+            assert!(bad.parse::<Target>().is_err(), "{bad}");
+        }
+
+        let sorted = [1, 2, 3, 4, 10];
+        assert_eq!(median.measure(&sorted), 3.0);
+        assert_eq!(mean.measure(&sorted), 4.0);
+        assert_eq!(p90.measure(&sorted), 10.0);
+        assert_eq!(mean.measure(&[]), 0.0);
+    }
+
+    #[test]
+    fn spaces_must_hold_only_valid_configs() {
+        let base = GameConfig::default();
+        assert_eq!(TuneSpace::default().validate(&base), Ok(()));
+
+        let invalid = [
+            (
+                "pipe_gap",
+                TuneSpace {
+                    pipe_gap: 0.0..=200.0,
+                    ..TuneSpace::default()
+                },
+            ),
+            (
+                "pipe_gap",
+                TuneSpace {
+                    pipe_gap: 200.0..=100.0,
+                    ..TuneSpace::default()
+                },
+            ),
+            (
+                "speed_increase_rate",
+                TuneSpace {
+                    speed_increase_rate: f32::NAN..=0.1,
+                    ..TuneSpace::default()
+                },
+            ),
+            (
+                "max_speed_multiplier",
+                TuneSpace {
+                    max_speed_multiplier: 1.0..=f32::INFINITY,
+                    ..TuneSpace::default()
+                },
+            ),
+        ];
+        for (field, space) in invalid {
+            assert_eq!(space.validate(&base), Err(InvalidConfig(field)));
+        }
+    }
+
+    #[test]
+    fn tuning_stays_in_the_space_and_never_gets_worse() {
+        let targets = ["median=4".parse().unwrap()];
+        let space = TuneSpace::default();
+        let options = TunerOptions {
+            exploration: 4,
+            refinement: 4,
+            seed: 1,
+            batch: BatchOptions {
+                games: 8,
+                max_ticks: 60 * 60,
+                threads: 1,
+                ..BatchOptions::default()
+            },
+        };
+        let mut losses = Vec::new();
+        let best = tune(
+            GameConfig::default(),
+            &space,
+            &targets,
+            &options,
+            |_, best| losses.push(best.loss),
+        );
+
+        assert_eq!(losses.len(), 9);
+        assert!(losses.windows(2).all(|pair| pair[1] <= pair[0]));
+        assert_eq!(best.loss, losses[8]);
+        // The base config is evaluated as is; everything else comes from the space
+        let config = best.config;
+        assert!(
+            config == GameConfig::default()
+                || space.pipe_gap.contains(&config.pipe_gap)
+                    && space
+                        .speed_increase_rate
+                        .contains(&config.speed_increase_rate)
+                    && space
+                        .max_speed_multiplier
+                        .contains(&config.max_speed_multiplier)
+        );
+        assert_eq!(config.validate(), Ok(()));
+
+        let again = tune(GameConfig::default(), &space, &targets, &options, |_, _| {});
+        assert_eq!(again.config, config);
+    }
+}